@@ -0,0 +1,355 @@
+use super::custom_error::CustomError;
+use super::distinct::DistinctSet;
+use super::expression::Operand;
+use super::row::Row;
+use super::schema::ColumnDefinition;
+use super::value::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Las funciones de agregación soportadas: COUNT, SUM, AVG, MIN y MAX.
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    /// Retorna la función correspondiente a un nombre, sin importar mayúsculas.
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        match name.to_uppercase().as_str() {
+            "COUNT" => Some(AggregateFunction::Count),
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AggregateFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AggregateFunction::Count => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Una función de agregación aplicada a un operando que se evalúa en cada fila, como una columna o Price * Stock,
+/// o a todas las filas con COUNT(*) si operand es None. Con distinct, cada valor del operando se considera una sola vez.
+///
+/// Se muestra como FUNCTION(operand) o FUNCTION(DISTINCT operand), que es también el nombre de su columna
+/// en el resultado de un SELECT.
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub operand: Option<Box<Operand>>,
+    pub distinct: bool,
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.operand, self.distinct) {
+            (Some(operand), true) => write!(f, "{}(DISTINCT {})", self.function, operand),
+            (Some(operand), false) => write!(f, "{}({})", self.function, operand),
+            (None, _) => write!(f, "{}(*)", self.function),
+        }
+    }
+}
+
+/// El estado parcial de un Aggregate sobre las filas de un grupo, que se actualiza fila por fila sin guardarlas.
+#[derive(Debug)]
+pub struct Accumulator {
+    function: AggregateFunction,
+    count: i64,
+    /// La suma mientras todos los valores sean enteros y no haya overflow.
+    integer_sum: Option<i64>,
+    float_sum: f64,
+    /// El menor o mayor valor hasta ahora.
+    extreme: Option<Value>,
+}
+
+impl Accumulator {
+    pub fn new(function: AggregateFunction) -> Accumulator {
+        Accumulator {
+            function,
+            count: 0,
+            integer_sum: Some(0),
+            float_sum: 0.0,
+            extreme: None,
+        }
+    }
+
+    /// Agrega el valor de una fila al acumulador, o una fila sin valor en el caso de COUNT(*).
+    /// Los valores NULL no se cuentan, salvo en COUNT(*).
+    pub fn add(&mut self, column: &str, value: Option<&Value>) -> Result<(), CustomError> {
+        match value {
+            None => {
+                self.count += 1;
+                Ok(())
+            }
+            Some(Value::Null) => Ok(()),
+            Some(value) => self.accumulate(column, value.clone()),
+        }
+    }
+
+    fn accumulate(&mut self, column: &str, value: Value) -> Result<(), CustomError> {
+        self.count += 1;
+        match self.function {
+            AggregateFunction::Count => {}
+            AggregateFunction::Sum | AggregateFunction::Avg => {
+                // un Text numérico se suma como número, igual que en una operación aritmética
+                let number = match &value {
+                    Value::Text(text) => Value::from_csv(text),
+                    other => other.clone(),
+                };
+                let float = match number.as_f64() {
+                    Some(float) => float,
+                    None => {
+                        return Err(CustomError::InvalidColumn {
+                            message: format!(
+                                "Can't apply {} to non-numeric value '{}' of column {}",
+                                self.function, value, column
+                            ),
+                        })
+                    }
+                };
+                self.integer_sum = match (self.integer_sum, number) {
+                    (Some(sum), Value::Integer(integer)) => sum.checked_add(integer),
+                    _ => None,
+                };
+                self.float_sum += float;
+            }
+            AggregateFunction::Min | AggregateFunction::Max => {
+                let replace = match &self.extreme {
+                    None => true,
+                    Some(extreme) => {
+                        let ordering = value.compare(extreme);
+                        if self.function == AggregateFunction::Min {
+                            ordering.is_lt()
+                        } else {
+                            ordering.is_gt()
+                        }
+                    }
+                };
+                if replace {
+                    self.extreme = Some(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Retorna el resultado. Sin valores, COUNT es 0 y las demás funciones son NULL.
+    pub fn result(&self) -> Value {
+        match self.function {
+            AggregateFunction::Count => Value::Integer(self.count),
+            _ if self.count == 0 => Value::Null,
+            AggregateFunction::Sum => match self.integer_sum {
+                Some(sum) => Value::Integer(sum),
+                None => Value::Float(self.float_sum),
+            },
+            AggregateFunction::Avg => Value::Float(self.float_sum / self.count as f64),
+            AggregateFunction::Min | AggregateFunction::Max => {
+                self.extreme.clone().unwrap_or(Value::Null)
+            }
+        }
+    }
+}
+
+/// Los acumuladores de las funciones de agregación de cada grupo de un SELECT.
+///
+/// Los valores que ya agregó cada función con DISTINCT se recuerdan en un único DistinctSet para todos los grupos,
+/// junto con el grupo y la función a los que pertenecen, por lo que todos comparten el mismo límite de memoria
+/// y los mismos archivos temporales. Los valores que se guardaron en disco se agregan en results.
+pub struct GroupAccumulators {
+    aggregates: Vec<Aggregate>,
+    groups: Vec<Vec<Accumulator>>,
+    distinct: DistinctSet,
+}
+
+impl GroupAccumulators {
+    /// Crea los acumuladores sin ningún grupo, recordando los valores distintos en distinct.
+    pub fn new(aggregates: Vec<Aggregate>, distinct: DistinctSet) -> GroupAccumulators {
+        GroupAccumulators {
+            aggregates,
+            groups: vec![],
+            distinct,
+        }
+    }
+
+    /// Agrega un grupo sin filas, con un acumulador por función, y retorna su índice.
+    pub fn add_group(&mut self) -> usize {
+        let accumulators = self
+            .aggregates
+            .iter()
+            .map(|aggregate| Accumulator::new(aggregate.function))
+            .collect();
+        self.groups.push(accumulators);
+        self.groups.len() - 1
+    }
+
+    /// Agrega una fila a cada función del grupo, evaluando su operando en la fila.
+    /// Con DISTINCT, los valores que la función ya agregó en el grupo se descartan.
+    pub fn add_row(&mut self, group: usize, row: &Row) -> Result<(), CustomError> {
+        for (position, aggregate) in self.aggregates.iter().enumerate() {
+            let accumulator = &mut self.groups[group][position];
+            let operand = match &aggregate.operand {
+                Some(operand) => operand,
+                None => {
+                    accumulator.add("*", None)?;
+                    continue;
+                }
+            };
+            let value = row.evaluate(operand)?;
+            if aggregate.distinct && value != Value::Null {
+                let key = [group.to_string(), position.to_string(), value.to_string()];
+                if !self.distinct.insert(&key)? {
+                    continue;
+                }
+            }
+            accumulator.add(&operand.to_string(), Some(&value))?;
+        }
+        Ok(())
+    }
+
+    /// Retorna el resultado de cada función en cada grupo, en el orden en que se agregaron los grupos.
+    /// Los valores distintos que se guardaron en disco se agregan antes, interpretándolos según definitions,
+    /// que tiene una definición por función.
+    pub fn results(
+        mut self,
+        definitions: &[ColumnDefinition],
+    ) -> Result<Vec<Vec<Value>>, CustomError> {
+        for key in self.distinct.into_spilled()? {
+            let key = key?;
+            let (group, position) = match (key[0].parse::<usize>(), key[1].parse::<usize>()) {
+                (Ok(group), Ok(position)) => (group, position),
+                _ => {
+                    return Err(CustomError::GenericError {
+                        message: "Couldn't read temporary file".to_string(),
+                    })
+                }
+            };
+            let definition = &definitions[position];
+            self.groups[group][position]
+                .add(&definition.name, Some(&definition.value_from_csv(&key[2])))?;
+        }
+        Ok(self
+            .groups
+            .iter()
+            .map(|accumulators| accumulators.iter().map(Accumulator::result).collect())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn accumulate(function: AggregateFunction, values: &[&str]) -> String {
+        let mut accumulator = Accumulator::new(function);
+        for value in values {
+            accumulator
+                .add("column", Some(&Value::from_csv(value)))
+                .unwrap();
+        }
+        accumulator.result().to_string()
+    }
+
+    #[test]
+    fn test_accumulators() {
+        let values = ["2", "", "10", "3"];
+        assert_eq!(accumulate(AggregateFunction::Count, &values), "3");
+        assert_eq!(accumulate(AggregateFunction::Sum, &values), "15");
+        assert_eq!(accumulate(AggregateFunction::Avg, &values), "5.0");
+        assert_eq!(accumulate(AggregateFunction::Min, &values), "2");
+        assert_eq!(accumulate(AggregateFunction::Max, &values), "10");
+        assert_eq!(accumulate(AggregateFunction::Sum, &["1.25", "2"]), "3.25");
+        assert_eq!(accumulate(AggregateFunction::Max, &[""]), "");
+        assert!(Accumulator::new(AggregateFunction::Sum)
+            .add("column", Some(&Value::Text("abc".to_string())))
+            .is_err());
+    }
+
+    #[test]
+    fn test_group_accumulators_share_distinct_values() {
+        let directory = "test_group_accumulators_share_distinct_values";
+        std::fs::create_dir_all(directory).unwrap();
+        let sum = Aggregate {
+            function: AggregateFunction::Sum,
+            operand: Some(Box::new(Operand::Column("column".to_string()))),
+            distinct: true,
+        };
+        let count = Aggregate {
+            function: AggregateFunction::Count,
+            operand: None,
+            distinct: false,
+        };
+        // la memoria alcanza para un solo valor de todos los grupos, por lo que los demás se agregan desde el disco
+        let mut accumulators =
+            GroupAccumulators::new(vec![sum, count], DistinctSet::new(directory, 70));
+        let columns = vec!["column".to_string()];
+        for (group, value) in [
+            (0, "2"),
+            (0, "3"),
+            (1, "2"),
+            (0, "2"),
+            (1, ""),
+            (0, "3"),
+            (1, "5"),
+        ] {
+            if group == accumulators.groups.len() {
+                accumulators.add_group();
+            }
+            let values = HashMap::from([("column".to_string(), Value::from_csv(value))]);
+            accumulators
+                .add_row(group, &Row::new(&columns, values))
+                .unwrap();
+        }
+        let definitions = [ColumnDefinition::new("column"), ColumnDefinition::new("*")];
+        assert_eq!(
+            accumulators.results(&definitions).unwrap(),
+            vec![
+                vec![Value::Integer(5), Value::Integer(4)],
+                vec![Value::Integer(7), Value::Integer(3)],
+            ]
+        );
+        assert_eq!(std::fs::read_dir(directory).unwrap().count(), 0);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_aggregate_names() {
+        let count = Aggregate {
+            function: AggregateFunction::Count,
+            operand: None,
+            distinct: false,
+        };
+        assert_eq!(count.to_string(), "COUNT(*)");
+        let sum = Aggregate {
+            function: AggregateFunction::Sum,
+            operand: Some(Box::new(Operand::Arithmetic {
+                left: Box::new(Operand::Column("Price".to_string())),
+                operator: '*',
+                right: Box::new(Operand::Column("Stock".to_string())),
+            })),
+            distinct: false,
+        };
+        assert_eq!(sum.to_string(), "SUM(Price * Stock)");
+        let distinct = Aggregate {
+            function: AggregateFunction::Count,
+            operand: Some(Box::new(Operand::Column("Category".to_string()))),
+            distinct: true,
+        };
+        assert_eq!(distinct.to_string(), "COUNT(DISTINCT Category)");
+    }
+}
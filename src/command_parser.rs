@@ -1,40 +1,74 @@
 use super::custom_error::CustomError;
-use super::expression::Expression;
-use super::expression_parser::parse_expression;
+use super::expression::{Expression, Operand};
+use super::expression_parser::{
+    is_literal, name_of, parse_aggregate, parse_expression, parse_literal, parse_operand,
+    parse_value,
+};
+use super::schema::{ColumnDefinition, ColumnType};
+use super::statement::{
+    AlterOperation, Join, JoinType, Limit, OrderBy, SelectColumn, SortDirection, Statement,
+};
 use super::tokenizer::Token;
 use std::collections::HashMap;
 use std::iter::Peekable;
 use std::slice::Iter;
 
+/// Parsea un comando SQL que llega en forma de vector de tokens, retornando el Statement correspondiente.
+/// El tipo de comando se determina por su primer token.
+pub fn parse_statement(tokens: &[Token]) -> Result<Statement, CustomError> {
+    if let Some(Token::Keyword(keyword)) = tokens.first() {
+        match keyword.to_uppercase().as_str() {
+            "INSERT" => parse_insert(tokens),
+            "UPDATE" => parse_update(tokens),
+            "DELETE" => parse_delete(tokens),
+            "SELECT" => parse_select(tokens),
+            "CREATE" => parse_create_table(tokens),
+            "DROP" => parse_drop_table(tokens),
+            "ALTER" => parse_alter_table(tokens),
+            "BEGIN" | "COMMIT" | "ROLLBACK" => parse_transaction_control(tokens),
+            other => Err(CustomError::InvalidSyntax {
+                message: format!("Invalid command: {}", other),
+            }),
+        }
+    } else {
+        Err(CustomError::InvalidSyntax {
+            message: "Usage: <COMMAND> <...>".to_string(),
+        })
+    }
+}
+
 /// Parsea un comando INSERT que llega en forma de vector de tokens.
-/// Modifica los parametros table_name, columns y values.
+/// Retorna un Statement::Insert con el nombre de la tabla, las columnas y los valores a insertar.
 ///
 /// El formato del comando INSERT esperado es:
 /// INSERT INTO <table_name> (<column1>, <column2>, ...) VALUES (<value1>, <value2>, ...);
-pub fn parse_insert(
-    tokens: &[Token],
-    table_name: &mut String,
-    columns: &mut Vec<String>,
-    values: &mut Vec<HashMap<String, String>>,
-) -> Result<(), CustomError> {
+pub fn parse_insert(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let mut table_name = String::new();
+    let mut columns = vec![];
+    let mut values = vec![];
     let mut iter = tokens.iter().peekable();
     iter.next(); // salteo el INSERT
-    parse_insert_into(&mut iter, table_name)?;
-    parse_insert_into_columns(columns, &mut iter)?;
-    parse_insert_values(values, &mut iter, columns)?;
+    parse_insert_into(&mut iter, &mut table_name)?;
+    parse_column_names(&mut columns, &mut iter)?;
+    parse_insert_values(&mut values, &mut iter, &columns)?;
     check_ending_with_semicolon(&mut iter)?;
-    Ok(())
+    Ok(Statement::Insert {
+        table_name,
+        columns,
+        values,
+    })
 }
 
 fn parse_insert_into(
     iter: &mut Peekable<Iter<Token>>,
     table_name: &mut String,
 ) -> Result<(), CustomError> {
-    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.as_str() == "INTO") {
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("INTO"))
+    {
         // Verifico que haya INTO
         return CustomError::error_invalid_syntax("Expected INTO after INSERT");
     }
-    if let Some(Token::Identifier(name)) | Some(Token::String(name)) = iter.next() {
+    if let Some(name) = iter.next().and_then(name_of) {
         // Verifico que haya nombre de tabla
         *table_name = name.to_string();
     } else {
@@ -43,7 +77,7 @@ fn parse_insert_into(
     Ok(())
 }
 
-fn parse_insert_into_columns(
+fn parse_column_names(
     columns: &mut Vec<String>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
@@ -52,7 +86,7 @@ fn parse_insert_into_columns(
         while let Some(token) = iter.next() {
             // Este ciclo termina al encontrar un ')'
             match token {
-                Token::Identifier(name) | Token::String(name) => {
+                Token::Identifier(name) | Token::String(name) | Token::Keyword(name) => {
                     // Si es nombre de columna, lo agrego
                     columns.push(name.to_string());
                     if let Some(Token::Symbol(')')) | Some(Token::Symbol(',')) = iter.peek() {
@@ -64,8 +98,7 @@ fn parse_insert_into_columns(
                 }
                 Token::Symbol(',') => {
                     // Si es coma, verifico que su siguiente sea nombre de columna
-                    if let Some(Token::Identifier(_)) | Some(Token::String(_)) = iter.peek() {
-                    } else {
+                    if iter.peek().and_then(|token| name_of(token)).is_none() {
                         return CustomError::error_invalid_syntax("Expected column name after ','");
                     }
                 }
@@ -88,11 +121,12 @@ fn parse_insert_into_columns(
 }
 
 fn parse_insert_values(
-    values: &mut Vec<HashMap<String, String>>,
+    values: &mut Vec<HashMap<String, Operand>>,
     iter: &mut Peekable<Iter<Token>>,
     columns: &[String],
 ) -> Result<(), CustomError> {
-    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.as_str() == "VALUES") {
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("VALUES"))
+    {
         // Verifico que haya VALUES
         return CustomError::error_invalid_syntax("Expected VALUES after column names");
     }
@@ -106,18 +140,18 @@ fn parse_insert_values(
 }
 
 fn parse_insert_value(
-    values: &mut Vec<HashMap<String, String>>,
+    values: &mut Vec<HashMap<String, Operand>>,
     iter: &mut Peekable<Iter<Token>>,
     columns: &[String],
 ) -> Result<(), CustomError> {
-    let mut row: HashMap<String, String> = HashMap::new(); // Hashmap de un VALUE para devolver: columna -> valor
+    let mut row: HashMap<String, Operand> = HashMap::new(); // Hashmap de un VALUE para devolver: columna -> valor
     if let Some(Token::Symbol('(')) = iter.next() {
         // Verifico que se abra parentesis
         let mut column_index = 0; // Indice de la columna actual
         while let Some(token) = iter.next() {
             // Este ciclo termina al encontrar un ')'
             match token {
-                Token::Integer(_) | Token::String(_) => {
+                token if is_literal(token) => {
                     // Si es un valor, lo agrego al hashmap
                     if let Some(Token::Symbol(')')) | Some(Token::Symbol(',')) = iter.peek() {
                     } else {
@@ -129,18 +163,13 @@ fn parse_insert_value(
                         // Si hay mas valores que columnas, devuelvo error
                         return CustomError::error_invalid_syntax("Too many values for columns");
                     }
-                    let value = match token {
-                        Token::Integer(int) => int.to_string(),
-                        Token::String(string) => string.to_string(),
-                        _ => return CustomError::error_invalid_syntax("Expected value after '('"),
-                    };
+                    let value = parse_value(token)?;
                     row.insert(columns[column_index].to_string(), value); // Agrego el valor de la columna[i] al hashmap
                     column_index += 1;
                 }
                 Token::Symbol(',') => {
                     // Si es coma, verifico que su siguiente sea un valor
-                    if let Some(Token::Integer(_)) | Some(Token::String(_)) = iter.peek() {
-                    } else {
+                    if !matches!(iter.peek(), Some(token) if is_literal(token)) {
                         return CustomError::error_invalid_syntax("Expected value after ','");
                     }
                 }
@@ -158,6 +187,309 @@ fn parse_insert_value(
     Ok(())
 }
 
+/// Parsea un comando CREATE TABLE que llega en forma de vector de tokens.
+/// Retorna un Statement::CreateTable con el nombre de la tabla y sus columnas.
+///
+/// El formato del comando CREATE TABLE esperado es:
+/// CREATE TABLE IF NOT EXISTS <table_name> (<column definition 1>, <column definition 2>, ...);
+/// donde IF NOT EXISTS es opcional y cada definición tiene el formato de parse_column_definition.
+pub fn parse_create_table(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let mut columns = vec![];
+    let mut iter = tokens.iter().peekable();
+    iter.next(); // salteo el CREATE
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("TABLE"))
+    {
+        // Verifico que haya TABLE
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected TABLE after CREATE".to_string(),
+        });
+    }
+    let if_not_exists = parse_if_exists(&mut iter, true)?;
+    let table_name = parse_name(&mut iter, "Expected table name after TABLE")?;
+    if !matches!(iter.next(), Some(Token::Symbol('('))) {
+        // Verifico que se abra parentesis
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected '(' after table name".to_string(),
+        });
+    }
+    columns.push(parse_column_definition(&mut iter)?); // Parseo la primera columna
+    while let Some(Token::Symbol(',')) = iter.peek() {
+        // Si lo sigue una coma, parseo otra columna
+        iter.next();
+        columns.push(parse_column_definition(&mut iter)?);
+    }
+    if !matches!(iter.next(), Some(Token::Symbol(')'))) {
+        // Verifico que se cierre parentesis
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected ',' or ')' after column definition".to_string(),
+        });
+    }
+    for (index, column) in columns.iter().enumerate() {
+        if columns[..index]
+            .iter()
+            .any(|other| other.name == column.name)
+        {
+            // Verifico que no haya columnas repetidas
+            return Err(CustomError::InvalidColumn {
+                message: format!("Duplicated column: {}", column.name),
+            });
+        }
+    }
+    if columns.iter().filter(|column| column.primary_key).count() > 1 {
+        // Una tabla puede tener una sola clave primaria
+        return Err(CustomError::InvalidSyntax {
+            message: "Multiple primary keys".to_string(),
+        });
+    }
+    check_ending_with_semicolon(&mut iter)?;
+    Ok(Statement::CreateTable {
+        table_name,
+        columns,
+        if_not_exists,
+    })
+}
+
+/// Parsea un comando DROP TABLE que llega en forma de vector de tokens.
+/// Retorna un Statement::DropTable con el nombre de la tabla.
+///
+/// El formato del comando DROP TABLE esperado es:
+/// DROP TABLE IF EXISTS <table_name>;
+/// donde IF EXISTS es opcional.
+pub fn parse_drop_table(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let mut iter = tokens.iter().peekable();
+    iter.next(); // salteo el DROP
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("TABLE"))
+    {
+        // Verifico que haya TABLE
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected TABLE after DROP".to_string(),
+        });
+    }
+    let if_exists = parse_if_exists(&mut iter, false)?;
+    let table_name = parse_name(&mut iter, "Expected table name after TABLE")?;
+    check_ending_with_semicolon(&mut iter)?;
+    Ok(Statement::DropTable {
+        table_name,
+        if_exists,
+    })
+}
+
+/// Parsea un comando de control de transacciones que llega en forma de vector de tokens.
+/// Retorna un Statement::Begin, Statement::Commit o Statement::Rollback.
+///
+/// Los formatos esperados son:
+/// BEGIN TRANSACTION;
+/// COMMIT TRANSACTION;
+/// ROLLBACK TRANSACTION;
+/// donde TRANSACTION es opcional.
+pub fn parse_transaction_control(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let mut iter = tokens.iter().peekable();
+    let statement = match iter.next() {
+        Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("BEGIN") => Statement::Begin,
+        Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("COMMIT") => {
+            Statement::Commit
+        }
+        Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("ROLLBACK") => {
+            Statement::Rollback
+        }
+        _ => {
+            return Err(CustomError::InvalidSyntax {
+                message: "Expected BEGIN, COMMIT or ROLLBACK".to_string(),
+            })
+        }
+    };
+    skip_keyword(&mut iter, "TRANSACTION");
+    check_ending_with_semicolon(&mut iter)?;
+    Ok(statement)
+}
+
+/// Parsea un comando ALTER TABLE que llega en forma de vector de tokens.
+/// Retorna un Statement::AlterTable con el nombre de la tabla y la operación a realizar.
+///
+/// Los formatos del comando ALTER TABLE esperados son:
+/// ALTER TABLE <table_name> ADD COLUMN <column definition>;
+/// ALTER TABLE <table_name> DROP COLUMN <column>;
+/// ALTER TABLE <table_name> RENAME COLUMN <from> TO <to>;
+/// ALTER TABLE <table_name> RENAME TO <new_name>;
+/// donde COLUMN es opcional y la definición tiene el formato de parse_column_definition.
+pub fn parse_alter_table(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let mut iter = tokens.iter().peekable();
+    iter.next(); // salteo el ALTER
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("TABLE"))
+    {
+        // Verifico que haya TABLE
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected TABLE after ALTER".to_string(),
+        });
+    }
+    let table_name = parse_name(&mut iter, "Expected table name after TABLE")?;
+    let operation = match iter.next() {
+        Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("ADD") => {
+            skip_keyword(&mut iter, "COLUMN");
+            let column = parse_column_definition(&mut iter)?;
+            AlterOperation::AddColumn { column }
+        }
+        Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("DROP") => {
+            skip_keyword(&mut iter, "COLUMN");
+            let column = parse_name(&mut iter, "Expected column name after DROP")?;
+            AlterOperation::DropColumn { column }
+        }
+        Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("RENAME") => {
+            if skip_keyword(&mut iter, "TO") {
+                let new_name = parse_name(&mut iter, "Expected table name after TO")?;
+                AlterOperation::RenameTable { new_name }
+            } else {
+                skip_keyword(&mut iter, "COLUMN");
+                let from = parse_name(&mut iter, "Expected column name after RENAME")?;
+                if !skip_keyword(&mut iter, "TO") {
+                    return Err(CustomError::InvalidSyntax {
+                        message: "Expected TO after column name".to_string(),
+                    });
+                }
+                let to = parse_name(&mut iter, "Expected column name after TO")?;
+                AlterOperation::RenameColumn { from, to }
+            }
+        }
+        _ => {
+            return Err(CustomError::InvalidSyntax {
+                message: "Expected ADD, DROP or RENAME after table name".to_string(),
+            })
+        }
+    };
+    check_ending_with_semicolon(&mut iter)?;
+    Ok(Statement::AlterTable {
+        table_name,
+        operation,
+    })
+}
+
+/// Parsea la definición de una columna, usada por CREATE TABLE, ALTER TABLE y los archivos de esquema.
+///
+/// El formato de la definición esperado es:
+/// <column> <type> NOT NULL DEFAULT <value> PRIMARY KEY UNIQUE
+/// donde el tipo puede ser INTEGER, REAL, TEXT, BOOLEAN o DATE, y tanto el tipo como las restricciones son opcionales.
+pub fn parse_column_definition(
+    iter: &mut Peekable<Iter<Token>>,
+) -> Result<ColumnDefinition, CustomError> {
+    let name = parse_name(iter, "Expected column name")?;
+    let mut definition = ColumnDefinition::new(&name);
+    if let Some(Token::Identifier(type_name)) = iter.peek() {
+        // Si hay un identificador, debe ser el tipo de la columna
+        if let Some(column_type) = ColumnType::from_name(type_name) {
+            definition.column_type = Some(column_type);
+            iter.next();
+        } else {
+            return Err(CustomError::InvalidSyntax {
+                message: format!("Invalid column type: {}", type_name),
+            });
+        }
+    }
+    loop {
+        // Este ciclo termina cuando no quedan restricciones por parsear
+        match iter.peek() {
+            Some(Token::LogicalOperator(operator)) if operator.as_str() == "NOT" => {
+                iter.next();
+                if !skip_keyword(iter, "NULL") {
+                    return Err(CustomError::InvalidSyntax {
+                        message: "Expected NULL after NOT".to_string(),
+                    });
+                }
+                definition.not_null = true;
+            }
+            Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("DEFAULT") => {
+                iter.next();
+                match iter.next() {
+                    Some(Token::Parameter(parameter)) => {
+                        return Err(CustomError::InvalidSyntax {
+                            message: format!("Parameter {} can't be used as DEFAULT", parameter),
+                        })
+                    }
+                    Some(token) if is_literal(token) => {
+                        definition.default = Some(parse_literal(token)?)
+                    }
+                    _ => {
+                        return Err(CustomError::InvalidSyntax {
+                            message: "Expected value after DEFAULT".to_string(),
+                        })
+                    }
+                }
+            }
+            Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("PRIMARY") => {
+                iter.next();
+                if !skip_keyword(iter, "KEY") {
+                    return Err(CustomError::InvalidSyntax {
+                        message: "Expected KEY after PRIMARY".to_string(),
+                    });
+                }
+                definition.primary_key = true;
+            }
+            Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("UNIQUE") => {
+                iter.next();
+                definition.unique = true;
+            }
+            _ => break,
+        }
+    }
+    if let Some(default) = &definition.default {
+        // Verifico que el valor por defecto cumpla con la definición
+        definition.validate(&default.to_string())?;
+    }
+    Ok(definition)
+}
+
+/// Si el siguiente token es el keyword dado, lo consume y retorna true.
+fn skip_keyword(iter: &mut Peekable<Iter<Token>>, expected: &str) -> bool {
+    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case(expected))
+    {
+        iter.next();
+        return true;
+    }
+    false
+}
+
+fn parse_if_exists(iter: &mut Peekable<Iter<Token>>, negated: bool) -> Result<bool, CustomError> {
+    let mut lookahead = iter.clone();
+    if !matches!(lookahead.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("IF"))
+        || !matches!(
+            lookahead.next(),
+            Some(Token::LogicalOperator(_)) | Some(Token::Keyword(_))
+        )
+    {
+        // Si no hay IF seguido de NOT o EXISTS, la clausula no está presente y IF es el nombre de la tabla
+        return Ok(false);
+    }
+    iter.next();
+    if negated
+        && !matches!(iter.next(), Some(Token::LogicalOperator(operator)) if operator.as_str() == "NOT")
+    {
+        // Verifico que haya NOT
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected NOT after IF".to_string(),
+        });
+    }
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("EXISTS"))
+    {
+        // Verifico que haya EXISTS
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected EXISTS after IF".to_string(),
+        });
+    }
+    Ok(true)
+}
+
+fn parse_name(
+    iter: &mut Peekable<Iter<Token>>,
+    error_message: &str,
+) -> Result<String, CustomError> {
+    if let Some(name) = iter.next().and_then(name_of) {
+        Ok(name.to_string())
+    } else {
+        Err(CustomError::InvalidSyntax {
+            message: error_message.to_string(),
+        })
+    }
+}
+
 fn check_ending_with_semicolon(iter: &mut Peekable<Iter<Token>>) -> Result<(), CustomError> {
     if let Some(Token::Symbol(';')) = iter.next() {
         if iter.peek().is_some() {
@@ -170,36 +502,41 @@ fn check_ending_with_semicolon(iter: &mut Peekable<Iter<Token>>) -> Result<(), C
 }
 
 /// Parsea un comando UPDATE que llega en forma de vector de tokens.
-/// Modifica los parametros table_name, set_values y condition.
+/// Retorna un Statement::Update con el nombre de la tabla, los valores a asignar y la condición.
 ///
 /// El formato del comando UPDATE esperado es:
 /// UPDATE <table_name> SET <column1> = <value1>, <column2> = <value2>, ... WHERE <condition>;
 /// donde WHERE es opcional.
-pub fn parse_update(
-    tokens: &[Token],
-    table_name: &mut String,
-    set_values: &mut HashMap<String, String>,
-    condition: &mut Expression,
-) -> Result<(), CustomError> {
+pub fn parse_update(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let table_name: String;
+    let mut set_values = HashMap::new();
+    let mut condition = Expression::True;
     let mut iter = tokens.iter().peekable();
     iter.next(); // salteo el UPDATE
-    if let Some(Token::Identifier(name)) | Some(Token::String(name)) = iter.next() {
+    if let Some(name) = iter.next().and_then(name_of) {
         // Verifico que haya nombre de tabla
-        *table_name = name.to_string();
+        table_name = name.to_string();
     } else {
-        return CustomError::error_invalid_syntax("Expected table name after UPDATE");
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected table name after UPDATE".to_string(),
+        });
     }
-    parse_update_set_values(set_values, &mut iter)?;
-    parse_condition(condition, &mut iter)?;
+    parse_update_set_values(&mut set_values, &mut iter)?;
+    parse_condition(&mut condition, &mut iter)?;
     check_ending_with_semicolon(&mut iter)?;
-    Ok(())
+    Ok(Statement::Update {
+        table_name,
+        set_values,
+        condition,
+    })
 }
 
 fn parse_update_set_values(
-    set_values: &mut HashMap<String, String>,
+    set_values: &mut HashMap<String, Operand>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
-    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.as_str() == "SET") {
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("SET"))
+    {
         // Verifico que haya SET
         return CustomError::error_invalid_syntax("Expected SET after table name");
     }
@@ -213,12 +550,12 @@ fn parse_update_set_values(
 }
 
 fn parse_update_set_value(
-    set_values: &mut HashMap<String, String>,
+    set_values: &mut HashMap<String, Operand>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
     let column: String;
-    let value: String;
-    if let Some(Token::Identifier(name)) | Some(Token::String(name)) = iter.next() {
+    let value: Operand;
+    if let Some(name) = iter.next().and_then(name_of) {
         // Verifico que haya nombre de columna
         column = name.to_string();
     } else {
@@ -226,9 +563,9 @@ fn parse_update_set_value(
     }
     if matches!(iter.next(), Some(Token::ComparisonOperator(keyword)) if keyword.as_str() == "=") {
         // Verifico que haya '='
-        if let Some(Token::Integer(string)) | Some(Token::String(string)) = iter.next() {
+        if let Some(token) = iter.next().filter(|token| is_literal(token)) {
             // Verifico que haya valor
-            value = string.to_string();
+            value = parse_value(token)?;
         } else {
             return CustomError::error_invalid_syntax("Expected value after '='");
         }
@@ -245,67 +582,170 @@ fn parse_condition(
 ) -> Result<(), CustomError> {
     if let Some(Token::Keyword(keyword)) = iter.peek() {
         // Verifico que haya WHERE
-        if keyword.as_str() == "WHERE" {
+        if keyword.eq_ignore_ascii_case("WHERE") {
             iter.next();
             *condition = parse_expression(iter)?; // Parseo la condicion
+            if !condition.aggregates().is_empty() {
+                return CustomError::error_invalid_syntax(
+                    "Aggregate functions are not allowed in WHERE",
+                );
+            }
         }
     }
     Ok(())
 }
 
 /// Parsea un comando DELETE que llega en forma de vector de tokens.
-/// Modifica los parametros table_name y condition.
+/// Retorna un Statement::Delete con el nombre de la tabla y la condición.
 ///
 /// El formato del comando DELETE esperado es:
-/// DELETE <table_name> WHERE <condition>;
+/// DELETE FROM <table_name> WHERE <condition>;
 /// donde WHERE es opcional.
-pub fn parse_delete(
-    tokens: &[Token],
-    table_name: &mut String,
-    condition: &mut Expression,
-) -> Result<(), CustomError> {
+pub fn parse_delete(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let table_name: String;
+    let mut condition = Expression::True;
     let mut iter = tokens.iter().peekable();
     iter.next(); // salteo el DELETE
-    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.as_str() == "FROM") {
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("FROM"))
+    {
         // Verifico que haya FROM
-        return CustomError::error_invalid_syntax("Expected FROM after DELETE");
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected FROM after DELETE".to_string(),
+        });
     }
-    if let Some(Token::Identifier(name)) | Some(Token::String(name)) = iter.next() {
+    if let Some(name) = iter.next().and_then(name_of) {
         // Verifico que haya nombre de tabla
-        *table_name = name.to_string();
+        table_name = name.to_string();
     } else {
-        return CustomError::error_invalid_syntax("Expected table name after DELETE");
+        return Err(CustomError::InvalidSyntax {
+            message: "Expected table name after DELETE".to_string(),
+        });
     }
-    parse_condition(condition, &mut iter)?;
+    parse_condition(&mut condition, &mut iter)?;
     check_ending_with_semicolon(&mut iter)?;
-    Ok(())
+    Ok(Statement::Delete {
+        table_name,
+        condition,
+    })
 }
 
 /// Parsea un comando SELECT que llega en forma de vector de tokens.
-/// Modifica los parametros columns, table_name, condition y order_by.
+/// Retorna un Statement::Select con las columnas, el nombre de la tabla, la condición, la agrupación y el orden.
 ///
 /// El formato del comando SELECT esperado es:
-/// SELECT <column1>, <column2>, ... FROM <table_name> WHERE <condition> ORDER BY <column> <order>, <column> <order>, ... ;
-/// donde WHERE y ORDER BY son opcionales.
-pub fn parse_select(
-    tokens: &[Token],
-    columns: &mut Vec<String>,
-    table_name: &mut String,
-    condition: &mut Expression,
-    order_by: &mut Vec<(String, String)>,
-) -> Result<(), CustomError> {
+/// SELECT DISTINCT <column1>, <column2>, ... FROM <table_name> [AS] <alias> <join> ... WHERE <condition>
+/// GROUP BY <column1>, <column2>, ... HAVING <condition> ORDER BY <column> <order>, <column> <order>, ...
+/// LIMIT <count> OFFSET <offset>;
+/// donde DISTINCT, el alias, los joins, WHERE, GROUP BY, HAVING, ORDER BY, LIMIT y OFFSET son opcionales, y OFFSET solo puede seguir a LIMIT. Las columnas pueden ser funciones de agregación
+/// como COUNT(*), COUNT(<column>), SUM(<column>), AVG(<column>), MIN(<column>) o MAX(<column>), que también pueden usarse
+/// en HAVING y ORDER BY. Dentro de una función, DISTINCT <column> considera cada valor una sola vez.
+///
+/// Cada columna es una expresión, como Price * Stock, 'texto' o UPPER(name), seguida opcionalmente de AS <alias>.
+/// El alias es el nombre de la columna en el resultado, y puede usarse en ORDER BY.
+///
+/// Cada join tiene la forma [INNER | LEFT [OUTER] | RIGHT [OUTER] | CROSS] JOIN <table_name> [AS] <alias> ON <condition>,
+/// donde el alias es opcional y CROSS JOIN no lleva condición.
+pub fn parse_select(tokens: &[Token]) -> Result<Statement, CustomError> {
+    let mut columns = vec![];
+    let mut table_name = String::new();
+    let mut table_alias = None;
+    let mut joins = vec![];
+    let mut condition = Expression::True;
+    let mut group_by = vec![];
+    let mut having = Box::new(Expression::True);
+    let mut order_by = vec![];
+    let mut limit = None;
     let mut iter = tokens.iter().peekable();
     iter.next(); // salteo el SELECT
-    parse_select_columns(columns, &mut iter)?;
-    parse_select_from(table_name, &mut iter)?;
-    parse_condition(condition, &mut iter)?;
-    parse_order_by(order_by, &mut iter)?;
+    let distinct = matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("DISTINCT"));
+    if distinct {
+        iter.next();
+    }
+    parse_select_columns(&mut columns, &mut iter)?;
+    parse_select_from(&mut table_name, &mut table_alias, &mut iter)?;
+    parse_joins(&mut joins, &mut iter)?;
+    check_table_qualifiers(&table_name, &table_alias, &joins)?;
+    parse_condition(&mut condition, &mut iter)?;
+    parse_group_by(&mut group_by, &mut having, &mut iter)?;
+    parse_order_by(&mut order_by, &mut iter)?;
+    parse_limit(&mut limit, &mut iter)?;
     check_ending_with_semicolon(&mut iter)?;
+    let statement = Statement::Select {
+        distinct,
+        columns,
+        table_name,
+        table_alias,
+        joins,
+        condition,
+        group_by,
+        having,
+        order_by,
+        limit,
+    };
+    if let Statement::Select {
+        columns, group_by, ..
+    } = &statement
+    {
+        if statement.is_aggregate() {
+            check_grouped_columns(columns, group_by)?;
+        }
+    }
+    Ok(statement)
+}
+
+/// Verifica que en un SELECT con agrupación cada columna consultada fuera de una función de agregación
+/// sea una de las columnas de GROUP BY.
+fn check_grouped_columns(
+    columns: &[SelectColumn],
+    group_by: &[Operand],
+) -> Result<(), CustomError> {
+    if columns.is_empty() {
+        return CustomError::error_invalid_syntax("Can't select * with GROUP BY or aggregates");
+    }
+    for column in columns {
+        for name in column.operand.columns() {
+            if !group_by.contains(&Operand::Column(name.to_string())) {
+                CustomError::error_invalid_syntax(&format!(
+                    "Column {} must appear in GROUP BY or be used in an aggregate function",
+                    name
+                ))?;
+            }
+        }
+    }
     Ok(())
 }
 
+/// Indica si el siguiente token termina la lista de columnas, que siempre es seguida por FROM.
+/// Las demás palabras clave pueden ser nombres de columnas, como key.
+fn ends_select_columns(iter: &mut Peekable<Iter<Token>>) -> bool {
+    match iter.peek() {
+        Some(Token::Keyword(keyword)) => keyword.eq_ignore_ascii_case("FROM"),
+        Some(_) => false,
+        None => true,
+    }
+}
+
+/// Retorna el nombre de la columna si la siguiente columna del SELECT es solo un String, que como en ORDER BY o SET
+/// es el nombre de una columna, para poder usar columnas con espacios. Un String dentro de una expresión es un literal.
+fn quoted_column(iter: &Peekable<Iter<Token>>) -> Option<String> {
+    let mut lookahead = iter.clone();
+    let name = match lookahead.next() {
+        Some(Token::String(name)) => name,
+        _ => return None,
+    };
+    match lookahead.peek() {
+        None | Some(Token::Symbol(',')) => Some(name.to_string()),
+        Some(Token::Keyword(keyword))
+            if keyword.eq_ignore_ascii_case("AS") || keyword.eq_ignore_ascii_case("FROM") =>
+        {
+            Some(name.to_string())
+        }
+        _ => None,
+    }
+}
+
 fn parse_select_columns(
-    columns: &mut Vec<String>,
+    columns: &mut Vec<SelectColumn>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
     if matches!(iter.peek(), Some(Token::Symbol('*'))) {
@@ -313,61 +753,227 @@ fn parse_select_columns(
         iter.next();
         return Ok(());
     }
-    while let Some(token) = iter.peek() {
-        // Este ciclo termina al encontrar un Keyword
-        match token {
-            Token::Identifier(name) | Token::String(name) => {
-                // Si es nombre de columna, lo agrego
-                columns.push(name.to_string());
+    while !ends_select_columns(iter) {
+        // Este ciclo termina al encontrar FROM
+        let operand = match quoted_column(iter) {
+            Some(name) => {
                 iter.next();
+                Operand::Column(name)
             }
-            Token::Keyword(_) => {
-                // Si es Keyword, termino
-                break;
+            None => parse_operand(iter)?,
+        };
+        let mut alias = None;
+        if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("AS"))
+        {
+            // Si la sigue AS, el nombre que sigue es el alias de la columna
+            iter.next();
+            match iter.next().and_then(name_of) {
+                Some(name) => alias = Some(name.to_string()),
+                None => return CustomError::error_invalid_syntax("Expected column alias after AS"),
             }
-            Token::Symbol(',') => {
-                // Si es coma, verifico que su siguiente sea nombre de columna
-                iter.next();
-                if let Some(Token::Identifier(_)) | Some(Token::String(_)) = iter.peek() {
-                } else {
-                    return CustomError::error_invalid_syntax("Expected column name after ','");
-                }
+        }
+        columns.push(SelectColumn { operand, alias });
+        if let Some(Token::Symbol(',')) = iter.peek() {
+            // Si es coma, verifico que la siga otra columna
+            iter.next();
+            if ends_select_columns(iter) {
+                return CustomError::error_invalid_syntax("Expected column name after ','");
             }
-            _ => {
+        } else if !ends_select_columns(iter) {
+            return CustomError::error_invalid_syntax(
+                "Expected column name or FROM <tablename> after column names",
+            );
+        }
+    }
+    Ok(())
+}
+
+fn parse_group_by(
+    group_by: &mut Vec<Operand>,
+    having: &mut Box<Expression>,
+    iter: &mut Peekable<Iter<Token>>,
+) -> Result<(), CustomError> {
+    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("GROUP"))
+    {
+        // Verifico que haya GROUP BY
+        iter.next();
+        if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("BY"))
+        {
+            return CustomError::error_invalid_syntax("Expected BY after GROUP");
+        }
+        loop {
+            if let Some(name) = iter.next().and_then(name_of) {
+                group_by.push(Operand::Column(name.to_string()));
+            } else {
                 return CustomError::error_invalid_syntax(
-                    "Expected column name or FROM <tablename> after column names",
+                    "Expected column name after GROUP BY or ','",
                 );
             }
+            if let Some(Token::Symbol(',')) = iter.peek() {
+                // Si lo sigue una coma, parseo otra columna
+                iter.next();
+            } else {
+                break;
+            }
         }
     }
+    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("HAVING"))
+    {
+        // HAVING filtra los grupos, por lo que puede usar funciones de agregación
+        iter.next();
+        **having = parse_expression(iter)?;
+    }
     Ok(())
 }
 
 fn parse_select_from(
     table_name: &mut String,
+    table_alias: &mut Option<String>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
-    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.as_str() == "FROM") {
+    if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("FROM"))
+    {
         // Verifico que haya FROM
         return CustomError::error_invalid_syntax("Expected FROM after column names");
     }
-    if let Some(Token::Identifier(name)) | Some(Token::String(name)) = iter.next() {
+    if let Some(name) = iter.next().and_then(name_of) {
         // Verifico que haya nombre de tabla
         *table_name = name.to_string();
     } else {
         return CustomError::error_invalid_syntax("Expected table name after FROM");
     }
+    *table_alias = parse_table_alias(iter)?;
     Ok(())
 }
 
+/// Parsea el alias opcional que sigue al nombre de una tabla, con o sin AS.
+fn parse_table_alias(iter: &mut Peekable<Iter<Token>>) -> Result<Option<String>, CustomError> {
+    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("AS")) {
+        iter.next();
+        if let Some(alias) = iter.next().and_then(name_of) {
+            return Ok(Some(alias.to_string()));
+        }
+        CustomError::error_invalid_syntax("Expected alias after AS")?;
+    }
+    if let Some(Token::Identifier(alias)) = iter.peek() {
+        iter.next();
+        return Ok(Some(alias.to_string()));
+    }
+    Ok(None)
+}
+
+fn parse_joins(joins: &mut Vec<Join>, iter: &mut Peekable<Iter<Token>>) -> Result<(), CustomError> {
+    while let Some(Token::Keyword(keyword)) = iter.peek() {
+        let join_type = match keyword.to_uppercase().as_str() {
+            "JOIN" | "INNER" => JoinType::Inner,
+            "LEFT" => JoinType::Left,
+            "RIGHT" => JoinType::Right,
+            "CROSS" => JoinType::Cross,
+            _ => break, // Si no es un join, termino
+        };
+        if !keyword.eq_ignore_ascii_case("JOIN") {
+            iter.next();
+            if matches!(join_type, JoinType::Left | JoinType::Right)
+                && matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("OUTER"))
+            {
+                iter.next();
+            }
+        }
+        if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("JOIN"))
+        {
+            return CustomError::error_invalid_syntax("Expected JOIN");
+        }
+        let table_name = match iter.next().and_then(name_of) {
+            Some(name) => name.to_string(),
+            None => return CustomError::error_invalid_syntax("Expected table name after JOIN"),
+        };
+        let alias = parse_table_alias(iter)?;
+        let mut condition = Expression::True;
+        if join_type != JoinType::Cross {
+            // Salvo CROSS JOIN, los joins necesitan una condición
+            if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("ON"))
+            {
+                return CustomError::error_invalid_syntax("Expected ON after JOIN table");
+            }
+            condition = parse_expression(iter)?;
+            if !condition.aggregates().is_empty() {
+                return CustomError::error_invalid_syntax(
+                    "Aggregate functions are not allowed in ON",
+                );
+            }
+        }
+        joins.push(Join {
+            join_type,
+            table_name,
+            alias,
+            condition,
+        });
+    }
+    Ok(())
+}
+
+/// Verifica que cada tabla de un SELECT se califique con un nombre distinto, usando alias para unir una tabla consigo misma.
+fn check_table_qualifiers(
+    table_name: &str,
+    table_alias: &Option<String>,
+    joins: &[Join],
+) -> Result<(), CustomError> {
+    let mut qualifiers = vec![table_alias.as_deref().unwrap_or(table_name)];
+    for join in joins {
+        if qualifiers.contains(&join.qualifier()) {
+            CustomError::error_invalid_syntax(&format!(
+                "Table name {} is used more than once, use an alias",
+                join.qualifier()
+            ))?;
+        }
+        qualifiers.push(join.qualifier());
+    }
+    Ok(())
+}
+
+fn parse_limit(
+    limit: &mut Option<Limit>,
+    iter: &mut Peekable<Iter<Token>>,
+) -> Result<(), CustomError> {
+    if !matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("LIMIT"))
+    {
+        // Si no hay LIMIT, se retornan todas las filas
+        return Ok(());
+    }
+    iter.next();
+    let count = parse_row_count(iter, "LIMIT")?;
+    let mut offset = 0;
+    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("OFFSET"))
+    {
+        iter.next();
+        offset = parse_row_count(iter, "OFFSET")?;
+    }
+    *limit = Some(Limit { count, offset });
+    Ok(())
+}
+
+/// Parsea la cantidad de filas que sigue a LIMIT u OFFSET, que debe ser un entero no negativo.
+fn parse_row_count(iter: &mut Peekable<Iter<Token>>, keyword: &str) -> Result<usize, CustomError> {
+    if let Some(Token::Integer(count)) = iter.next() {
+        if let Ok(count) = count.parse::<usize>() {
+            return Ok(count);
+        }
+    }
+    Err(CustomError::InvalidSyntax {
+        message: format!("Expected a non-negative integer after {}", keyword),
+    })
+}
+
 fn parse_order_by(
-    order_by: &mut Vec<(String, String)>,
+    order_by: &mut Vec<OrderBy>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
-    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.as_str() == "ORDER") {
+    if matches!(iter.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("ORDER"))
+    {
         // Verifico que haya ORDER
         iter.next();
-        if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.as_str() == "BY") {
+        if !matches!(iter.next(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("BY"))
+        {
             // Verifico que haya BY
             return CustomError::error_invalid_syntax("Expected BY after ORDER");
         }
@@ -385,40 +991,47 @@ fn parse_order_by(
 }
 
 fn parse_order_by_column(
-    order_by: &mut Vec<(String, String)>,
+    order_by: &mut Vec<OrderBy>,
     iter: &mut Peekable<Iter<Token>>,
 ) -> Result<(), CustomError> {
-    let order_by_tuple: (String, String);
-    let order_by_column: String;
-    if let Some(Token::Identifier(name)) | Some(Token::String(name)) = iter.next() {
+    let operand: Operand;
+    let direction: SortDirection;
+    if let Some(aggregate) = parse_aggregate(iter)? {
+        operand = Operand::Aggregate(aggregate);
+    } else if let Some(name) = iter.next().and_then(name_of) {
         // Verifico que haya nombre de columna
-        order_by_column = name.to_string();
+        operand = Operand::Column(name.to_string());
     } else {
         return CustomError::error_invalid_syntax("Expected column name after ORDER BY or ','");
     }
     if let Some(Token::Keyword(keyword)) = iter.peek() {
         // Verifico que haya DESC o nada
-        if keyword.as_str() == "DESC" {
+        if keyword.eq_ignore_ascii_case("DESC") {
             iter.next();
-            order_by_tuple = (order_by_column, "DESC".to_string());
-        } else if keyword.as_str() == "ASC" {
+            direction = SortDirection::Descending;
+        } else if keyword.eq_ignore_ascii_case("ASC") {
             iter.next();
-            order_by_tuple = (order_by_column, "ASC".to_string());
+            direction = SortDirection::Ascending;
+        } else if keyword.eq_ignore_ascii_case("LIMIT") {
+            // LIMIT termina el ORDER BY
+            direction = SortDirection::Ascending;
         } else {
             return CustomError::error_invalid_syntax(
                 "Expected DESC, ASC or nothing after column name",
             );
         }
     } else {
-        order_by_tuple = (order_by_column, "ASC".to_string());
+        direction = SortDirection::Ascending;
     }
-    order_by.push(order_by_tuple); // Agrego la columna y su orden al vector order_by
+    order_by.push(OrderBy { operand, direction }); // Agrego la clave y su sentido al vector order_by
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::expression::Operand;
+    use super::super::aggregate::{Aggregate, AggregateFunction};
+    use super::super::tokenizer::tokenize;
+    use super::super::value::Value;
     use super::*;
 
     #[test]
@@ -441,26 +1054,27 @@ mod tests {
             Token::Symbol(')'),
             Token::Symbol(';'),
         ];
-        let mut table_name = String::new();
-        let mut columns = Vec::new();
-        let mut values = Vec::new();
-
-        let result = parse_insert(&tokens, &mut table_name, &mut columns, &mut values);
+        let result = parse_insert(&tokens);
 
         assert!(result.is_ok());
-        assert_eq!(table_name, "table");
         assert_eq!(
-            columns,
-            vec!["column1".to_string(), "column2 with spaces".to_string(),]
-        );
-        assert_eq!(
-            values,
-            vec![{
-                let mut row = HashMap::new();
-                row.insert("column1".to_string(), "value1".to_string());
-                row.insert("column2 with spaces".to_string(), "value2".to_string());
-                row
-            }]
+            result.unwrap(),
+            Statement::Insert {
+                table_name: "table".to_string(),
+                columns: vec!["column1".to_string(), "column2 with spaces".to_string(),],
+                values: vec![{
+                    let mut row = HashMap::new();
+                    row.insert(
+                        "column1".to_string(),
+                        Operand::Literal(Value::Text("value1".to_string())),
+                    );
+                    row.insert(
+                        "column2 with spaces".to_string(),
+                        Operand::Literal(Value::Text("value2".to_string())),
+                    );
+                    row
+                }],
+            }
         );
     }
 
@@ -484,11 +1098,7 @@ mod tests {
             Token::String("value2".to_string()),
             Token::Symbol(')'),
         ];
-        let mut table_name = String::new();
-        let mut columns = Vec::new();
-        let mut values = Vec::new();
-
-        let result = parse_insert(&tokens, &mut table_name, &mut columns, &mut values);
+        let result = parse_insert(&tokens);
 
         assert!(result.is_err());
         assert_eq!(
@@ -519,30 +1129,30 @@ mod tests {
             Token::String("value3".to_string()),
             Token::Symbol(';'),
         ];
-        let mut table_name = String::new();
-        let mut set_values = HashMap::new();
-        let mut condition = Expression::Comparison {
-            left: Operand::Column("column1".to_string()),
-            operator: "=".to_string(),
-            right: Operand::String("value1".to_string()),
-        };
-
-        let result = parse_update(&tokens, &mut table_name, &mut set_values, &mut condition);
+        let result = parse_update(&tokens);
 
         assert!(result.is_ok());
-        assert_eq!(table_name, "table");
-        assert_eq!(set_values, {
-            let mut set_values = HashMap::new();
-            set_values.insert("column1".to_string(), "value1".to_string());
-            set_values.insert("column2".to_string(), "value2".to_string());
-            set_values
-        });
         assert_eq!(
-            condition,
-            Expression::Comparison {
-                left: Operand::Column("column3".to_string()),
-                operator: "=".to_string(),
-                right: Operand::String("value3".to_string()),
+            result.unwrap(),
+            Statement::Update {
+                table_name: "table".to_string(),
+                set_values: {
+                    let mut set_values = HashMap::new();
+                    set_values.insert(
+                        "column1".to_string(),
+                        Operand::Literal(Value::Text("value1".to_string())),
+                    );
+                    set_values.insert(
+                        "column2".to_string(),
+                        Operand::Literal(Value::Text("value2".to_string())),
+                    );
+                    set_values
+                },
+                condition: Expression::Comparison {
+                    left: Operand::Column("column3".to_string()),
+                    operator: "=".to_string(),
+                    right: Operand::Literal(Value::Text("value3".to_string())),
+                },
             }
         );
     }
@@ -566,15 +1176,7 @@ mod tests {
             Token::String("value3".to_string()),
             Token::Symbol(';'),
         ];
-        let mut table_name = String::new();
-        let mut set_values = HashMap::new();
-        let mut condition = Expression::Comparison {
-            left: Operand::Column("column1".to_string()),
-            operator: "=".to_string(),
-            right: Operand::String("value1".to_string()),
-        };
-
-        let result = parse_update(&tokens, &mut table_name, &mut set_values, &mut condition);
+        let result = parse_update(&tokens);
         assert!(result.is_err());
 
         assert_eq!(
@@ -598,23 +1200,18 @@ mod tests {
             Token::String("value1".to_string()),
             Token::Symbol(';'),
         ];
-        let mut table_name = String::new();
-        let mut condition = Expression::Comparison {
-            left: Operand::Column("column1".to_string()),
-            operator: "=".to_string(),
-            right: Operand::String("value1".to_string()),
-        };
-
-        let result = parse_delete(&tokens, &mut table_name, &mut condition);
+        let result = parse_delete(&tokens);
 
         assert!(result.is_ok());
-        assert_eq!(table_name, "table");
         assert_eq!(
-            condition,
-            Expression::Comparison {
-                left: Operand::Column("column1".to_string()),
-                operator: "=".to_string(),
-                right: Operand::String("value1".to_string()),
+            result.unwrap(),
+            Statement::Delete {
+                table_name: "table".to_string(),
+                condition: Expression::Comparison {
+                    left: Operand::Column("column1".to_string()),
+                    operator: "=".to_string(),
+                    right: Operand::Literal(Value::Text("value1".to_string())),
+                },
             }
         );
     }
@@ -630,14 +1227,7 @@ mod tests {
             Token::ComparisonOperator("=".to_string()),
             Token::String("value1".to_string()),
         ];
-        let mut table_name = String::new();
-        let mut condition = Expression::Comparison {
-            left: Operand::Column("column1".to_string()),
-            operator: "=".to_string(),
-            right: Operand::String("value1".to_string()),
-        };
-
-        let result = parse_delete(&tokens, &mut table_name, &mut condition);
+        let result = parse_delete(&tokens);
 
         assert!(result.is_err());
         assert_eq!(
@@ -670,41 +1260,40 @@ mod tests {
             Token::Identifier("column5".to_string()),
             Token::Symbol(';'),
         ];
-        let mut columns = Vec::new();
-        let mut table_name = String::new();
-        let mut condition = Expression::Comparison {
-            left: Operand::Column("column3".to_string()),
-            operator: "=".to_string(),
-            right: Operand::String("value3".to_string()),
-        };
-        let mut order_by = Vec::new();
-
-        let result = parse_select(
-            &tokens,
-            &mut columns,
-            &mut table_name,
-            &mut condition,
-            &mut order_by,
-        );
+        let result = parse_select(&tokens);
 
         assert!(result.is_ok());
-        assert_eq!(columns, vec!["column1".to_string(), "column2".to_string(),]);
-        assert_eq!(table_name, "table");
         assert_eq!(
-            condition,
-            Expression::Comparison {
-                left: Operand::Column("column3".to_string()),
-                operator: "=".to_string(),
-                right: Operand::String("value3".to_string()),
+            result.unwrap(),
+            Statement::Select {
+                distinct: false,
+                columns: vec![
+                    SelectColumn::column("column1"),
+                    SelectColumn::column("column2"),
+                ],
+                table_name: "table".to_string(),
+                condition: Expression::Comparison {
+                    left: Operand::Column("column3".to_string()),
+                    operator: "=".to_string(),
+                    right: Operand::Literal(Value::Text("value3".to_string())),
+                },
+                table_alias: None,
+                joins: vec![],
+                group_by: vec![],
+                having: Box::new(Expression::True),
+                order_by: vec![
+                    OrderBy {
+                        operand: Operand::Column("column4".to_string()),
+                        direction: SortDirection::Descending,
+                    },
+                    OrderBy {
+                        operand: Operand::Column("column5".to_string()),
+                        direction: SortDirection::Ascending,
+                    },
+                ],
+                limit: None,
             }
         );
-        assert_eq!(
-            order_by,
-            vec![
-                ("column4".to_string(), "DESC".to_string()),
-                ("column5".to_string(), "ASC".to_string()),
-            ]
-        );
     }
 
     #[test]
@@ -730,22 +1319,7 @@ mod tests {
             Token::Keyword("REVERSE".to_string()), // Keyword invalido
             Token::Symbol(';'),
         ];
-        let mut columns = Vec::new();
-        let mut table_name = String::new();
-        let mut condition = Expression::Comparison {
-            left: Operand::Column("column3".to_string()),
-            operator: "=".to_string(),
-            right: Operand::String("value3".to_string()),
-        };
-        let mut order_by = Vec::new();
-
-        let result = parse_select(
-            &tokens,
-            &mut columns,
-            &mut table_name,
-            &mut condition,
-            &mut order_by,
-        );
+        let result = parse_select(&tokens);
 
         assert!(result.is_err());
         assert_eq!(
@@ -755,4 +1329,629 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_statement() {
+        // DELETE FROM table;
+        let tokens = vec![
+            Token::Keyword("DELETE".to_string()),
+            Token::Keyword("FROM".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_statement(&tokens);
+
+        assert_eq!(
+            result.unwrap(),
+            Statement::Delete {
+                table_name: "table".to_string(),
+                condition: Expression::True,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_select_group_by_having() {
+        let tokens = tokenize(
+            "SELECT Category, COUNT(*), SUM(Stock) FROM items WHERE Stock > 0 GROUP BY Category HAVING COUNT(*) > 1 ORDER BY sum(Stock) DESC;",
+        )
+        .unwrap();
+        let count = Aggregate {
+            function: AggregateFunction::Count,
+            operand: None,
+            distinct: false,
+        };
+        assert_eq!(
+            parse_select(&tokens).unwrap(),
+            Statement::Select {
+                distinct: false,
+                columns: vec![
+                    SelectColumn::column("Category"),
+                    SelectColumn {
+                        operand: Operand::Aggregate(count.clone()),
+                        alias: None,
+                    },
+                    SelectColumn {
+                        operand: Operand::Aggregate(Aggregate {
+                            function: AggregateFunction::Sum,
+                            operand: Some(Box::new(Operand::Column("Stock".to_string()))),
+                            distinct: false,
+                        }),
+                        alias: None,
+                    },
+                ],
+                table_name: "items".to_string(),
+                table_alias: None,
+                joins: vec![],
+                condition: Expression::Comparison {
+                    left: Operand::Column("Stock".to_string()),
+                    operator: ">".to_string(),
+                    right: Operand::Literal(Value::Integer(0)),
+                },
+                group_by: vec![Operand::Column("Category".to_string())],
+                having: Box::new(Expression::Comparison {
+                    left: Operand::Aggregate(count),
+                    operator: ">".to_string(),
+                    right: Operand::Literal(Value::Integer(1)),
+                }),
+                order_by: vec![OrderBy {
+                    operand: Operand::Aggregate(Aggregate {
+                        function: AggregateFunction::Sum,
+                        operand: Some(Box::new(Operand::Column("Stock".to_string()))),
+                        distinct: false,
+                    }),
+                    direction: SortDirection::Descending,
+                }],
+                limit: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_select_invalid_aggregates() {
+        for (command, message) in [
+            (
+                "SELECT Name, COUNT(*) FROM items GROUP BY Category;",
+                "Column Name must appear in GROUP BY or be used in an aggregate function",
+            ),
+            (
+                "SELECT * FROM items GROUP BY Category;",
+                "Can't select * with GROUP BY or aggregates",
+            ),
+            (
+                "SELECT Name FROM items WHERE COUNT(*) > 1;",
+                "Aggregate functions are not allowed in WHERE",
+            ),
+            (
+                "SELECT SUM(COUNT(*) + 1) FROM items;",
+                "Aggregate functions can't be nested in SUM()",
+            ),
+        ] {
+            assert_eq!(
+                parse_select(&tokenize(command).unwrap()),
+                Err(CustomError::InvalidSyntax {
+                    message: message.to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_select_joins() {
+        let tokens = tokenize(
+            "SELECT p.name, d.name FROM patients AS p LEFT OUTER JOIN doctors d ON p.doctor_id = d.id CROSS JOIN rooms;",
+        )
+        .unwrap();
+        assert_eq!(
+            parse_select(&tokens).unwrap(),
+            Statement::Select {
+                distinct: false,
+                columns: vec![
+                    SelectColumn::column("p.name"),
+                    SelectColumn::column("d.name"),
+                ],
+                table_name: "patients".to_string(),
+                table_alias: Some("p".to_string()),
+                joins: vec![
+                    Join {
+                        join_type: JoinType::Left,
+                        table_name: "doctors".to_string(),
+                        alias: Some("d".to_string()),
+                        condition: Expression::Comparison {
+                            left: Operand::Column("p.doctor_id".to_string()),
+                            operator: "=".to_string(),
+                            right: Operand::Column("d.id".to_string()),
+                        },
+                    },
+                    Join {
+                        join_type: JoinType::Cross,
+                        table_name: "rooms".to_string(),
+                        alias: None,
+                        condition: Expression::True,
+                    },
+                ],
+                condition: Expression::True,
+                group_by: vec![],
+                having: Box::new(Expression::True),
+                order_by: vec![],
+                limit: None,
+            }
+        );
+        for (command, message) in [
+            (
+                "SELECT * FROM patients JOIN doctors;",
+                "Expected ON after JOIN table",
+            ),
+            (
+                "SELECT * FROM patients JOIN patients ON id = id;",
+                "Table name patients is used more than once, use an alias",
+            ),
+        ] {
+            assert_eq!(
+                parse_select(&tokenize(command).unwrap()),
+                Err(CustomError::InvalidSyntax {
+                    message: message.to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_select_limit_offset() {
+        let tokens = tokenize("SELECT * FROM items ORDER BY id LIMIT 10 OFFSET 20;").unwrap();
+        match parse_select(&tokens).unwrap() {
+            Statement::Select { limit, .. } => assert_eq!(
+                limit,
+                Some(Limit {
+                    count: 10,
+                    offset: 20
+                })
+            ),
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        for (command, message) in [
+            (
+                "SELECT * FROM items LIMIT -1;",
+                "Expected a non-negative integer after LIMIT",
+            ),
+            (
+                "SELECT * FROM items LIMIT 1 OFFSET;",
+                "Expected a non-negative integer after OFFSET",
+            ),
+            (
+                "SELECT * FROM items OFFSET 1;",
+                "Expected ';' at the end of the command",
+            ),
+        ] {
+            assert_eq!(
+                parse_select(&tokenize(command).unwrap()),
+                Err(CustomError::InvalidSyntax {
+                    message: message.to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_select_computed_columns() {
+        let tokens =
+            tokenize("SELECT Price * Stock AS inventory_value, 'First Name', CONCAT('new', Name) FROM items;")
+                .unwrap();
+        match parse_select(&tokens).unwrap() {
+            Statement::Select { columns, .. } => {
+                let names: Vec<String> = columns.iter().map(|column| column.name()).collect();
+                assert_eq!(
+                    names,
+                    vec!["inventory_value", "First Name", "CONCAT('new', Name)"]
+                );
+                assert_eq!(columns[1], SelectColumn::column("First Name"));
+            }
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+
+        let tokens = tokenize("SELECT Category, Stock + 1 FROM items GROUP BY Category;").unwrap();
+        assert_eq!(
+            parse_select(&tokens),
+            Err(CustomError::InvalidSyntax {
+                message: "Column Stock must appear in GROUP BY or be used in an aggregate function"
+                    .to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_select_distinct() {
+        let tokens = tokenize(
+            "SELECT DISTINCT Category, COUNT(DISTINCT Brand) FROM items GROUP BY Category;",
+        )
+        .unwrap();
+        match parse_select(&tokens).unwrap() {
+            Statement::Select {
+                distinct, columns, ..
+            } => {
+                assert!(distinct);
+                assert_eq!(
+                    columns[1].operand,
+                    Operand::Aggregate(Aggregate {
+                        function: AggregateFunction::Count,
+                        operand: Some(Box::new(Operand::Column("Brand".to_string()))),
+                        distinct: true,
+                    })
+                );
+            }
+            statement => panic!("Unexpected statement: {:?}", statement),
+        }
+        assert_eq!(
+            parse_select(&tokenize("SELECT COUNT(DISTINCT *) FROM items;").unwrap()),
+            Err(CustomError::InvalidSyntax {
+                message: "Expected column name in COUNT()".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_statement_invalid_command() {
+        let tokens = vec![Token::Keyword("FROM".to_string())];
+
+        let result = parse_statement(&tokens);
+
+        assert_eq!(
+            result.err().unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Invalid command: FROM".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_create_table() {
+        // CREATE TABLE IF NOT EXISTS table (column1, column2);
+        let tokens = vec![
+            Token::Keyword("CREATE".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Keyword("IF".to_string()),
+            Token::LogicalOperator("NOT".to_string()),
+            Token::Keyword("EXISTS".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Symbol('('),
+            Token::Identifier("column1".to_string()),
+            Token::Symbol(','),
+            Token::Identifier("column2".to_string()),
+            Token::Symbol(')'),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_create_table(&tokens);
+
+        assert_eq!(
+            result.unwrap(),
+            Statement::CreateTable {
+                table_name: "table".to_string(),
+                columns: vec![
+                    ColumnDefinition::new("column1"),
+                    ColumnDefinition::new("column2")
+                ],
+                if_not_exists: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_create_table_with_types() {
+        // CREATE TABLE table (column1 INTEGER NOT NULL, column2 TEXT DEFAULT 'none');
+        let tokens = vec![
+            Token::Keyword("CREATE".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Symbol('('),
+            Token::Identifier("column1".to_string()),
+            Token::Identifier("integer".to_string()),
+            Token::LogicalOperator("NOT".to_string()),
+            Token::Keyword("NULL".to_string()),
+            Token::Symbol(','),
+            Token::Identifier("column2".to_string()),
+            Token::Identifier("TEXT".to_string()),
+            Token::Keyword("DEFAULT".to_string()),
+            Token::String("none".to_string()),
+            Token::Symbol(')'),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_create_table(&tokens);
+
+        assert_eq!(
+            result.unwrap(),
+            Statement::CreateTable {
+                table_name: "table".to_string(),
+                columns: vec![
+                    ColumnDefinition {
+                        name: "column1".to_string(),
+                        column_type: Some(ColumnType::Integer),
+                        not_null: true,
+                        default: None,
+                        primary_key: false,
+                        unique: false,
+                    },
+                    ColumnDefinition {
+                        name: "column2".to_string(),
+                        column_type: Some(ColumnType::Text),
+                        not_null: false,
+                        default: Some(Value::Text("none".to_string())),
+                        primary_key: false,
+                        unique: false,
+                    },
+                ],
+                if_not_exists: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_column_definition_invalid_default() {
+        // column1 INTEGER DEFAULT 'abc'
+        let tokens = [
+            Token::Identifier("column1".to_string()),
+            Token::Identifier("INTEGER".to_string()),
+            Token::Keyword("DEFAULT".to_string()),
+            Token::String("abc".to_string()),
+        ];
+
+        let result = parse_column_definition(&mut tokens.iter().peekable());
+
+        assert_eq!(
+            result.err().unwrap(),
+            CustomError::InvalidColumn {
+                message: "Invalid value 'abc' for column column1 of type INTEGER".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_column_definition_primary_key_and_unique() {
+        // ID INTEGER PRIMARY KEY UNIQUE
+        let tokens = [
+            Token::Identifier("ID".to_string()),
+            Token::Identifier("INTEGER".to_string()),
+            Token::Keyword("PRIMARY".to_string()),
+            Token::Keyword("KEY".to_string()),
+            Token::Keyword("UNIQUE".to_string()),
+        ];
+
+        let definition = parse_column_definition(&mut tokens.iter().peekable()).unwrap();
+
+        assert!(definition.primary_key);
+        assert!(definition.unique);
+    }
+
+    #[test]
+    fn test_parse_create_table_multiple_primary_keys() {
+        // CREATE TABLE table (column1 PRIMARY KEY, column2 PRIMARY KEY);
+        let tokens = vec![
+            Token::Keyword("CREATE".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Symbol('('),
+            Token::Identifier("column1".to_string()),
+            Token::Keyword("PRIMARY".to_string()),
+            Token::Keyword("KEY".to_string()),
+            Token::Symbol(','),
+            Token::Identifier("column2".to_string()),
+            Token::Keyword("PRIMARY".to_string()),
+            Token::Keyword("KEY".to_string()),
+            Token::Symbol(')'),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_create_table(&tokens);
+
+        assert_eq!(
+            result.err().unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Multiple primary keys".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_transaction_control() {
+        // BEGIN TRANSACTION;
+        let tokens = vec![
+            Token::Keyword("BEGIN".to_string()),
+            Token::Keyword("TRANSACTION".to_string()),
+            Token::Symbol(';'),
+        ];
+        assert_eq!(parse_statement(&tokens).unwrap(), Statement::Begin);
+
+        // ROLLBACK;
+        let tokens = vec![Token::Keyword("ROLLBACK".to_string()), Token::Symbol(';')];
+        assert_eq!(parse_statement(&tokens).unwrap(), Statement::Rollback);
+
+        // COMMIT
+        let tokens = vec![Token::Keyword("COMMIT".to_string())];
+        assert_eq!(
+            parse_statement(&tokens).err().unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Expected ';' at the end of the command".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_create_table_duplicated_column() {
+        // CREATE TABLE table (column1, column1);
+        let tokens = vec![
+            Token::Keyword("CREATE".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Symbol('('),
+            Token::Identifier("column1".to_string()),
+            Token::Symbol(','),
+            Token::Identifier("column1".to_string()),
+            Token::Symbol(')'),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_create_table(&tokens);
+
+        assert_eq!(
+            result.err().unwrap(),
+            CustomError::InvalidColumn {
+                message: "Duplicated column: column1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_drop_table() {
+        // DROP TABLE IF EXISTS table;
+        let tokens = vec![
+            Token::Keyword("DROP".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Keyword("IF".to_string()),
+            Token::Keyword("EXISTS".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_drop_table(&tokens);
+
+        assert_eq!(
+            result.unwrap(),
+            Statement::DropTable {
+                table_name: "table".to_string(),
+                if_exists: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_alter_table_add_column() {
+        // ALTER TABLE table ADD COLUMN column1 DEFAULT 0;
+        let tokens = vec![
+            Token::Keyword("ALTER".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Keyword("ADD".to_string()),
+            Token::Keyword("COLUMN".to_string()),
+            Token::Identifier("column1".to_string()),
+            Token::Keyword("DEFAULT".to_string()),
+            Token::Integer("0".to_string()),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_alter_table(&tokens);
+
+        assert_eq!(
+            result.unwrap(),
+            Statement::AlterTable {
+                table_name: "table".to_string(),
+                operation: AlterOperation::AddColumn {
+                    column: ColumnDefinition {
+                        name: "column1".to_string(),
+                        column_type: None,
+                        not_null: false,
+                        default: Some(Value::Integer(0)),
+                        primary_key: false,
+                        unique: false,
+                    },
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_alter_table_rename_column() {
+        // ALTER TABLE table RENAME column1 TO column2;
+        let tokens = vec![
+            Token::Keyword("ALTER".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Keyword("RENAME".to_string()),
+            Token::Identifier("column1".to_string()),
+            Token::Keyword("TO".to_string()),
+            Token::Identifier("column2".to_string()),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_alter_table(&tokens);
+
+        assert_eq!(
+            result.unwrap(),
+            Statement::AlterTable {
+                table_name: "table".to_string(),
+                operation: AlterOperation::RenameColumn {
+                    from: "column1".to_string(),
+                    to: "column2".to_string(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_alter_table_invalid_syntax() {
+        // ALTER TABLE table MODIFY column1;
+        let tokens = vec![
+            Token::Keyword("ALTER".to_string()),
+            Token::Keyword("TABLE".to_string()),
+            Token::Identifier("table".to_string()),
+            Token::Identifier("MODIFY".to_string()),
+            Token::Identifier("column1".to_string()),
+            Token::Symbol(';'),
+        ];
+
+        let result = parse_alter_table(&tokens);
+
+        assert_eq!(
+            result.err().unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Expected ADD, DROP or RENAME after table name".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_keywords_as_names() {
+        let tokens =
+            tokenize("SELECT key, left AS to FROM table AS t WHERE key = 1 ORDER BY default;")
+                .unwrap();
+
+        match parse_statement(&tokens).unwrap() {
+            Statement::Select {
+                columns,
+                table_name,
+                table_alias,
+                order_by,
+                ..
+            } => {
+                assert_eq!(table_name, "table");
+                assert_eq!(table_alias, Some("t".to_string()));
+                assert_eq!(columns[0], SelectColumn::column("key"));
+                assert_eq!(columns[1].name(), "to");
+                assert_eq!(
+                    order_by,
+                    vec![OrderBy {
+                        operand: Operand::Column("default".to_string()),
+                        direction: SortDirection::Ascending,
+                    }]
+                );
+            }
+            other => panic!("Expected SELECT, got {:?}", other),
+        }
+
+        let tokens = tokenize("CREATE TABLE if (key INTEGER PRIMARY KEY, column TEXT);").unwrap();
+        match parse_statement(&tokens).unwrap() {
+            Statement::CreateTable {
+                table_name,
+                columns,
+                if_not_exists,
+            } => {
+                assert_eq!(table_name, "if");
+                assert_eq!(columns[0].name, "key");
+                assert_eq!(columns[1].name, "column");
+                assert!(!if_not_exists);
+            }
+            other => panic!("Expected CREATE TABLE, got {:?}", other),
+        }
+    }
 }
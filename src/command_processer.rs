@@ -1,57 +1,249 @@
-use super::command_parser::{parse_delete, parse_insert, parse_select, parse_update};
+use super::aggregate::{Aggregate, GroupAccumulators};
+use super::command_parser::parse_statement;
 use super::custom_error::CustomError;
-use super::expression::Expression;
+use super::database::Database;
+use super::distinct::{distinct_rows, DistinctSet, DISTINCT_MEMORY_LIMIT};
+use super::expression::{evaluate_operand, Expression, Operand};
+use super::join::{join_tables, scan_table, RowSource};
+use super::lock::remove_lock_file;
+use super::output_format::RowSink;
 use super::row::Row;
-use super::tokenizer::{tokenize, Token};
-use crate::row_parser::{parse_columns, parse_row};
-use std::collections::HashMap;
+use super::schema::{column_definitions, schema_path, ColumnDefinition, Schema};
+use super::statement::{
+    AlterOperation, Join, Limit, OrderBy, SelectColumn, SortDirection, Statement,
+};
+use super::storage::{remove_file, rename_file, replace_file, sync_directory, temp_directory};
+use super::tokenizer::tokenize;
+use super::unique_index::{move_index, remove_index, unique_value, IndexChanges, UniqueIndex};
+use super::value::Value;
+use crate::row_parser::{format_field, parse_columns, parse_row, read_record};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 
 // Recibe un vector de argumentos y devuelve un Result: Ok(()) o Err(CustomError)
 /// Procesa el comando recibido recibiendo un vector de argumentos, donde el primer argumento es el directorio de los archivos csv, y el segundo argumento es el comando a procesar.
 pub fn process_command<W: Write>(args: &[String], output: &mut W) -> Result<(), CustomError> {
+    if args.len() < 3 {
+        return CustomError::error_generic("Usage: <directory> <command>");
+    }
     let tokens = tokenize(args[2].as_str())?;
     let directory = args[1].as_str();
-    if let Some(Token::Keyword(keyword)) = tokens.first() {
-        match keyword.as_str() {
-            "INSERT" => process_insert(&tokens, directory),
-            "UPDATE" => process_update(&tokens, directory),
-            "DELETE" => process_delete(&tokens, directory),
-            "SELECT" => process_select(&tokens, directory, output),
-            other => CustomError::error_invalid_syntax(&format!("Invalid command: {}", other)),
+    let statement = parse_statement(&tokens)?;
+    Database::open(directory)?.execute(statement, output)?;
+    Ok(())
+}
+
+/// Ejecuta un Statement ya parseado sobre las tablas del directorio recibido.
+/// El resultado de un SELECT se escribe en output.
+/// Retorna la cantidad de filas insertadas, actualizadas o eliminadas, que es 0 para los demás Statements.
+/// Los Statements de control de transacciones solo pueden ejecutarse a través de una Database.
+pub fn process_statement(
+    statement: Statement,
+    directory: &str,
+    output: &mut dyn RowSink,
+) -> Result<usize, CustomError> {
+    let grouped = statement.is_aggregate();
+    match statement {
+        Statement::Insert {
+            table_name,
+            columns: _,
+            values,
+        } => {
+            let mut rows = vec![];
+            for row in &values {
+                rows.push(column_values(row)?);
+            }
+            process_insert(&table_name, rows, directory)
+        }
+        Statement::Update {
+            table_name,
+            set_values,
+            condition,
+        } => process_update(
+            &table_name,
+            &column_values(&set_values)?,
+            &condition,
+            directory,
+        ),
+        Statement::Delete {
+            table_name,
+            condition,
+        } => process_delete(&table_name, &condition, directory),
+        Statement::Select {
+            distinct,
+            columns,
+            table_name,
+            table_alias,
+            joins,
+            condition,
+            group_by,
+            having,
+            order_by,
+            limit,
+        } => {
+            let mut source = if table_alias.is_none() && joins.is_empty() {
+                scan_table(directory, &table_name)?
+            } else {
+                let references =
+                    select_references(&columns, &joins, &condition, &group_by, &having, &order_by);
+                join_tables(directory, &table_name, &table_alias, &joins, &references)?
+            };
+            let mut condition = condition;
+            if !grouped && columns.iter().any(|column| column.is_computed()) {
+                // las columnas calculadas se agregan a las filas que cumplen la condición
+                source = computed_rows(source, condition, &columns)?;
+                condition = Expression::True;
+            }
+            // el encabezado muestra el nombre de cada columna, pero las filas guardan las calculadas con otro nombre
+            let header: Vec<String> = columns.iter().map(|c| c.name()).collect();
+            let mut columns_to_print: Vec<String> = columns
+                .iter()
+                .enumerate()
+                .map(|(position, column)| select_key(position, column))
+                .collect();
+            let order_by = order_by_keys(order_by, &columns);
+            if grouped {
+                let rows = select_rows_grouped(
+                    source, &condition, &columns, &group_by, &having, &order_by,
+                )?;
+                output.write_header(&header)?;
+                let (offset, count) = match limit {
+                    Some(limit) => (limit.offset, limit.count),
+                    None => (0, usize::MAX),
+                };
+                // los grupos ya están en memoria, por lo que alcanza un HashSet para descartar los repetidos
+                let mut printed_rows = HashSet::new();
+                let mut selected_rows = vec![];
+                for row in rows {
+                    let values = row.selected_values(&columns_to_print)?;
+                    if !distinct || printed_rows.insert(values) {
+                        selected_rows.push(row.selected(&columns_to_print)?);
+                    }
+                }
+                for values in selected_rows.into_iter().skip(offset).take(count) {
+                    output.write_row(&values)?;
+                }
+                output.finish()?;
+            } else if distinct {
+                let projected_columns = if columns_to_print.is_empty() {
+                    &source.columns
+                } else {
+                    &columns_to_print
+                };
+                check_columns_to_print(projected_columns, &source.names)?;
+                for key in &order_by {
+                    let column = key.operand.to_string();
+                    if !projected_columns.contains(&column) {
+                        CustomError::error_invalid_syntax(&format!(
+                            "ORDER BY column {} must be selected with DISTINCT",
+                            column
+                        ))?;
+                    }
+                }
+                let source = distinct_rows(source, &condition, &columns_to_print);
+                select_rows(
+                    source,
+                    &Expression::True,
+                    &header,
+                    &mut columns_to_print,
+                    &order_by,
+                    limit,
+                    output,
+                )?;
+            } else {
+                select_rows(
+                    source,
+                    &condition,
+                    &header,
+                    &mut columns_to_print,
+                    &order_by,
+                    limit,
+                    output,
+                )?;
+            }
+            Ok(0)
+        }
+        Statement::CreateTable {
+            table_name,
+            columns,
+            if_not_exists,
+        } => {
+            process_create_table(&table_name, &columns, if_not_exists, directory)?;
+            Ok(0)
+        }
+        Statement::DropTable {
+            table_name,
+            if_exists,
+        } => {
+            process_drop_table(&table_name, if_exists, directory)?;
+            Ok(0)
+        }
+        Statement::AlterTable {
+            table_name,
+            operation,
+        } => {
+            process_alter_table(&table_name, &operation, directory)?;
+            Ok(0)
+        }
+        Statement::Begin | Statement::Commit | Statement::Rollback => {
+            CustomError::error_generic("Transactions must be executed through a Database")?;
+            Ok(0)
         }
-    } else {
-        CustomError::error_invalid_syntax("Usage: <COMMAND> <...>")
     }
 }
 
-fn create_file(file_path: &str) -> Result<File, CustomError> {
-    let create_file_result = fs::File::create(file_path);
-    if let Ok(file) = create_file_result {
-        return Ok(file);
+/// Retorna los valores que asigna un INSERT o un UPDATE a cada columna, que no dependen de las filas de la tabla.
+fn column_values(
+    operands: &HashMap<String, Operand>,
+) -> Result<HashMap<String, Value>, CustomError> {
+    let mut values = HashMap::new();
+    for (column, operand) in operands {
+        values.insert(
+            column.to_string(),
+            evaluate_operand(operand, &HashMap::new())?,
+        );
     }
-    Err(CustomError::GenericError {
-        message: "Couldn't create file".to_string(),
-    })
+    Ok(values)
 }
 
-fn process_insert(tokens: &[Token], directory: &str) -> Result<(), CustomError> {
-    let mut table_name = String::new();
-    let mut columns = vec![];
-    let mut values = vec![];
-    parse_insert(tokens, &mut table_name, &mut columns, &mut values)?; // parseo los tokens
+fn process_insert(
+    table_name: &str,
+    values: Vec<HashMap<String, Value>>,
+    directory: &str,
+) -> Result<usize, CustomError> {
     let table_path = format!("{}/{}.csv", directory, table_name);
     let table_file = open_table_path(&table_path)?;
     let mut table_reader = BufReader::new(table_file);
-    let mut line = String::new();
-    let full_columns: Vec<String> = if table_reader.read_line(&mut line).is_ok() {
-        // leo la primera linea para obtener las columnas
-        parse_columns(&line)?
+    let full_columns: Vec<String> = if let Some(header) = read_record(&mut table_reader)? {
+        // leo el primer registro para obtener las columnas
+        parse_columns(&header)?
     } else {
-        return CustomError::error_invalid_table("Couldn't read table file");
+        return Err(CustomError::InvalidTable {
+            message: "Couldn't read table file".to_string(),
+        });
     };
 
+    let schema = Schema::load(directory, table_name)?;
+    let mut values = values;
+    let mut unique_index = None;
+    let mut unique_columns = vec![];
+    if let Some(schema) = &schema {
+        for new_value in values.iter_mut() {
+            // completo los valores por defecto y verifico todas las filas antes de escribir
+            schema.prepare_row(new_value)?;
+        }
+        unique_columns = schema.unique_columns();
+        if !unique_columns.is_empty() {
+            let mut index = UniqueIndex::open(directory, table_name, &unique_columns)?;
+            check_unique_insert(&mut index, &unique_columns, &values)?;
+            unique_index = Some(index);
+        }
+    }
+    let unique_values = unique_column_values(&unique_columns, &values);
+
+    let inserted_rows = values.len();
     if let Ok(file) = OpenOptions::new().append(true).open(&table_path) {
         let mut writer = BufWriter::new(file);
         add_newline_if_needed(&mut writer, &mut table_reader)?;
@@ -60,12 +252,97 @@ fn process_insert(tokens: &[Token], directory: &str) -> Result<(), CustomError>
             let row = Row::new(&full_columns, new_value);
             row.write_row(&mut writer)?;
         }
+        // las filas deben llegar al disco antes de que el checkpoint borre el log
+        if writer.flush().is_err() || writer.get_ref().sync_all().is_err() {
+            CustomError::error_invalid_table("Couldn't write table file")?;
+        }
     } else {
-        return CustomError::error_invalid_table("Couldn't open table file");
+        return Err(CustomError::InvalidTable {
+            message: "Couldn't open table file".to_string(),
+        });
+    }
+    if let Some(index) = unique_index {
+        index.insert(&unique_values)?;
+    }
+    Ok(inserted_rows)
+}
+
+fn duplicate_value_error(column: &str, value: &str) -> Result<(), CustomError> {
+    CustomError::error_constraint_violation(&format!(
+        "Duplicate value '{}' for column {}",
+        value, column
+    ))
+}
+
+/// Retorna los valores que reciben las columnas únicas en las filas recibidas, como pares de columna y valor.
+/// Los valores NULL no se consideran repetidos, por lo que no se incluyen.
+fn unique_column_values(
+    unique_columns: &[ColumnDefinition],
+    rows: &[HashMap<String, Value>],
+) -> Vec<(String, Value)> {
+    let mut values = vec![];
+    for row in rows {
+        for column in unique_columns {
+            match row.get(&column.name) {
+                Some(Value::Null) | None => {}
+                Some(value) => values.push((column.name.to_string(), value.clone())),
+            }
+        }
+    }
+    values
+}
+
+/// Verifica que las filas a insertar no repitan valores de las columnas únicas, ni entre ellas ni con las filas de la tabla,
+/// buscando cada valor en el índice de la tabla en lugar de recorrerla. Los valores se comparan según el tipo de su columna,
+/// como en el índice.
+fn check_unique_insert(
+    index: &mut UniqueIndex,
+    unique_columns: &[ColumnDefinition],
+    new_rows: &[HashMap<String, Value>],
+) -> Result<(), CustomError> {
+    let mut new_values = HashSet::new();
+    for row in new_rows {
+        for column in unique_columns {
+            let (value, key) = match row.get(&column.name) {
+                Some(value) => match unique_value(column, value) {
+                    Some(key) => (value, key),
+                    None => continue,
+                },
+                None => continue,
+            };
+            if index.contains(&column.name, value)?
+                || !new_values.insert((column.name.to_string(), key))
+            {
+                duplicate_value_error(&column.name, &value.to_string())?;
+            }
+        }
     }
     Ok(())
 }
 
+/// Retorna las definiciones de las columnas únicas de una tabla, que no tiene ninguna si no tiene esquema.
+fn table_unique_columns(
+    directory: &str,
+    table_name: &str,
+) -> Result<Vec<ColumnDefinition>, CustomError> {
+    Ok(Schema::load(directory, table_name)?
+        .map(|schema| schema.unique_columns())
+        .unwrap_or_default())
+}
+
+/// Abre el índice de las columnas únicas de una tabla solo si ya existe y corresponde a la tabla, para mantenerlo
+/// actualizado al reescribirla. Si no existe, se construye recién cuando se necesite buscar un valor.
+fn existing_index(
+    directory: &str,
+    table_name: &str,
+    unique_columns: &[ColumnDefinition],
+) -> Result<Option<UniqueIndex>, CustomError> {
+    if unique_columns.is_empty() {
+        return Ok(None);
+    }
+    UniqueIndex::open_existing(directory, table_name, unique_columns)
+}
+
 fn add_newline_if_needed(
     writer: &mut BufWriter<File>,
     reader: &mut BufReader<File>,
@@ -84,81 +361,245 @@ fn add_newline_if_needed(
     Ok(())
 }
 
-fn remove_file(file_path: &str) -> Result<(), CustomError> {
-    let remove_file_result = fs::remove_file(file_path);
-    if remove_file_result.is_err() {
-        return CustomError::error_generic("Couldn't remove file");
+/// Reescribe una tabla a través de un archivo temporal con nombre único, que reemplaza de forma atómica a la tabla original
+/// una vez escrito por completo. Si la escritura falla, la tabla original queda intacta.
+/// write_table recibe el path de la tabla original y el writer del archivo temporal.
+fn replace_table<F>(directory: &str, table_name: &str, write_table: F) -> Result<(), CustomError>
+where
+    F: FnOnce(&str, &mut BufWriter<File>) -> Result<(), CustomError>,
+{
+    let table_path = format!("{}/{}.csv", directory, table_name);
+    open_table_path(&table_path)?; // verifico que la tabla exista antes de crear el temporal
+    replace_file(directory, &format!("{}.csv", table_name), |writer| {
+        write_table(table_path.as_str(), writer)
+    })
+}
+
+fn process_update(
+    table_name: &str,
+    set_values: &HashMap<String, Value>,
+    condition: &Expression,
+    directory: &str,
+) -> Result<usize, CustomError> {
+    let mut unique_values = vec![];
+    let mut unique_index = None;
+    let schema = Schema::load(directory, table_name)?;
+    let mut set_values = set_values.clone();
+    if let Some(schema) = &schema {
+        for (column, value) in set_values.iter_mut() {
+            schema.validate_value(column, &value.to_string())?;
+            if let Some(definition) = schema.column(column) {
+                *value = definition.stored_value(value);
+            }
+        }
+        let unique_columns = schema.unique_columns();
+        let assigned = unique_column_values(&unique_columns, std::slice::from_ref(&set_values));
+        if assigned.is_empty() {
+            // el índice solo se actualiza si ya existe, ya que no hace falta buscar valores en él
+            unique_index = existing_index(directory, table_name, &unique_columns)?;
+        } else {
+            // antes de reescribir la tabla se busca en el índice qué valores ya tiene alguna fila
+            let mut index = UniqueIndex::open(directory, table_name, &unique_columns)?;
+            for (column, value) in assigned {
+                let indexed = index.contains(&column, &value)?;
+                unique_values.push(UniqueValue {
+                    column,
+                    value,
+                    indexed,
+                });
+            }
+            unique_index = Some(index);
+        }
     }
-    Ok(())
+    let mut updated_rows = 0;
+    let mut changes = IndexChanges::default();
+    replace_table(directory, table_name, |table_path, writer| {
+        updated_rows = update_table(
+            table_path,
+            writer,
+            schema.as_ref(),
+            condition,
+            &set_values,
+            &unique_values,
+            &mut changes,
+        )?;
+        Ok(())
+    })?;
+    if let Some(index) = unique_index {
+        index.apply(changes)?;
+    }
+    Ok(updated_rows)
 }
 
-fn rename_file(from: &str, to: &str) -> Result<(), CustomError> {
-    let rename_file_result = fs::rename(from, to);
-    if rename_file_result.is_err() {
-        return CustomError::error_generic("Couldn't rename file");
+fn process_delete(
+    table_name: &str,
+    condition: &Expression,
+    directory: &str,
+) -> Result<usize, CustomError> {
+    let mut deleted_rows = 0;
+    let schema = Schema::load(directory, table_name)?;
+    let unique_columns = schema
+        .as_ref()
+        .map(|schema| schema.unique_columns())
+        .unwrap_or_default();
+    let unique_index = existing_index(directory, table_name, &unique_columns)?;
+    let mut changes = IndexChanges::default();
+    replace_table(directory, table_name, |table_path, writer| {
+        deleted_rows = delete_rows_table(
+            table_path,
+            writer,
+            schema.as_ref(),
+            condition,
+            &unique_columns,
+            &mut changes,
+        )?;
+        Ok(())
+    })?;
+    if let Some(index) = unique_index {
+        index.apply(changes)?;
     }
-    Ok(())
+    Ok(deleted_rows)
 }
 
-fn process_update(tokens: &[Token], directory: &str) -> Result<(), CustomError> {
-    let mut table_name = String::new();
-    let mut set_values = HashMap::new();
-    let mut condition = Expression::True;
-    parse_update(tokens, &mut table_name, &mut set_values, &mut condition)?; // parseo los tokens
-    table_name.push_str(".csv");
-    let table_path = format!("{}/{}", directory, table_name);
-    let tmp_path = table_path.trim_end_matches(table_name.as_str()).to_string() + "_tmp.csv"; // creo el path del archivo temporal
-    let tmp_file = create_file(&tmp_path)?; // creo el archivo temporal
-    let mut writer = BufWriter::new(tmp_file);
-    update_table(table_path.as_str(), &mut writer, &condition, &set_values)?;
-    remove_file(&table_path)?;
-    rename_file(&tmp_path, &table_path)?;
+fn process_alter_table(
+    table_name: &str,
+    operation: &AlterOperation,
+    directory: &str,
+) -> Result<(), CustomError> {
+    if let AlterOperation::RenameTable { new_name } = operation {
+        // renombrar la tabla no cambia su contenido, alcanza con renombrar el archivo
+        let table_path = format!("{}/{}.csv", directory, table_name);
+        let new_path = format!("{}/{}.csv", directory, new_name);
+        open_table_path(&table_path)?;
+        if fs::metadata(&new_path).is_ok() {
+            return CustomError::error_invalid_table(&format!(
+                "Table already exists: {}",
+                new_name
+            ));
+        }
+        rename_file(&table_path, &new_path)?;
+        move_index(directory, table_name, directory, new_name)?;
+        sync_directory(directory)?;
+        remove_lock_file(directory, table_name)?;
+        return alter_schema(table_name, operation, directory);
+    }
+    if let (AlterOperation::AddColumn { column }, Some(schema)) =
+        (operation, Schema::load(directory, table_name)?)
+    {
+        if column.primary_key && schema.columns.iter().any(|other| other.primary_key) {
+            return CustomError::error_invalid_syntax("Multiple primary keys");
+        }
+    }
+    let unique_columns = table_unique_columns(directory, table_name)?;
+    let unique_index = existing_index(directory, table_name, &unique_columns)?;
+    replace_table(directory, table_name, |table_path, writer| {
+        alter_table_rows(table_path, writer, operation)
+    })?;
+    alter_schema(table_name, operation, directory)?;
+    if let Some(index) = unique_index {
+        if table_unique_columns(directory, table_name)? == unique_columns {
+            // las claves no cambian, alcanza con marcar el índice como correspondiente a la tabla nueva
+            index.apply(IndexChanges::default())?;
+        } else {
+            // el índice se vuelve a construir con las nuevas columnas únicas cuando se necesite
+            drop(index);
+            remove_index(directory, table_name)?;
+        }
+    }
     Ok(())
 }
 
-fn process_delete(tokens: &[Token], directory: &str) -> Result<(), CustomError> {
-    let mut table_name = String::new();
-    let mut condition = Expression::True;
-    parse_delete(tokens, &mut table_name, &mut condition)?; // parseo los tokens
-    table_name.push_str(".csv");
-    let table_path = format!("{}/{}", directory, table_name);
-    let tmp_path = table_path.trim_end_matches(table_name.as_str()).to_string() + "_tmp.csv"; // creo el path del archivo temporal
-    let tmp_file = create_file(&tmp_path)?; // creo el archivo temporal
-    let mut writer = BufWriter::new(tmp_file);
-    delete_rows_table(table_path.as_str(), &mut writer, &condition)?;
-    remove_file(&table_path)?;
-    rename_file(&tmp_path, &table_path)?;
-    Ok(())
+/// Actualiza el esquema de una tabla luego de aplicar una operación de ALTER TABLE.
+fn alter_schema(
+    table_name: &str,
+    operation: &AlterOperation,
+    directory: &str,
+) -> Result<(), CustomError> {
+    let mut schema = match (Schema::load(directory, table_name)?, operation) {
+        (Some(schema), _) => schema,
+        (None, AlterOperation::AddColumn { column }) if column.has_constraints() => {
+            Schema { columns: vec![] }
+        }
+        (None, _) => return Ok(()), // si la tabla no tiene esquema no hay nada que actualizar
+    };
+    match operation {
+        AlterOperation::AddColumn { column } => schema.columns.push(column.clone()),
+        AlterOperation::DropColumn { column } => schema
+            .columns
+            .retain(|definition| &definition.name != column),
+        AlterOperation::RenameColumn { from, to } => {
+            if let Some(definition) = schema.column_mut(from) {
+                definition.name = to.to_string();
+            }
+        }
+        AlterOperation::RenameTable { new_name } => {
+            remove_file(&schema_path(directory, table_name))?;
+            return schema.save(directory, new_name);
+        }
+    }
+    schema.save(directory, table_name)
 }
 
-fn process_select<W: Write>(
-    tokens: &[Token],
+fn process_create_table(
+    table_name: &str,
+    columns: &[ColumnDefinition],
+    if_not_exists: bool,
     directory: &str,
-    output: &mut W,
 ) -> Result<(), CustomError> {
-    let mut columns = vec![];
-    let mut table_name = String::new();
-    let mut condition = Expression::True;
-    let mut order_by = vec![];
-    parse_select(
-        tokens,
-        &mut columns,
-        &mut table_name,
-        &mut condition,
-        &mut order_by,
-    )?; // parseo los tokens
     let table_path = format!("{}/{}.csv", directory, table_name);
-    select_rows_table(
-        table_path.as_str(),
-        &condition,
-        &mut columns,
-        &order_by,
-        output,
-    )?;
+    if fs::metadata(&table_path).is_ok() {
+        if if_not_exists {
+            return Ok(());
+        }
+        return CustomError::error_invalid_table(&format!("Table already exists: {}", table_name));
+    }
+    let header: Vec<String> = columns
+        .iter()
+        .map(|column| format_field(&column.name))
+        .collect();
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&table_path)
+    {
+        Ok(mut file) => {
+            if writeln!(file, "{}", header.join(",")).is_err() {
+                return CustomError::error_invalid_table("Couldn't write table file");
+            }
+        }
+        Err(_) => return CustomError::error_invalid_table("Couldn't create table file"),
+    }
+    if columns.iter().any(|column| column.has_constraints()) {
+        // solo se guarda un esquema si alguna columna declara tipo o restricciones
+        let schema = Schema {
+            columns: columns.to_vec(),
+        };
+        schema.save(directory, table_name)?;
+    }
     Ok(())
 }
 
-fn open_table_path(table_path: &str) -> Result<File, CustomError> {
+fn process_drop_table(
+    table_name: &str,
+    if_exists: bool,
+    directory: &str,
+) -> Result<(), CustomError> {
+    let table_path = format!("{}/{}.csv", directory, table_name);
+    if fs::metadata(&table_path).is_err() {
+        if if_exists {
+            return Ok(());
+        }
+        return CustomError::error_invalid_table(&format!("Table does not exist: {}", table_name));
+    }
+    remove_file(&table_path)?;
+    if Schema::load(directory, table_name)?.is_some() {
+        remove_file(&schema_path(directory, table_name))?;
+    }
+    remove_index(directory, table_name)?;
+    remove_lock_file(directory, table_name)
+}
+
+pub(crate) fn open_table_path(table_path: &str) -> Result<File, CustomError> {
     let table_file_result = fs::File::open(table_path);
     if let Ok(table_file) = table_file_result {
         return Ok(table_file);
@@ -168,60 +609,209 @@ fn open_table_path(table_path: &str) -> Result<File, CustomError> {
     })
 }
 
+/// Un valor que un UPDATE asigna a una columna única, y si antes del UPDATE ya lo tenía alguna fila según el índice.
+struct UniqueValue {
+    column: String,
+    value: Value,
+    indexed: bool,
+}
+
+/// Actualiza las filas que cumplen la condición mientras reescribe la tabla, y retorna cuántas se actualizaron.
+/// Si se asigna un valor a una columna única, solo puede recibirlo una fila, y si ya lo tenía alguna fila
+/// debe ser esa misma. Los valores que cambian en las columnas únicas se registran en changes.
 fn update_table(
     table_path: &str,
     writer: &mut BufWriter<File>,
+    schema: Option<&Schema>,
     condition: &Expression,
-    update_values: &HashMap<String, String>,
-) -> Result<(), CustomError> {
+    update_values: &HashMap<String, Value>,
+    unique_values: &[UniqueValue],
+    changes: &mut IndexChanges,
+) -> Result<usize, CustomError> {
+    let unique_columns: Vec<ColumnDefinition> = schema
+        .map(|schema| schema.unique_columns())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|column| update_values.contains_key(&column.name))
+        .collect();
+    let mut updated_rows = 0;
+    // si alguna fila actualizada ya tenía el valor, es la fila que lo tenía según el índice
+    let mut kept_values = vec![false; unique_values.len()];
     let table_file = open_table_path(table_path)?;
-    let mut columns: Vec<String> = vec![];
-    let table_reader = std::io::BufReader::new(table_file);
-    let mut first_line = true; // flag para saber si es la primera linea = columnas
-    for line in table_reader.lines() {
-        if line.is_err() {
-            return CustomError::error_generic("Couldn't read table file");
-        }
-        if let Ok(line) = line {
-            if first_line {
-                first_line = false;
-                columns = line.split(",").map(|s| s.to_string()).collect();
-                let row = parse_row(&columns, line.as_str())?;
-                row.write_row(writer)?;
-                continue;
+    let mut columns: Vec<ColumnDefinition> = vec![];
+    let mut table_reader = std::io::BufReader::new(table_file);
+    let mut first_line = true; // flag para saber si es el primer registro = columnas
+    while let Some(line) = read_record(&mut table_reader)? {
+        if first_line {
+            first_line = false;
+            columns = column_definitions(schema, &parse_columns(&line)?);
+            let row = parse_row(&columns, line.as_str())?;
+            row.write_row(writer)?;
+            continue;
+        }
+        let mut row = parse_row(&columns, line.as_str())?;
+        let previous: Vec<Option<Value>> = unique_columns
+            .iter()
+            .map(|column| row.get(&column.name).cloned())
+            .collect();
+        if !row.update_and_write_row(update_values, condition, writer)? {
+            continue;
+        }
+        updated_rows += 1;
+        for (column, previous) in unique_columns.iter().zip(previous) {
+            let new_value = row.get(&column.name).cloned().unwrap_or(Value::Null);
+            let previous = previous.unwrap_or(Value::Null);
+            if let Some((unique, kept)) = unique_values
+                .iter()
+                .zip(kept_values.iter_mut())
+                .find(|(unique, _)| unique.column == column.name)
+            {
+                if updated_rows > 1 {
+                    duplicate_value_error(&unique.column, &unique.value.to_string())?;
+                }
+                *kept = unique_value(column, &previous) == unique_value(column, &unique.value);
             }
-            let mut row = parse_row(&columns, line.as_str())?;
-            row.update_and_write_row(update_values, condition, writer)?;
+            changes.remove(&column.name, &previous);
+            changes.add(&column.name, &new_value);
         }
     }
-    Ok(())
+    if updated_rows > 0 {
+        for (unique, kept) in unique_values.iter().zip(kept_values) {
+            if unique.indexed && !kept {
+                duplicate_value_error(&unique.column, &unique.value.to_string())?;
+            }
+        }
+    }
+    Ok(updated_rows)
 }
 
+/// Reescribe la tabla sin las filas que cumplen la condición, y retorna cuántas se eliminaron.
+/// Los valores de las columnas únicas de las filas eliminadas se registran en changes.
 fn delete_rows_table(
     table_path: &str,
     writer: &mut BufWriter<File>,
+    schema: Option<&Schema>,
     condition: &Expression,
-) -> Result<(), CustomError> {
+    unique_columns: &[ColumnDefinition],
+    changes: &mut IndexChanges,
+) -> Result<usize, CustomError> {
+    let mut deleted_rows = 0;
     let table_file = open_table_path(table_path)?;
-    let mut columns: Vec<String> = vec![];
-    let table_reader = std::io::BufReader::new(table_file);
-    let mut first_line = true; // flag para saber si es la primera linea = columnas
-    for line in table_reader.lines() {
-        if line.is_err() {
-            return CustomError::error_generic("Couldn't read table file");
-        }
-        if let Ok(line) = line {
-            if first_line {
-                first_line = false;
-                columns = line.split(",").map(|s| s.to_string()).collect();
-                let row = parse_row(&columns, line.as_str())?;
-                row.write_row(writer)?;
-                continue;
-            }
+    let mut columns: Vec<ColumnDefinition> = vec![];
+    let mut table_reader = std::io::BufReader::new(table_file);
+    let mut first_line = true; // flag para saber si es el primer registro = columnas
+    while let Some(line) = read_record(&mut table_reader)? {
+        if first_line {
+            first_line = false;
+            columns = column_definitions(schema, &parse_columns(&line)?);
             let row = parse_row(&columns, line.as_str())?;
-            row.delete_or_write_row(condition, writer)?;
+            row.write_row(writer)?;
+            continue;
+        }
+        let row = parse_row(&columns, line.as_str())?;
+        if row.delete_or_write_row(condition, writer)? {
+            deleted_rows += 1;
+            for column in unique_columns {
+                if let Some(value) = row.get(&column.name) {
+                    changes.remove(&column.name, value);
+                }
+            }
         }
     }
+    Ok(deleted_rows)
+}
+
+/// Calcula las columnas de la tabla luego de aplicar una operación de ALTER TABLE.
+fn altered_columns(
+    columns: &[String],
+    operation: &AlterOperation,
+) -> Result<Vec<String>, CustomError> {
+    let mut new_columns = columns.to_vec();
+    match operation {
+        AlterOperation::AddColumn { column } => {
+            if columns.contains(&column.name) {
+                CustomError::error_invalid_column(&format!(
+                    "Column already exists: {}",
+                    column.name
+                ))?;
+            }
+            new_columns.push(column.name.to_string());
+        }
+        AlterOperation::DropColumn { column } => {
+            if !columns.contains(column) {
+                CustomError::error_invalid_column(&format!("Column not found: {}", column))?;
+            }
+            if columns.len() == 1 {
+                CustomError::error_invalid_column("Can't drop the only column of a table")?;
+            }
+            new_columns.retain(|existing| existing != column);
+        }
+        AlterOperation::RenameColumn { from, to } => {
+            if !columns.contains(from) {
+                CustomError::error_invalid_column(&format!("Column not found: {}", from))?;
+            }
+            if columns.contains(to) {
+                CustomError::error_invalid_column(&format!("Column already exists: {}", to))?;
+            }
+            for column in new_columns.iter_mut() {
+                if column == from {
+                    *column = to.to_string();
+                }
+            }
+        }
+        AlterOperation::RenameTable { .. } => {}
+    }
+    Ok(new_columns)
+}
+
+fn alter_table_rows(
+    table_path: &str,
+    writer: &mut BufWriter<File>,
+    operation: &AlterOperation,
+) -> Result<(), CustomError> {
+    let table_file = open_table_path(table_path)?;
+    let mut columns: Vec<ColumnDefinition> = vec![];
+    let mut new_columns: Vec<String> = vec![];
+    let mut rows = 0;
+    let mut table_reader = std::io::BufReader::new(table_file);
+    let mut first_line = true; // flag para saber si es el primer registro = columnas
+    while let Some(line) = read_record(&mut table_reader)? {
+        if first_line {
+            first_line = false;
+            // las celdas solo se copian, por lo que no hace falta interpretarlas según su tipo
+            let names = parse_columns(&line)?;
+            new_columns = altered_columns(&names, operation)?;
+            columns = column_definitions(None, &names);
+            let header = new_columns
+                .iter()
+                .map(|column| (column.to_string(), column.to_string()))
+                .collect();
+            Row::from_csv(&new_columns, header).write_row(writer)?;
+            continue;
+        }
+        let row = parse_row(&columns, line.as_str())?;
+        rows += 1;
+        let mut values = HashMap::new();
+        for column in &new_columns {
+            let value = match operation {
+                AlterOperation::AddColumn { column: added } if &added.name == column => {
+                    let default = added.default.clone().unwrap_or(Value::Null).to_string();
+                    added.validate(&default)?; // las filas existentes deben cumplir la definición
+                    if added.is_unique() && !default.is_empty() && rows > 1 {
+                        // todas las filas existentes recibirían el mismo valor
+                        duplicate_value_error(&added.name, &default)?;
+                    }
+                    default
+                }
+                AlterOperation::RenameColumn { from, to } if to == column => {
+                    row.text(from).unwrap_or_default()
+                }
+                _ => row.text(column).unwrap_or_default(),
+            };
+            values.insert(column.to_string(), value);
+        }
+        Row::from_csv(&new_columns, values).write_row(writer)?;
+    }
     Ok(())
 }
 
@@ -239,117 +829,415 @@ fn check_columns_to_print(
     Ok(())
 }
 
-fn select_rows_default<W: Write>(
-    table_reader: BufReader<File>,
+/// Escribe las filas que cumplen la condición a medida que se leen.
+/// Con un límite, se deja de leer la tabla al llegar a la última fila que se escribe.
+fn select_rows_default(
+    source: RowSource,
     condition: &Expression,
+    header: &[String],
     columns_to_print: &[String],
-    output: &mut W,
+    limit: Option<Limit>,
+    output: &mut dyn RowSink,
 ) -> Result<(), CustomError> {
-    let mut first_line = true;
-    let mut full_columns: Vec<String> = vec![];
-    for line in table_reader.lines() {
-        if line.is_err() {
-            return Err(CustomError::GenericError {
-                message: "Couldn't read table file".to_string(),
-            });
-        }
-        if let Ok(line) = line {
-            if first_line {
-                // si es la primera linea, guardo las columnas
-                first_line = false;
-                full_columns = line.split(",").map(|s| s.to_string()).collect();
-                check_columns_to_print(columns_to_print, &full_columns)?; // chequeo que las columnas a imprimir existan
-                let row = parse_row(&full_columns, line.as_str())?;
-                if columns_to_print.is_empty() {
-                    row.print_row(&full_columns, output)?;
-                } else {
-                    row.print_row(columns_to_print, output)?;
-                }
+    check_columns_to_print(columns_to_print, &source.names)?; // chequeo que las columnas a imprimir existan
+    if columns_to_print.is_empty() {
+        output.write_header(&source.columns)?;
+    } else {
+        output.write_header(header)?;
+    }
+    let end = limit.map_or(usize::MAX, |limit| limit.end());
+    let offset = limit.map_or(0, |limit| limit.offset);
+    let mut selected_rows = 0;
+    for row in source.rows {
+        if selected_rows >= end {
+            break;
+        }
+        let row = row?;
+        let selected = row.check_condition(condition)?;
+        if selected {
+            selected_rows += 1;
+            if selected_rows <= offset {
                 continue;
             }
-            let row = parse_row(&full_columns, line.as_str())?;
-            let selected = row.check_condition(condition)?;
-            if selected {
-                if columns_to_print.is_empty() {
-                    row.print_row(&full_columns, output)?;
-                } else {
-                    row.print_row(columns_to_print, output)?;
-                }
+            if columns_to_print.is_empty() {
+                row.print_row(&source.columns, output)?;
+            } else {
+                row.print_row(columns_to_print, output)?;
             }
         }
     }
-    Ok(())
+    output.finish()
 }
 
-fn select_rows_ordered<W: Write>(
-    table_reader: BufReader<File>,
+/// Escribe las filas que cumplen la condición en el orden de ORDER BY.
+/// Sin límite se ordenan todas, y con límite solo se guardan las primeras count + offset en un heap.
+fn select_rows_ordered(
+    source: RowSource,
     condition: &Expression,
+    header: &[String],
     columns_to_print: &mut Vec<String>,
-    order_by: &[(String, String)],
-    output: &mut W,
+    order_by: &[OrderBy],
+    limit: Option<Limit>,
+    output: &mut dyn RowSink,
 ) -> Result<(), CustomError> {
-    let mut first_line = true; // flag para saber si es la primera linea = columnas
     let mut selected_rows = vec![];
-    let mut full_columns: Vec<String> = vec![];
-    for line in table_reader.lines() {
-        if line.is_err() {
-            return Err(CustomError::GenericError {
-                message: "Couldn't read table file".to_string(),
-            });
-        }
-        if let Ok(line) = line {
-            if first_line {
-                first_line = false;
-                full_columns = line.split(",").map(|s| s.to_string()).collect();
-                if columns_to_print.is_empty() {
-                    for column in &full_columns {
-                        columns_to_print.push(column.to_string());
-                    }
+    let mut top_rows = BinaryHeap::new();
+    let mut position = 0; // el orden de lectura desempata las filas iguales, como en un ordenamiento estable
+    if columns_to_print.is_empty() {
+        columns_to_print.extend(source.columns.iter().cloned());
+        output.write_header(&source.columns)?;
+    } else {
+        output.write_header(header)?;
+    }
+    check_columns_to_print(columns_to_print, &source.names)?;
+    for row in source.rows {
+        let row = row?;
+        let selected: bool = row.check_condition(condition)?;
+        match limit {
+            Some(limit) if selected => {
+                top_rows.push(RankedRow {
+                    row,
+                    position,
+                    order_by,
+                });
+                position += 1;
+                if top_rows.len() > limit.end() {
+                    // se descarta la fila que quedaría última
+                    top_rows.pop();
                 }
-                let row = parse_row(&full_columns, line.as_str())?;
-                row.print_row(columns_to_print, output)?;
-                continue;
             }
-            let row = parse_row(&full_columns, line.as_str())?;
-            let selected: bool = row.check_condition(condition)?;
-            if selected {
-                selected_rows.push(row);
+            None if selected => selected_rows.push(row),
+            _ => {}
+        }
+    }
+    match limit {
+        Some(limit) => {
+            let ranked_rows = top_rows.into_sorted_vec();
+            for ranked_row in ranked_rows.into_iter().skip(limit.offset) {
+                ranked_row.row.print_row(columns_to_print, output)?;
+            }
+        }
+        None => {
+            order_rows(&mut selected_rows, order_by)?;
+            for row in selected_rows {
+                row.print_row(columns_to_print, output)?;
             }
         }
     }
-    order_rows(&mut selected_rows, order_by)?;
-    for row in selected_rows {
-        row.print_row(columns_to_print, output)?;
+    output.finish()
+}
+
+/// Compara dos filas según ORDER BY, donde cada clave de orden solo desempata a las anteriores.
+fn compare_rows(a: &Row, b: &Row, order_by: &[OrderBy]) -> Ordering {
+    order_by.iter().fold(Ordering::Equal, |ordering, key| {
+        ordering.then_with(|| match key.direction {
+            SortDirection::Ascending => compare_by_operand(a, b, &key.operand),
+            SortDirection::Descending => compare_by_operand(b, a, &key.operand),
+        })
+    })
+}
+
+/// Compara dos filas según el operando de una clave de ORDER BY. Una columna se compara directamente,
+/// y los demás operandos, como las funciones de agregación de un SELECT agrupado, se evalúan en cada fila.
+fn compare_by_operand(a: &Row, b: &Row, operand: &Operand) -> Ordering {
+    match operand {
+        Operand::Column(column) => a.cmp_by_column(column, b),
+        operand => {
+            let a = a.evaluate(operand).unwrap_or(Value::Null);
+            let b = b.evaluate(operand).unwrap_or(Value::Null);
+            a.compare(&b)
+        }
     }
+}
+
+fn order_rows(rows: &mut [Row], order_by: &[OrderBy]) -> Result<(), CustomError> {
+    rows.sort_by(|a, b| compare_rows(a, b, order_by));
     Ok(())
 }
 
-fn order_rows(rows: &mut [Row], order_by: &[(String, String)]) -> Result<(), CustomError> {
-    for (column, order) in order_by.iter().rev() {
-        rows.sort_by(|a, b| {
-            if order == "ASC" {
-                a.cmp_by_column(column, b)
-            } else {
-                b.cmp_by_column(column, a)
-            }
-        });
+/// Una fila con su posición de lectura, ordenada según ORDER BY para guardarla en un BinaryHeap.
+/// El heap tiene arriba a la fila que va última, que es la que se descarta al superar el límite.
+struct RankedRow<'a> {
+    row: Row,
+    position: usize,
+    order_by: &'a [OrderBy],
+}
+
+impl Ord for RankedRow<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_rows(&self.row, &other.row, self.order_by)
+            .then_with(|| self.position.cmp(&other.position))
     }
-    Ok(())
 }
 
-fn select_rows_table<W: Write>(
-    table_path: &str,
+impl PartialOrd for RankedRow<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RankedRow<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedRow<'_> {}
+
+/// Escribe las filas que cumplen la condición, con el encabezado recibido, o con las columnas de la fuente
+/// si no se eligieron columnas.
+fn select_rows(
+    source: RowSource,
     condition: &Expression,
+    header: &[String],
     columns_to_print: &mut Vec<String>,
-    order_by: &[(String, String)],
-    output: &mut W,
+    order_by: &[OrderBy],
+    limit: Option<Limit>,
+    output: &mut dyn RowSink,
 ) -> Result<(), CustomError> {
-    let table_file = open_table_path(table_path)?;
-    let table_reader = std::io::BufReader::new(table_file);
     if order_by.is_empty() {
-        select_rows_default(table_reader, condition, columns_to_print, output)?;
+        select_rows_default(source, condition, header, columns_to_print, limit, output)?;
     } else {
-        select_rows_ordered(table_reader, condition, columns_to_print, order_by, output)?;
+        select_rows_ordered(
+            source,
+            condition,
+            header,
+            columns_to_print,
+            order_by,
+            limit,
+            output,
+        )?;
     }
     Ok(())
 }
+
+/// Filtra las filas según la condición y les agrega el valor de cada columna calculada o con alias del SELECT,
+/// con el nombre que le da select_key, para poder imprimirlas, ordenarlas o descartar las repetidas.
+fn computed_rows(
+    source: RowSource,
+    condition: Expression,
+    columns: &[SelectColumn],
+) -> Result<RowSource, CustomError> {
+    let used_columns: Vec<String> = columns
+        .iter()
+        .flat_map(|column| column.operand.columns())
+        .collect();
+    check_columns_to_print(&used_columns, &source.names)?;
+    let columns = columns.to_vec();
+    let mut names = source.names;
+    names.extend(
+        columns
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.is_computed())
+            .map(|(position, column)| select_key(position, column)),
+    );
+    let rows = source.rows.filter_map(move |row| {
+        let selected = row.and_then(|row| match row.check_condition(&condition)? {
+            true => compute_columns(row, &columns).map(Some),
+            false => Ok(None),
+        });
+        selected.transpose()
+    });
+    Ok(RowSource {
+        columns: source.columns,
+        names,
+        definitions: source.definitions,
+        rows: Box::new(rows),
+    })
+}
+
+/// Retorna el nombre con que las filas guardan el valor de una columna del SELECT en la posición recibida.
+/// Las columnas calculadas o con alias se guardan según su posición, con un nombre que no puede tener una columna
+/// de una tabla, para que un alias no reemplace a una columna de la fila con el mismo nombre.
+fn select_key(position: usize, column: &SelectColumn) -> String {
+    if column.is_computed() {
+        format!("\0{}", position)
+    } else {
+        column.name()
+    }
+}
+
+/// Reemplaza las claves de ORDER BY que nombran el alias de una columna calculada o con alias del SELECT por el nombre
+/// con que las filas guardan su valor. Un alias tiene prioridad sobre una columna de la tabla con el mismo nombre.
+fn order_by_keys(order_by: Vec<OrderBy>, columns: &[SelectColumn]) -> Vec<OrderBy> {
+    order_by
+        .into_iter()
+        .map(|mut key| {
+            if let Operand::Column(name) = &key.operand {
+                let position = columns
+                    .iter()
+                    .position(|column| column.alias.as_ref() == Some(name));
+                if let Some(position) = position {
+                    key.operand = Operand::Column(select_key(position, &columns[position]));
+                }
+            }
+            key
+        })
+        .collect()
+}
+
+/// Agrega a la fila el valor de cada columna calculada o con alias del SELECT, con el nombre que le da select_key.
+fn compute_columns(mut row: Row, columns: &[SelectColumn]) -> Result<Row, CustomError> {
+    for (position, column) in columns.iter().enumerate() {
+        if !column.is_computed() {
+            continue;
+        }
+        let value = row.evaluate(&column.operand)?;
+        row.set_value(&select_key(position, column), value);
+    }
+    Ok(row)
+}
+
+/// Retorna una fila con el valor de cada operando de GROUP BY, llamado como se escribe el operando.
+/// Una columna conserva el texto original de la celda.
+fn compute_group(row: &Row, group_by: &[Operand]) -> Result<Row, CustomError> {
+    let mut group = Row::default();
+    for operand in group_by {
+        match operand {
+            Operand::Column(name) => group.copy_value(name, row, name),
+            operand => group.set_value(&operand.to_string(), row.evaluate(operand)?),
+        }
+    }
+    Ok(group)
+}
+
+/// Retorna los nombres de todas las columnas que consulta un SELECT, para verificar que no sean ambiguas.
+fn select_references(
+    columns: &[SelectColumn],
+    joins: &[Join],
+    condition: &Expression,
+    group_by: &[Operand],
+    having: &Expression,
+    order_by: &[OrderBy],
+) -> Vec<String> {
+    let mut references: Vec<String> = columns
+        .iter()
+        .flat_map(|column| column.operand.referenced_columns())
+        .collect();
+    for join in joins {
+        references.extend(join.condition.columns());
+    }
+    references.extend(condition.columns());
+    references.extend(
+        group_by
+            .iter()
+            .flat_map(|operand| operand.referenced_columns()),
+    );
+    references.extend(having.columns());
+    references.extend(
+        order_by
+            .iter()
+            .flat_map(|key| key.operand.referenced_columns()),
+    );
+    references
+}
+
+/// Retorna las funciones de agregación que usa un SELECT agrupado, sin repetir: las de las columnas,
+/// las de HAVING y las de ORDER BY, que se guardan por su nombre.
+fn select_aggregates(
+    columns: &[SelectColumn],
+    having: &Expression,
+    order_by: &[OrderBy],
+) -> Vec<Aggregate> {
+    let mut aggregates: Vec<Aggregate> = vec![];
+    let candidates = columns
+        .iter()
+        .flat_map(|column| column.operand.aggregates())
+        .chain(having.aggregates())
+        .chain(order_by.iter().flat_map(|key| key.operand.aggregates()));
+    for aggregate in candidates {
+        if !aggregates.contains(&aggregate) {
+            aggregates.push(aggregate);
+        }
+    }
+    aggregates
+}
+
+/// Selecciona las filas de la tabla agrupadas por las columnas de GROUP BY, con agregación por hash:
+/// solo se guarda en memoria un acumulador por función y grupo, no las filas de la tabla.
+/// Sin GROUP BY toda la tabla es un único grupo, que existe aunque la tabla esté vacía.
+/// Retorna una fila por grupo, filtradas con HAVING, con las columnas calculadas del SELECT
+/// y en el orden de ORDER BY, o en el orden en que aparecieron.
+fn select_rows_grouped(
+    source: RowSource,
+    condition: &Expression,
+    columns: &[SelectColumn],
+    group_by: &[Operand],
+    having: &Expression,
+    order_by: &[OrderBy],
+) -> Result<Vec<Row>, CustomError> {
+    let aggregates = select_aggregates(columns, having, order_by);
+    // las columnas agrupadas y las que se agregan deben existir
+    let mut used_columns: Vec<String> = group_by
+        .iter()
+        .flat_map(|operand| operand.columns())
+        .collect();
+    used_columns.extend(
+        aggregates
+            .iter()
+            .filter_map(|aggregate| aggregate.operand.as_ref())
+            .flat_map(|operand| operand.columns()),
+    );
+    check_columns_to_print(&used_columns, &source.names)?;
+    // cada grupo se identifica por los valores de sus columnas, en el orden en que aparece por primera vez
+    let mut group_indexes: HashMap<Vec<String>, usize> = HashMap::new();
+    // de cada grupo se guardan los valores de sus columnas, tomados de la primera fila
+    let mut groups: Vec<Row> = vec![];
+    let mut accumulators = GroupAccumulators::new(
+        aggregates.clone(),
+        DistinctSet::new(&temp_directory(), DISTINCT_MEMORY_LIMIT),
+    );
+    if group_by.is_empty() {
+        groups.push(Row::default());
+        accumulators.add_group();
+    }
+    // los valores distintos que se guardan en disco se vuelven a interpretar según el tipo de su columna,
+    // o se infieren si se agrega una expresión
+    let aggregated_columns: Vec<ColumnDefinition> = aggregates
+        .iter()
+        .map(|aggregate| match aggregate.operand.as_deref() {
+            Some(Operand::Column(column)) => source.definition(column),
+            Some(operand) => ColumnDefinition::new(&operand.to_string()),
+            None => ColumnDefinition::new("*"),
+        })
+        .collect();
+    for row in source.rows {
+        let row = row?;
+        if !row.check_condition(condition)? {
+            continue;
+        }
+        let mut key: Vec<String> = vec![];
+        for operand in group_by {
+            key.push(row.evaluate(operand)?.to_string());
+        }
+        let index = match group_indexes.get(&key) {
+            Some(index) => *index,
+            None if group_by.is_empty() => 0,
+            None => {
+                groups.push(compute_group(&row, group_by)?);
+                let index = accumulators.add_group();
+                group_indexes.insert(key, index);
+                index
+            }
+        };
+        accumulators.add_row(index, &row)?;
+    }
+    let mut result_columns: Vec<String> =
+        group_by.iter().map(|operand| operand.to_string()).collect();
+    result_columns.extend(aggregates.iter().map(|aggregate| aggregate.to_string()));
+    let mut result_rows = vec![];
+    let results = accumulators.results(&aggregated_columns)?;
+    for (group, results) in groups.iter().zip(results) {
+        let mut row = Row::new(&result_columns, HashMap::new());
+        row.extend(group);
+        for (aggregate, result) in aggregates.iter().zip(results) {
+            row.set_value(&aggregate.to_string(), result);
+        }
+        if row.check_condition(having)? {
+            result_rows.push(compute_columns(row, columns)?);
+        }
+    }
+    order_rows(&mut result_rows, order_by)?;
+    Ok(result_rows)
+}
@@ -9,6 +9,10 @@ pub enum CustomError {
     InvalidColumn { message: String },
     /// Error relacionados con sintaxis del comando.
     InvalidSyntax { message: String },
+    /// Error por una escritura que viola una restricción de la tabla, como PRIMARY KEY o UNIQUE.
+    ConstraintViolation { message: String },
+    /// Error por una tabla que otro proceso tiene bloqueada.
+    TableLocked { message: String },
     /// Error genérico.
     GenericError { message: String },
 }
@@ -19,12 +23,30 @@ impl fmt::Display for CustomError {
             CustomError::InvalidTable { message } => write!(f, "INVALID_TABLE: {}", message),
             CustomError::InvalidColumn { message } => write!(f, "INVALID_COLUMN: {}", message),
             CustomError::InvalidSyntax { message } => write!(f, "INVALID_SYNTAX: {}", message),
+            CustomError::ConstraintViolation { message } => {
+                write!(f, "CONSTRAINT_VIOLATION: {}", message)
+            }
+            CustomError::TableLocked { message } => write!(f, "TABLE_LOCKED: {}", message),
             CustomError::GenericError { message } => write!(f, "ERROR: {}", message),
         }
     }
 }
 
 impl CustomError {
+    /// Retorna el código de salida del programa para el error, distinto para cada variante:
+    /// 1 para errores genéricos, 2 para tablas, 3 para columnas, 4 para sintaxis,
+    /// 5 para restricciones violadas y 6 para tablas bloqueadas.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CustomError::GenericError { .. } => 1,
+            CustomError::InvalidTable { .. } => 2,
+            CustomError::InvalidColumn { .. } => 3,
+            CustomError::InvalidSyntax { .. } => 4,
+            CustomError::ConstraintViolation { .. } => 5,
+            CustomError::TableLocked { .. } => 6,
+        }
+    }
+
     pub fn error_invalid_syntax(message: &str) -> Result<(), CustomError> {
         Err(CustomError::InvalidSyntax {
             message: message.to_string(),
@@ -43,6 +65,12 @@ impl CustomError {
         })
     }
 
+    pub fn error_constraint_violation(message: &str) -> Result<(), CustomError> {
+        Err(CustomError::ConstraintViolation {
+            message: message.to_string(),
+        })
+    }
+
     pub fn error_generic(message: &str) -> Result<(), CustomError> {
         Err(CustomError::GenericError {
             message: message.to_string(),
@@ -0,0 +1,740 @@
+use super::command_parser::parse_statement;
+use super::command_processer::process_statement;
+use super::custom_error::CustomError;
+use super::expression::Operand;
+use super::lock::{lock_table, lock_tables, remove_lock_file, TableLock};
+use super::output_format::{OutputFormat, RowSink};
+use super::prepared::{unbound_parameter_error, PreparedStatement};
+use super::row_parser::{parse_columns, read_record};
+use super::rows::{DiscardRows, Rows};
+use super::schema::{column_definitions, ColumnDefinition, Schema};
+use super::statement::Statement;
+use super::storage::{remove_file, rename_file, replace_file, sync_directory};
+use super::tokenizer::tokenize;
+use super::unique_index::{index_path, move_index, remove_index};
+use super::wal;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Directorio donde se preparan los cambios de la transacción en curso.
+const TRANSACTION_DIRECTORY: &str = ".transaction";
+/// Archivo de la transacción con las tablas eliminadas durante ella, una por línea.
+const DROPPED_TABLES_FILE: &str = "dropped";
+/// Marca que indica que la transacción fue confirmada y solo falta publicar sus cambios.
+const COMMIT_MARKER_FILE: &str = "committed";
+/// Tiempo que se espera por defecto a que otro proceso libere una tabla.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Una Database es una sesión sobre un directorio de tablas CSV, sobre el que se ejecutan Statements.
+///
+/// Una transacción pertenece a la sesión que la empieza, y dura como máximo lo que dura la Database:
+/// BEGIN crea el directorio .transaction, y cada tabla que se modifica durante la transacción se copia ahí
+/// la primera vez que se usa. Los Statements siguientes de la sesión trabajan sobre esas copias, sin tocar las tablas originales.
+/// COMMIT deja una marca de confirmación y luego mueve las copias sobre las tablas originales.
+/// ROLLBACK descarta el directorio.
+///
+/// Cada Statement que modifica tablas, dentro o fuera de una transacción, se registra antes en un log (ver wal),
+/// para poder deshacerlo si se interrumpe.
+///
+/// Para que varios procesos puedan usar el mismo directorio, cada Statement toma locks sobre sus tablas (ver lock):
+/// compartidos si solo las lee y exclusivos si las modifica. La sesión con una transacción mantiene el lock de la
+/// transacción y los locks exclusivos de las tablas que usó hasta terminarla, por lo que las demás sesiones no pueden
+/// empezar otra transacción ni usar esas tablas, pero sí leer y modificar las demás.
+pub struct Database {
+    directory: String,
+    lock_timeout: Duration,
+    output_format: OutputFormat,
+    transaction: Mutex<Option<Transaction>>,
+}
+
+/// La transacción en curso de una sesión, con los locks que mantiene tomados hasta terminarla.
+struct Transaction {
+    _lock: TableLock,
+    table_locks: HashMap<String, TableLock>,
+}
+
+impl Transaction {
+    /// Retorna los nombres de las tablas cuyo lock la transacción todavía no tiene, sin repetir.
+    fn unlocked_tables(&self, table_names: &[String]) -> Vec<String> {
+        let mut unlocked: Vec<String> = table_names
+            .iter()
+            .filter(|table_name| !self.table_locks.contains_key(*table_name))
+            .cloned()
+            .collect();
+        unlocked.sort();
+        unlocked.dedup();
+        unlocked
+    }
+}
+
+impl Database {
+    /// Abre el directorio de tablas recibido.
+    /// Si quedó un Statement sin terminar de aplicar por un corte, se deshace usando el log.
+    /// Si quedó una transacción de una sesión que ya terminó, se terminan de publicar sus cambios si había sido confirmada,
+    /// o se descarta si no.
+    ///
+    /// El tiempo a esperar por una tabla bloqueada por otro proceso se toma en milisegundos de la variable de entorno
+    /// SQL_RUSTICO_LOCK_TIMEOUT_MS, o es DEFAULT_LOCK_TIMEOUT si no está definida.
+    pub fn open(directory: &str) -> Result<Database, CustomError> {
+        let lock_timeout = env::var("SQL_RUSTICO_LOCK_TIMEOUT_MS")
+            .ok()
+            .and_then(|millis| millis.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_LOCK_TIMEOUT);
+        Database::open_with_lock_timeout(directory, lock_timeout)
+    }
+
+    /// Abre el directorio de tablas recibido, esperando como máximo lock_timeout a que otro proceso libere una tabla.
+    /// Con un timeout de cero, un Statement sobre una tabla bloqueada falla inmediatamente.
+    pub fn open_with_lock_timeout(
+        directory: &str,
+        lock_timeout: Duration,
+    ) -> Result<Database, CustomError> {
+        let database = Database {
+            directory: directory.to_string(),
+            lock_timeout,
+            output_format: OutputFormat::default(),
+            transaction: Mutex::new(None),
+        };
+        for log_name in wal::pending_logs(directory)? {
+            // si otro proceso tiene la tabla, su Statement sigue en curso y no hay que deshacerlo
+            if let Some(_lock) =
+                skip_if_locked(lock_table(directory, &log_name, true, Duration::ZERO))?
+            {
+                wal::recover(directory, &log_name)?;
+            }
+        }
+        if fs::metadata(database.transaction_directory()).is_ok() {
+            // si otra sesión tiene el lock de la transacción, la transacción sigue en curso
+            if let Some(_lock) = skip_if_locked(database.lock_transaction(Duration::ZERO))? {
+                database.recover_transaction()?;
+            }
+        }
+        Ok(database)
+    }
+
+    /// Indica si esta sesión tiene una transacción en curso.
+    pub fn in_transaction(&self) -> bool {
+        self.transaction().is_some()
+    }
+
+    /// Cambia el formato en que se escribe el resultado de los SELECT, que por defecto es CSV.
+    pub fn set_output_format(&mut self, output_format: OutputFormat) {
+        self.output_format = output_format;
+    }
+
+    /// Ejecuta un Statement. El resultado de un SELECT se escribe en output, en el formato de la Database.
+    /// Retorna la cantidad de filas insertadas, actualizadas o eliminadas, que es 0 para los demás Statements.
+    /// Si hay una transacción en curso, el Statement trabaja sobre las copias de la transacción.
+    pub fn execute<W: Write>(
+        &self,
+        statement: Statement,
+        output: &mut W,
+    ) -> Result<usize, CustomError> {
+        self.execute_with_sink(statement, self.output_format.sink(output).as_mut())
+    }
+
+    /// Parsea y ejecuta un comando SQL, retornando la cantidad de filas afectadas como execute.
+    /// El resultado de un SELECT se descarta; para leerlo se usa query.
+    pub fn execute_sql(&self, command: &str) -> Result<usize, CustomError> {
+        let statement = parse_statement(&tokenize(command)?)?;
+        self.execute_with_sink(statement, &mut DiscardRows)
+    }
+
+    /// Ejecuta una consulta SELECT y retorna sus filas con los valores tipados, junto a la definición de sus columnas.
+    /// Las filas se leen de las tablas a medida que se recorren (ver Rows).
+    ///
+    /// # Ejemplo
+    /// ```no_run
+    /// use sql_rustico::database::Database;
+    ///
+    /// let database = Database::open("tables/")?;
+    /// let rows = database.query("SELECT Product, Price FROM supermarket;")?;
+    /// let price = rows.column_index("Price").unwrap();
+    /// for row in rows {
+    ///     let row = row?;
+    ///     println!("{} {}", row[0], row[price]);
+    /// }
+    /// # Ok::<(), sql_rustico::custom_error::CustomError>(())
+    /// ```
+    pub fn query(&self, command: &str) -> Result<Rows, CustomError> {
+        self.query_statement(parse_statement(&tokenize(command)?)?)
+    }
+
+    /// Prepara un comando SQL con parámetros para ejecutarlo con execute_prepared o query_prepared.
+    pub fn prepare(&self, command: &str) -> Result<PreparedStatement, CustomError> {
+        PreparedStatement::new(command)
+    }
+
+    /// Ejecuta un comando preparado con los valores asignados a sus parámetros, como execute.
+    pub fn execute_prepared<W: Write>(
+        &self,
+        prepared: &PreparedStatement,
+        output: &mut W,
+    ) -> Result<usize, CustomError> {
+        self.execute(prepared.statement()?, output)
+    }
+
+    /// Ejecuta una consulta preparada con los valores asignados a sus parámetros, como query.
+    pub fn query_prepared(&self, prepared: &PreparedStatement) -> Result<Rows, CustomError> {
+        self.query_statement(prepared.statement()?)
+    }
+
+    fn query_statement(&self, mut statement: Statement) -> Result<Rows, CustomError> {
+        // las tablas del SELECT con el nombre con que se califican sus columnas, si se califican
+        let tables: Vec<(String, Option<String>)> = match &statement {
+            Statement::Select {
+                table_name,
+                table_alias,
+                joins,
+                ..
+            } => {
+                let qualified = table_alias.is_some() || !joins.is_empty();
+                let mut tables = vec![(
+                    table_name.to_string(),
+                    qualified.then(|| table_alias.as_deref().unwrap_or(table_name).to_string()),
+                )];
+                for join in joins {
+                    tables.push((
+                        join.table_name.to_string(),
+                        Some(join.qualifier().to_string()),
+                    ));
+                }
+                tables
+            }
+            _ => {
+                return Err(CustomError::InvalidSyntax {
+                    message: "Only SELECT statements can be queried".to_string(),
+                })
+            }
+        };
+        check_parameters(&mut statement)?;
+        let mut table_columns = vec![];
+        for (table_name, qualifier) in tables {
+            for column in self.table_columns(&table_name)? {
+                if let Some(qualifier) = &qualifier {
+                    let mut qualified_column = column.clone();
+                    qualified_column.name = format!("{}.{}", qualifier, column.name);
+                    table_columns.push(qualified_column);
+                }
+                table_columns.push(column);
+            }
+        }
+        // los locks de la consulta se mantienen en su hilo hasta que termina
+        let (directory, table_locks) = {
+            let mut transaction = self.transaction();
+            match transaction.as_mut() {
+                Some(transaction) => self.prepare_in_transaction(transaction, &statement)?,
+                None => (
+                    self.directory.to_string(),
+                    lock_tables(
+                        &self.directory,
+                        &statement.table_names(),
+                        false,
+                        self.lock_timeout,
+                    )?,
+                ),
+            }
+        };
+        Rows::spawn(table_columns, move |output| {
+            let _table_locks = table_locks;
+            process_statement(statement, &directory, output)
+        })
+    }
+
+    fn execute_with_sink(
+        &self,
+        mut statement: Statement,
+        output: &mut dyn RowSink,
+    ) -> Result<usize, CustomError> {
+        check_parameters(&mut statement)?;
+        let table_names = statement.table_names();
+        let exclusive = !statement.is_read_only();
+        // la transacción se mantiene tomada durante todo el Statement, así los de una misma sesión se ejecutan de a uno
+        let mut transaction = self.transaction();
+        match (statement, transaction.as_mut()) {
+            (Statement::Begin, Some(_)) => {
+                CustomError::error_generic("Transaction already in progress")?;
+                Ok(0)
+            }
+            (Statement::Begin, None) => {
+                *transaction = Some(self.begin()?);
+                Ok(0)
+            }
+            (Statement::Commit | Statement::Rollback, None) => {
+                CustomError::error_generic("No transaction in progress")?;
+                Ok(0)
+            }
+            (Statement::Commit, Some(_)) => {
+                self.commit()?;
+                *transaction = None;
+                Ok(0)
+            }
+            (Statement::Rollback, Some(_)) => {
+                self.remove_transaction_directory()?;
+                *transaction = None;
+                Ok(0)
+            }
+            (statement, Some(transaction)) => {
+                self.execute_in_transaction(transaction, statement, output)
+            }
+            (statement, None) => {
+                let _table_locks =
+                    lock_tables(&self.directory, &table_names, exclusive, self.lock_timeout)?;
+                apply_statement(statement, &self.directory, output)
+            }
+        }
+    }
+
+    /// Retorna los nombres de las tablas en orden alfabético, incluyendo los cambios de la transacción en curso.
+    pub fn table_names(&self) -> Result<Vec<String>, CustomError> {
+        let mut table_names = table_files(&self.directory)?;
+        if self.in_transaction() {
+            let dropped_tables = self.dropped_tables()?;
+            table_names.retain(|table_name| !dropped_tables.contains(table_name));
+            table_names.extend(self.staged_tables()?);
+        }
+        table_names.sort();
+        table_names.dedup();
+        Ok(table_names)
+    }
+
+    /// Retorna las definiciones de las columnas de una tabla, en el orden en que aparecen en ella.
+    /// Las columnas que no figuran en el esquema de la tabla no tienen tipo ni restricciones.
+    pub fn table_columns(&self, table_name: &str) -> Result<Vec<ColumnDefinition>, CustomError> {
+        let transaction = self.transaction();
+        let _table_lock = match transaction.as_ref() {
+            Some(transaction) if transaction.table_locks.contains_key(table_name) => None,
+            _ => Some(lock_table(
+                &self.directory,
+                table_name,
+                false,
+                self.lock_timeout,
+            )?),
+        };
+        let mut directory = self.directory.to_string();
+        if transaction.is_some()
+            && (self.is_staged(table_name)
+                || self.dropped_tables()?.iter().any(|name| name == table_name))
+        {
+            directory = self.transaction_directory();
+        }
+        let header = match File::open(format!("{}/{}.csv", directory, table_name)) {
+            Ok(file) => read_record(&mut BufReader::new(file))?.unwrap_or_default(),
+            Err(_) => {
+                return Err(CustomError::InvalidTable {
+                    message: format!("Table does not exist: {}", table_name),
+                })
+            }
+        };
+        let schema = Schema::load(&directory, table_name)?;
+        Ok(column_definitions(
+            schema.as_ref(),
+            &parse_columns(&header)?,
+        ))
+    }
+
+    fn transaction(&self) -> MutexGuard<'_, Option<Transaction>> {
+        // un Statement que entró en pánico no deja la transacción a medias, ya que cada uno se aplica con el log
+        self.transaction
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Toma el lock de la transacción, que se guarda junto a los de las tablas con el nombre del directorio de la transacción.
+    fn lock_transaction(&self, timeout: Duration) -> Result<TableLock, CustomError> {
+        lock_table(&self.directory, TRANSACTION_DIRECTORY, true, timeout)
+    }
+
+    fn transaction_directory(&self) -> String {
+        format!("{}/{}", self.directory, TRANSACTION_DIRECTORY)
+    }
+
+    fn transaction_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.transaction_directory(), file_name)
+    }
+
+    fn begin(&self) -> Result<Transaction, CustomError> {
+        let lock = match self.lock_transaction(self.lock_timeout) {
+            Err(CustomError::TableLocked { .. }) => {
+                return Err(CustomError::TableLocked {
+                    message: "Another session has a transaction in progress".to_string(),
+                })
+            }
+            lock => lock?,
+        };
+        if fs::metadata(self.transaction_directory()).is_ok() {
+            // con el lock tomado, una transacción que quedó en disco es de una sesión que ya terminó
+            self.recover_transaction()?;
+        }
+        if fs::create_dir(self.transaction_directory()).is_err() {
+            CustomError::error_generic("Couldn't create transaction directory")?;
+        }
+        sync_directory(&self.directory)?;
+        Ok(Transaction {
+            _lock: lock,
+            table_locks: HashMap::new(),
+        })
+    }
+
+    /// Confirma la transacción de la sesión, que ya tiene los locks de todas las tablas que publica.
+    fn commit(&self) -> Result<(), CustomError> {
+        // una vez escrita la marca la transacción está confirmada, aunque se interrumpa la publicación
+        replace_file(
+            &self.transaction_directory(),
+            COMMIT_MARKER_FILE,
+            |_| Ok(()),
+        )?;
+        self.publish_transaction()
+    }
+
+    /// Termina la transacción que dejó en disco una sesión que ya no existe, con el lock de la transacción tomado.
+    /// Si había sido confirmada se terminan de publicar sus cambios, y si no se descarta.
+    fn recover_transaction(&self) -> Result<(), CustomError> {
+        for log_name in wal::pending_logs(&self.transaction_directory())? {
+            wal::recover(&self.transaction_directory(), &log_name)?;
+        }
+        if fs::metadata(self.transaction_path(COMMIT_MARKER_FILE)).is_err() {
+            return self.remove_transaction_directory();
+        }
+        let mut table_names = self.staged_tables()?;
+        table_names.extend(self.dropped_tables()?);
+        let _table_locks = lock_tables(&self.directory, &table_names, true, self.lock_timeout)?;
+        self.publish_transaction()
+    }
+
+    fn remove_transaction_directory(&self) -> Result<(), CustomError> {
+        if fs::remove_dir_all(self.transaction_directory()).is_err() {
+            return CustomError::error_generic("Couldn't remove transaction directory");
+        }
+        sync_directory(&self.directory)
+    }
+
+    /// Mueve las copias de la transacción sobre las tablas originales y elimina las tablas borradas durante ella.
+    /// Puede volver a ejecutarse si se interrumpe, ya que cada archivo publicado deja de estar en la transacción.
+    /// Quien la llama debe tener los locks exclusivos de las tablas de la transacción.
+    fn publish_transaction(&self) -> Result<(), CustomError> {
+        let staged_tables = self.staged_tables()?;
+        let dropped_tables = self.dropped_tables()?;
+        for table_name in staged_tables {
+            let schema_file = format!("{}.schema", table_name);
+            if fs::metadata(self.transaction_path(&schema_file)).is_ok() {
+                self.publish_file(&schema_file)?;
+            } else {
+                // la copia de la tabla no tiene esquema, así que el original tampoco debe tenerlo
+                remove_if_exists(&format!("{}/{}", self.directory, schema_file))?;
+            }
+            // el índice de la copia corresponde a la tabla publicada, y el de la tabla original ya no
+            if fs::metadata(index_path(&self.transaction_directory(), &table_name)).is_ok() {
+                move_index(
+                    &self.transaction_directory(),
+                    &table_name,
+                    &self.directory,
+                    &table_name,
+                )?;
+            } else {
+                remove_index(&self.directory, &table_name)?;
+            }
+            self.publish_file(&format!("{}.csv", table_name))?;
+        }
+        for table_name in dropped_tables {
+            remove_if_exists(&format!("{}/{}.csv", self.directory, table_name))?;
+            remove_if_exists(&format!("{}/{}.schema", self.directory, table_name))?;
+            remove_index(&self.directory, &table_name)?;
+            remove_lock_file(&self.directory, &table_name)?;
+        }
+        sync_directory(&self.directory)?;
+        self.remove_transaction_directory()
+    }
+
+    fn publish_file(&self, file_name: &str) -> Result<(), CustomError> {
+        rename_file(
+            &self.transaction_path(file_name),
+            &format!("{}/{}", self.directory, file_name),
+        )
+    }
+
+    /// Retorna los nombres de las tablas copiadas en la transacción.
+    fn staged_tables(&self) -> Result<Vec<String>, CustomError> {
+        table_files(&self.transaction_directory())
+    }
+
+    fn is_staged(&self, table_name: &str) -> bool {
+        fs::metadata(self.transaction_path(&format!("{}.csv", table_name))).is_ok()
+    }
+
+    fn dropped_tables(&self) -> Result<Vec<String>, CustomError> {
+        match File::open(self.transaction_path(DROPPED_TABLES_FILE)) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .collect::<Result<Vec<String>, io::Error>>()
+                .map_err(|_| transaction_error()),
+            Err(_) => Ok(vec![]),
+        }
+    }
+
+    fn save_dropped_tables(&self, dropped_tables: &[String]) -> Result<(), CustomError> {
+        replace_file(
+            &self.transaction_directory(),
+            DROPPED_TABLES_FILE,
+            |writer| {
+                for table_name in dropped_tables {
+                    if writeln!(writer, "{}", table_name).is_err() {
+                        return Err(transaction_error());
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Copia una tabla y su esquema a la transacción, si todavía no fue copiada ni eliminada durante ella.
+    fn stage_table(&self, table_name: &str, dropped_tables: &[String]) -> Result<(), CustomError> {
+        if self.is_staged(table_name) || dropped_tables.iter().any(|name| name == table_name) {
+            return Ok(());
+        }
+        for file_name in [
+            format!("{}.schema", table_name),
+            format!("{}.csv", table_name), // la tabla se copia al final, ya que su copia marca la tabla como copiada
+        ] {
+            let original_path = format!("{}/{}", self.directory, file_name);
+            if let Ok(mut original) = File::open(&original_path) {
+                replace_file(
+                    &self.transaction_directory(),
+                    &file_name,
+                    |writer| match io::copy(&mut original, writer) {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(transaction_error()),
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepara las tablas de un Statement de la transacción, y retorna el directorio donde debe ejecutarse
+    /// junto con los locks que deben mantenerse mientras se ejecuta.
+    /// Una consulta sobre tablas que la transacción no modificó lee las originales con locks compartidos.
+    /// Si no, las tablas se copian a la transacción, que mantiene sus locks exclusivos hasta terminar.
+    fn prepare_in_transaction(
+        &self,
+        transaction: &mut Transaction,
+        statement: &Statement,
+    ) -> Result<(String, Vec<TableLock>), CustomError> {
+        let table_names = statement.table_names();
+        let unlocked_tables = transaction.unlocked_tables(&table_names);
+        let dropped_tables = self.dropped_tables()?;
+        let untouched = table_names
+            .iter()
+            .all(|table_name| !self.is_staged(table_name) && !dropped_tables.contains(table_name));
+        if untouched && statement.is_read_only() {
+            let table_locks =
+                lock_tables(&self.directory, &unlocked_tables, false, self.lock_timeout)?;
+            return Ok((self.directory.to_string(), table_locks));
+        }
+        // los locks de las tablas que se copian se mantienen hasta terminar la transacción,
+        // para que otra sesión no modifique las originales antes de que se publiquen las copias
+        let table_locks = lock_tables(&self.directory, &unlocked_tables, true, self.lock_timeout)?;
+        transaction
+            .table_locks
+            .extend(unlocked_tables.into_iter().zip(table_locks));
+        for table_name in &table_names {
+            self.stage_table(table_name, &dropped_tables)?;
+        }
+        Ok((self.transaction_directory(), vec![]))
+    }
+
+    fn execute_in_transaction(
+        &self,
+        transaction: &mut Transaction,
+        statement: Statement,
+        output: &mut dyn RowSink,
+    ) -> Result<usize, CustomError> {
+        let table_names = statement.table_names();
+        let read_only = statement.is_read_only();
+        let (directory, _table_locks) = self.prepare_in_transaction(transaction, &statement)?;
+        let affected_rows = apply_statement(statement, &directory, output)?;
+        if read_only {
+            // una consulta no crea ni elimina tablas
+            return Ok(affected_rows);
+        }
+        let mut dropped_tables = self.dropped_tables()?;
+        for table_name in &table_names {
+            let staged = self.is_staged(table_name);
+            if staged {
+                dropped_tables.retain(|name| name != table_name);
+            } else if !dropped_tables.contains(table_name) {
+                dropped_tables.push(table_name.to_string());
+            }
+        }
+        self.save_dropped_tables(&dropped_tables)?;
+        Ok(affected_rows)
+    }
+}
+
+/// Verifica que no queden parámetros sin reemplazar en un Statement, ya que no son valores y no pueden llegar a las tablas.
+fn check_parameters(statement: &mut Statement) -> Result<(), CustomError> {
+    match statement.parameters_mut().first() {
+        Some(Operand::Parameter(parameter)) => Err(unbound_parameter_error(parameter)),
+        _ => Ok(()),
+    }
+}
+
+/// Aplica un Statement sobre las tablas de un directorio.
+/// Los Statements que modifican tablas se registran antes en el log, y si fallan se deshacen por completo.
+fn apply_statement(
+    statement: Statement,
+    directory: &str,
+    output: &mut dyn RowSink,
+) -> Result<usize, CustomError> {
+    if statement.is_read_only() {
+        return process_statement(statement, directory, output);
+    }
+    let log_name = match wal::log_name(&statement) {
+        Some(log_name) => log_name,
+        None => return process_statement(statement, directory, output),
+    };
+    wal::log_statement(directory, &log_name, &statement)?;
+    match process_statement(statement, directory, output) {
+        Ok(affected_rows) => {
+            wal::checkpoint(directory, &log_name)?;
+            Ok(affected_rows)
+        }
+        Err(error) => {
+            wal::recover(directory, &log_name)?;
+            Err(error)
+        }
+    }
+}
+
+/// Convierte el error de una tabla bloqueada en None, para poder saltear la tabla.
+fn skip_if_locked(
+    result: Result<TableLock, CustomError>,
+) -> Result<Option<TableLock>, CustomError> {
+    match result {
+        Ok(lock) => Ok(Some(lock)),
+        Err(CustomError::TableLocked { .. }) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Retorna los nombres de las tablas de un directorio, según sus archivos CSV.
+fn table_files(directory: &str) -> Result<Vec<String>, CustomError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return Err(CustomError::InvalidTable {
+                message: "Couldn't read directory".to_string(),
+            })
+        }
+    };
+    let mut table_names = vec![];
+    for entry in entries {
+        let file_name = entry.map_err(|_| transaction_error())?.file_name();
+        let file_name = file_name.to_string_lossy();
+        if let Some(table_name) = file_name.strip_suffix(".csv") {
+            if !table_name.starts_with('.') {
+                // los archivos que empiezan con '.' son temporales
+                table_names.push(table_name.to_string());
+            }
+        }
+    }
+    Ok(table_names)
+}
+
+fn transaction_error() -> CustomError {
+    CustomError::GenericError {
+        message: "Couldn't access transaction files".to_string(),
+    }
+}
+
+fn remove_if_exists(file_path: &str) -> Result<(), CustomError> {
+    if fs::metadata(file_path).is_ok() {
+        remove_file(file_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execute(database: &Database, command: &str) -> Result<String, CustomError> {
+        let tokens = crate::tokenizer::tokenize(command)?;
+        let statement = crate::command_parser::parse_statement(&tokens)?;
+        let mut output = vec![];
+        database.execute(statement, &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_rollback_discards_changes() {
+        let directory = "test_database_rollback";
+        fs::create_dir_all(directory).unwrap();
+        fs::write(format!("{}/items.csv", directory), "id,name\n1,first\n").unwrap();
+        let database = Database::open(directory).unwrap();
+        execute(&database, "BEGIN;").unwrap();
+        assert!(database.in_transaction());
+        execute(
+            &database,
+            "INSERT INTO items (id, name) VALUES (2, 'second');",
+        )
+        .unwrap();
+        assert_eq!(
+            execute(&database, "SELECT * FROM items;").unwrap(),
+            "id,name\n1,first\n2,second\n"
+        );
+        execute(&database, "ROLLBACK;").unwrap();
+        assert!(!database.in_transaction());
+        assert_eq!(
+            execute(&database, "SELECT * FROM items;").unwrap(),
+            "id,name\n1,first\n"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_commit_is_published_on_open() {
+        let directory = "test_database_recover_commit";
+        fs::create_dir_all(directory).unwrap();
+        fs::write(format!("{}/items.csv", directory), "id\n1\n").unwrap();
+        let database = Database::open(directory).unwrap();
+        execute(&database, "BEGIN;").unwrap();
+        execute(&database, "DELETE FROM items WHERE id = 1;").unwrap();
+        // simulo un corte justo después de confirmar la transacción, que termina la sesión
+        fs::write(database.transaction_path(COMMIT_MARKER_FILE), "").unwrap();
+        drop(database);
+        let database = Database::open(directory).unwrap();
+        assert!(!database.in_transaction());
+        assert_eq!(
+            fs::read_to_string(format!("{}/items.csv", directory)).unwrap(),
+            "id\n"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_transaction_statements_without_transaction() {
+        let directory = "test_database_no_transaction";
+        fs::create_dir_all(directory).unwrap();
+        let database = Database::open(directory).unwrap();
+        assert_eq!(
+            execute(&database, "COMMIT;").err().unwrap(),
+            CustomError::GenericError {
+                message: "No transaction in progress".to_string()
+            }
+        );
+        execute(&database, "BEGIN;").unwrap();
+        assert_eq!(
+            execute(&database, "BEGIN;").err().unwrap(),
+            CustomError::GenericError {
+                message: "Transaction already in progress".to_string()
+            }
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+use super::custom_error::CustomError;
+use super::expression::Expression;
+use super::join::RowSource;
+use super::row::Row;
+use super::row_parser::{format_field, parse_fields, read_record};
+use super::schema::ColumnDefinition;
+use super::storage::{create_temp_file, temp_directory};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Write};
+
+/// Memoria que puede ocupar un DistinctSet antes de empezar a guardar los valores nuevos en disco.
+pub const DISTINCT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Cantidad de particiones en que se reparten los valores guardados en disco.
+const SPILL_PARTITIONS: usize = 16;
+
+/// Cantidad máxima de veces que se vuelve a repartir una partición que no entra en memoria. A partir de ahí se lee
+/// completa igual, lo que solo ocurre si muchos valores distintos coinciden en el hash de todos los niveles.
+const MAX_SPLIT_LEVEL: usize = 8;
+
+/// Memoria estimada que ocupa cada valor en el HashSet, además de sus textos.
+const ENTRY_OVERHEAD: usize = 64;
+
+fn spill_error() -> CustomError {
+    CustomError::GenericError {
+        message: "Couldn't write temporary file".to_string(),
+    }
+}
+
+/// Memoria estimada que ocupa una fila de valores en un HashSet.
+fn entry_size(values: &[String]) -> usize {
+    ENTRY_OVERHEAD + values.iter().map(|value| value.len()).sum::<usize>()
+}
+
+/// Crea los archivos de las particiones en directory.
+fn create_partitions(directory: &str) -> Result<Vec<(String, BufWriter<File>)>, CustomError> {
+    let mut partitions = vec![];
+    for _ in 0..SPILL_PARTITIONS {
+        match create_temp_file(directory, "distinct") {
+            Ok((path, file)) => partitions.push((path, BufWriter::new(file))),
+            Err(error) => {
+                let paths: Vec<String> = partitions.into_iter().map(|(path, _)| path).collect();
+                remove_partitions(&paths);
+                return Err(error);
+            }
+        }
+    }
+    Ok(partitions)
+}
+
+/// Escribe una fila de valores en la partición que le corresponde según su hash. El nivel cambia el hash,
+/// para que las filas de una partición que se vuelve a repartir no caigan todas en la misma.
+fn write_partitioned(
+    partitions: &mut [(String, BufWriter<File>)],
+    values: &[String],
+    level: usize,
+) -> Result<(), CustomError> {
+    let mut hasher = DefaultHasher::new();
+    (level, values).hash(&mut hasher);
+    let partition = (hasher.finish() % SPILL_PARTITIONS as u64) as usize;
+    let fields: Vec<String> = values.iter().map(|value| format_field(value)).collect();
+    writeln!(partitions[partition].1, "{}", fields.join(",")).map_err(|_| spill_error())
+}
+
+/// Termina de escribir las particiones y retorna sus paths. Si alguna no se pudo escribir, las elimina todas.
+fn finish_partitions(
+    partitions: Vec<(String, BufWriter<File>)>,
+) -> Result<Vec<String>, CustomError> {
+    let mut paths = vec![];
+    let mut failed = false;
+    for (path, writer) in partitions {
+        failed |= writer.into_inner().is_err();
+        paths.push(path);
+    }
+    if failed {
+        remove_partitions(&paths);
+        return Err(spill_error());
+    }
+    Ok(paths)
+}
+
+/// Un conjunto de filas de valores que descarta las repetidas, donde dos filas son iguales si tienen el mismo texto
+/// en cada columna.
+///
+/// Las filas se guardan en memoria hasta ocupar memory_limit. A partir de ahí, las filas que no están en memoria
+/// se reparten por hash en archivos temporales, sin saber todavía si se repiten entre sí: como dos filas iguales
+/// caen en la misma partición, luego se descartan las repetidas leyendo una partición a la vez con into_spilled.
+/// Una partición con más valores distintos de los que entran en memory_limit se vuelve a repartir antes de leerla.
+#[derive(Debug)]
+pub struct DistinctSet {
+    directory: String,
+    memory_limit: usize,
+    memory_used: usize,
+    seen: HashSet<Vec<String>>,
+    /// Los archivos de las particiones, que se crean al llenarse la memoria.
+    partitions: Vec<(String, BufWriter<File>)>,
+}
+
+impl DistinctSet {
+    /// Crea un conjunto vacío, cuyos archivos temporales se crean en directory, que no debería ser el de las tablas.
+    pub fn new(directory: &str, memory_limit: usize) -> DistinctSet {
+        DistinctSet {
+            directory: directory.to_string(),
+            memory_limit,
+            memory_used: 0,
+            seen: HashSet::new(),
+            partitions: vec![],
+        }
+    }
+
+    /// Agrega una fila al conjunto. Retorna true si la fila no estaba y quedó en memoria, por lo que puede usarse enseguida.
+    /// Retorna false si la fila ya estaba, o si la memoria está llena y se guardó en disco para obtenerla con into_spilled.
+    pub fn insert(&mut self, values: &[String]) -> Result<bool, CustomError> {
+        if self.seen.contains(values) {
+            return Ok(false);
+        }
+        let size = entry_size(values);
+        if self.partitions.is_empty() && self.memory_used + size <= self.memory_limit {
+            self.memory_used += size;
+            self.seen.insert(values.to_vec());
+            return Ok(true);
+        }
+        self.spill(values)?;
+        Ok(false)
+    }
+
+    fn spill(&mut self, values: &[String]) -> Result<(), CustomError> {
+        if self.partitions.is_empty() {
+            self.partitions = create_partitions(&self.directory)?;
+        }
+        write_partitioned(&mut self.partitions, values, 0)
+    }
+
+    /// Retorna las filas que se guardaron en disco, sin repetir.
+    pub fn into_spilled(mut self) -> Result<SpilledValues, CustomError> {
+        let paths = finish_partitions(std::mem::take(&mut self.partitions))?;
+        Ok(SpilledValues {
+            directory: self.directory.to_string(),
+            memory_limit: self.memory_limit,
+            partitions: paths.into_iter().map(|path| (path, 0)).collect(),
+            current: vec![].into_iter(),
+        })
+    }
+}
+
+impl Drop for DistinctSet {
+    fn drop(&mut self) {
+        let paths: Vec<String> = self
+            .partitions
+            .iter()
+            .map(|(path, _)| path.to_string())
+            .collect();
+        remove_partitions(&paths);
+    }
+}
+
+fn remove_partitions(paths: &[String]) {
+    for path in paths {
+        let _ = fs::remove_file(path); // un temporal que no se pudo borrar no afecta al resultado
+    }
+}
+
+/// Las filas distintas que un DistinctSet guardó en disco, leídas de a una partición.
+pub struct SpilledValues {
+    directory: String,
+    memory_limit: usize,
+    /// Las particiones que falta leer, con el nivel con que se repartieron sus filas.
+    partitions: Vec<(String, usize)>,
+    current: std::vec::IntoIter<Vec<String>>,
+}
+
+impl SpilledValues {
+    /// Lee una partición y descarta sus filas repetidas. Si sus filas distintas no entran en memoria,
+    /// en lugar de leerla la vuelve a repartir en particiones más chicas, que se leen después.
+    fn read_partition(&mut self, path: &str, level: usize) -> Result<(), CustomError> {
+        let file = File::open(path).map_err(|_| spill_error())?;
+        let mut reader = BufReader::new(file);
+        let mut seen = HashSet::new();
+        let mut values = vec![];
+        let mut memory_used = 0;
+        while let Some(record) = read_record(&mut reader)? {
+            let fields = parse_fields(&record)?;
+            if seen.contains(&fields) {
+                continue;
+            }
+            memory_used += entry_size(&fields);
+            if memory_used > self.memory_limit && !seen.is_empty() && level < MAX_SPLIT_LEVEL {
+                return self.split_partition(path, level + 1);
+            }
+            seen.insert(fields.clone());
+            values.push(fields);
+        }
+        self.current = values.into_iter();
+        Ok(())
+    }
+
+    /// Reparte las filas de una partición en particiones nuevas, usando el hash del nivel recibido.
+    fn split_partition(&mut self, path: &str, level: usize) -> Result<(), CustomError> {
+        let file = File::open(path).map_err(|_| spill_error())?;
+        let mut reader = BufReader::new(file);
+        let mut partitions = create_partitions(&self.directory)?;
+        let mut result = Ok(());
+        while let Some(record) = read_record(&mut reader).transpose() {
+            result = record
+                .and_then(|record| parse_fields(&record))
+                .and_then(|fields| write_partitioned(&mut partitions, &fields, level));
+            if result.is_err() {
+                break;
+            }
+        }
+        if let Err(error) = result {
+            let paths: Vec<String> = partitions.into_iter().map(|(path, _)| path).collect();
+            remove_partitions(&paths);
+            return Err(error);
+        }
+        for path in finish_partitions(partitions)? {
+            self.partitions.push((path, level));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SpilledValues {
+    type Item = Result<Vec<String>, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(values) = self.current.next() {
+                return Some(Ok(values));
+            }
+            let (path, level) = self.partitions.pop()?;
+            let result = self.read_partition(&path, level);
+            remove_partitions(&[path]);
+            if let Err(error) = result {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+impl Drop for SpilledValues {
+    fn drop(&mut self) {
+        let paths: Vec<String> = self
+            .partitions
+            .iter()
+            .map(|(path, _)| path.to_string())
+            .collect();
+        remove_partitions(&paths);
+    }
+}
+
+/// Las filas que cumplen la condición, reducidas a las columnas elegidas y sin repetir.
+/// Las filas que entran en memoria se retornan a medida que se leen, y las demás al terminar de leer la fuente.
+struct DistinctRows {
+    source: Box<dyn Iterator<Item = Result<Row, CustomError>>>,
+    condition: Expression,
+    columns: Vec<String>,
+    /// Las definiciones de las columnas elegidas, para interpretar los valores que se guardaron en disco.
+    definitions: Vec<ColumnDefinition>,
+    set: Option<DistinctSet>,
+    spilled: Option<SpilledValues>,
+}
+
+impl DistinctRows {
+    /// Arma una fila con los valores que se guardaron en disco, que se vuelven a interpretar según el tipo de su columna.
+    fn row(&self, values: Vec<String>) -> Row {
+        Row::from_table(&self.definitions, values)
+    }
+
+    fn next_row(&mut self) -> Result<Option<Row>, CustomError> {
+        while let Some(set) = self.set.as_mut() {
+            match self.source.next() {
+                Some(row) => {
+                    let row = row?;
+                    if !row.check_condition(&self.condition)? {
+                        continue;
+                    }
+                    let values = row.selected_values(&self.columns)?;
+                    if set.insert(&values)? {
+                        return Ok(Some(row.project(&self.columns)));
+                    }
+                }
+                None => {
+                    if let Some(set) = self.set.take() {
+                        self.spilled = Some(set.into_spilled()?);
+                    }
+                }
+            }
+        }
+        match self.spilled.as_mut().and_then(|spilled| spilled.next()) {
+            Some(values) => Ok(Some(self.row(values?))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Iterator for DistinctRows {
+    type Item = Result<Row, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_row().transpose()
+    }
+}
+
+/// Retorna las filas distintas de source que cumplen la condición, con solo las columnas elegidas,
+/// o todas si columns está vacío. Los archivos temporales se crean en el directorio temporal del sistema.
+pub(crate) fn distinct_rows(
+    source: RowSource,
+    condition: &Expression,
+    columns: &[String],
+) -> RowSource {
+    let columns = if columns.is_empty() {
+        source.columns.clone()
+    } else {
+        columns.to_vec()
+    };
+    let definitions: Vec<ColumnDefinition> = columns
+        .iter()
+        .map(|column| source.definition(column))
+        .collect();
+    RowSource {
+        names: columns.clone(),
+        definitions: columns
+            .iter()
+            .cloned()
+            .zip(definitions.iter().cloned())
+            .collect(),
+        rows: Box::new(DistinctRows {
+            source: source.rows,
+            condition: condition.clone(),
+            columns: columns.clone(),
+            definitions,
+            set: Some(DistinctSet::new(&temp_directory(), DISTINCT_MEMORY_LIMIT)),
+            spilled: None,
+        }),
+        columns,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_set_spills_to_disk() {
+        let directory = "test_distinct_set_spills";
+        fs::create_dir_all(directory).unwrap();
+        // solo entran en memoria dos filas
+        let mut set = DistinctSet::new(directory, 2 * (ENTRY_OVERHEAD + 1));
+        let mut in_memory = vec![];
+        for value in ["a", "b", "a", "c", "d", "c", "b", "e,f", "e,f"] {
+            if set.insert(&[value.to_string()]).unwrap() {
+                in_memory.push(value);
+            }
+        }
+        assert_eq!(in_memory, vec!["a", "b"]);
+        assert_eq!(fs::read_dir(directory).unwrap().count(), SPILL_PARTITIONS);
+        let mut spilled: Vec<String> = set
+            .into_spilled()
+            .unwrap()
+            .map(|values| values.unwrap().join(""))
+            .collect();
+        spilled.sort();
+        assert_eq!(spilled, vec!["c", "d", "e,f"]);
+        assert_eq!(fs::read_dir(directory).unwrap().count(), 0);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_large_partition_is_split_again() {
+        let directory = "test_large_partition_is_split_again";
+        fs::create_dir_all(directory).unwrap();
+        let path = format!("{}/partition", directory);
+        fs::write(&path, "a\nb\na\nc\nb\n").unwrap();
+        // la partición tiene tres filas distintas, pero solo entran dos en memoria
+        let mut spilled = SpilledValues {
+            directory: directory.to_string(),
+            memory_limit: 2 * (ENTRY_OVERHEAD + 1),
+            partitions: vec![(path.to_string(), 0)],
+            current: vec![].into_iter(),
+        };
+        let (path, level) = spilled.partitions.pop().unwrap();
+        spilled.read_partition(&path, level).unwrap();
+        assert_eq!(spilled.current.len(), 0);
+        assert_eq!(spilled.partitions.len(), SPILL_PARTITIONS);
+        assert!(spilled.partitions.iter().all(|(_, level)| *level == 1));
+        fs::remove_file(&path).unwrap();
+        let mut values: Vec<String> = spilled.map(|values| values.unwrap().join("")).collect();
+        values.sort();
+        assert_eq!(values, vec!["a", "b", "c"]);
+        assert_eq!(fs::read_dir(directory).unwrap().count(), 0);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
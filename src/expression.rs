@@ -1,7 +1,13 @@
+use super::aggregate::Aggregate;
 use super::custom_error::CustomError;
+use super::prepared::{unbound_parameter_error, Parameter};
+use super::scalar_function::ScalarFunction;
+use super::value::Value;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 /// Una expresión puede ser evaluada como verdadera o falsa.
 pub enum Expression {
     True,
@@ -25,20 +31,212 @@ pub enum Expression {
     },
 }
 
-#[derive(Debug, PartialEq)]
-/// Los operandos son la unidadad mínima de una expresión en esta implementación.
-/// Pueden ser columnas, que consultan el valor de una columna en una fila, o valores literales limitados a Strings e Integers.
+#[derive(Debug, Clone, PartialEq)]
+/// Los operandos son expresiones que producen un valor para cada fila.
+/// Pueden ser columnas, que consultan el valor de una columna en una fila, valores literales tipados,
+/// parámetros de un comando preparado, que se reemplazan por un literal con el valor asignado antes de ejecutarlo,
+/// funciones de agregación, que consultan la columna con su nombre en una fila ya agregada,
+/// operaciones aritméticas (+, -, *, / o %) entre dos operandos, o funciones escalares aplicadas a sus argumentos.
 pub enum Operand {
     Column(String),
-    String(String),
-    Integer(String),
+    Literal(Value),
+    Parameter(Parameter),
+    Aggregate(Aggregate),
+    Arithmetic {
+        left: Box<Operand>,
+        operator: char,
+        right: Box<Operand>,
+    },
+    Function {
+        function: ScalarFunction,
+        arguments: Vec<Operand>,
+    },
+}
+
+/// Retorna la precedencia de un operador aritmético: la multiplicación, la división y el módulo
+/// se aplican antes que la suma y la resta.
+fn operator_precedence(operator: char) -> u8 {
+    match operator {
+        '*' | '/' | '%' => 2,
+        _ => 1,
+    }
+}
+
+/// Muestra un operando dentro de una operación, entre paréntesis si es otra operación que los necesita según su precedencia,
+/// para que el texto de dos operandos distintos nunca sea el mismo.
+fn nested_operand(operand: &Operand, needs_parentheses: impl Fn(u8) -> bool) -> String {
+    match operand {
+        Operand::Arithmetic { operator, .. }
+            if needs_parentheses(operator_precedence(*operator)) =>
+        {
+            format!("({})", operand)
+        }
+        operand => operand.to_string(),
+    }
+}
+
+impl fmt::Display for Operand {
+    /// Muestra el operando como se escribe en un comando, que es también el nombre de su columna en un SELECT.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Column(column) => write!(f, "{}", column),
+            Operand::Literal(value) => write!(f, "{}", value.to_sql_literal()),
+            Operand::Parameter(parameter) => write!(f, "{}", parameter),
+            Operand::Aggregate(aggregate) => write!(f, "{}", aggregate),
+            Operand::Arithmetic {
+                left,
+                operator,
+                right,
+            } => {
+                let precedence = operator_precedence(*operator);
+                // el operando derecho con la misma precedencia también va entre paréntesis,
+                // porque los operadores se agrupan de izquierda a derecha
+                write!(
+                    f,
+                    "{} {} {}",
+                    nested_operand(left, |nested| nested < precedence),
+                    operator,
+                    nested_operand(right, |nested| nested <= precedence)
+                )
+            }
+            Operand::Function {
+                function,
+                arguments,
+            } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect();
+                write!(f, "{}({})", function, arguments.join(", "))
+            }
+        }
+    }
+}
+
+impl Operand {
+    /// Retorna los operandos que contiene directamente el operando.
+    fn children(&self) -> Vec<&Operand> {
+        match self {
+            Operand::Arithmetic { left, right, .. } => vec![left, right],
+            Operand::Function { arguments, .. } => arguments.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Retorna las funciones de agregación del operando.
+    pub fn aggregates(&self) -> Vec<Aggregate> {
+        match self {
+            Operand::Aggregate(aggregate) => vec![aggregate.clone()],
+            _ => self
+                .children()
+                .into_iter()
+                .flat_map(|operand| operand.aggregates())
+                .collect(),
+        }
+    }
+
+    /// Retorna los nombres de las columnas que consulta el operando fuera de sus funciones de agregación.
+    pub fn columns(&self) -> Vec<String> {
+        match self {
+            Operand::Column(column) => vec![column.to_string()],
+            _ => self
+                .children()
+                .into_iter()
+                .flat_map(|operand| operand.columns())
+                .collect(),
+        }
+    }
+
+    /// Retorna los nombres de todas las columnas que consulta el operando, incluidas las de sus funciones de agregación.
+    pub fn referenced_columns(&self) -> Vec<String> {
+        let mut columns = self.columns();
+        columns.extend(
+            self.aggregates()
+                .into_iter()
+                .filter_map(|aggregate| aggregate.operand)
+                .flat_map(|operand| operand.columns()),
+        );
+        columns
+    }
+
+    /// Agrega a parameters referencias a los parámetros del operando, para reemplazarlos por sus valores.
+    pub(crate) fn collect_parameters_mut<'a>(&'a mut self, parameters: &mut Vec<&'a mut Operand>) {
+        match self {
+            Operand::Parameter(_) => parameters.push(self),
+            Operand::Arithmetic { left, right, .. } => {
+                left.collect_parameters_mut(parameters);
+                right.collect_parameters_mut(parameters);
+            }
+            Operand::Function { arguments, .. } => {
+                for argument in arguments {
+                    argument.collect_parameters_mut(parameters);
+                }
+            }
+            Operand::Column(_) | Operand::Literal(_) | Operand::Aggregate(_) => {}
+        }
+    }
+}
+
+impl Expression {
+    /// Retorna las funciones de agregación de la expresión.
+    pub fn aggregates(&self) -> Vec<Aggregate> {
+        match self {
+            Expression::True => vec![],
+            Expression::And { left, right } | Expression::Or { left, right } => {
+                let mut aggregates = left.aggregates();
+                aggregates.extend(right.aggregates());
+                aggregates
+            }
+            Expression::Not { right } => right.aggregates(),
+            Expression::Comparison { left, right, .. } => {
+                let mut aggregates = left.aggregates();
+                aggregates.extend(right.aggregates());
+                aggregates
+            }
+        }
+    }
+
+    /// Retorna los nombres de las columnas que consulta la expresión, incluidas las de sus funciones de agregación.
+    pub fn columns(&self) -> Vec<String> {
+        match self {
+            Expression::True => vec![],
+            Expression::And { left, right } | Expression::Or { left, right } => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Expression::Not { right } => right.columns(),
+            Expression::Comparison { left, right, .. } => {
+                let mut columns = left.referenced_columns();
+                columns.extend(right.referenced_columns());
+                columns
+            }
+        }
+    }
+
+    /// Agrega a parameters referencias a los parámetros de la expresión, para reemplazarlos por sus valores.
+    pub(crate) fn collect_parameters_mut<'a>(&'a mut self, parameters: &mut Vec<&'a mut Operand>) {
+        match self {
+            Expression::True => {}
+            Expression::And { left, right } | Expression::Or { left, right } => {
+                left.collect_parameters_mut(parameters);
+                right.collect_parameters_mut(parameters);
+            }
+            Expression::Not { right } => right.collect_parameters_mut(parameters),
+            Expression::Comparison { left, right, .. } => {
+                left.collect_parameters_mut(parameters);
+                right.collect_parameters_mut(parameters);
+            }
+        }
+    }
 }
 
 /// Evalúa una expresión dada un Hashmap de columnas y valores.
 /// Retorna un booleano que indica si la expresión es verdadera o falsa.
+/// Los valores se comparan según Value::compare.
 pub fn evaluate_expression(
     expression: &Expression,
-    row: &HashMap<String, String>,
+    row: &HashMap<String, Value>,
 ) -> Result<bool, CustomError> {
     match expression {
         Expression::True => Ok(true),
@@ -61,28 +259,13 @@ pub fn evaluate_expression(
             operator,
             right,
         } => {
-            let left_value = evaluate_operand(left, row)?;
-            let right_value = evaluate_operand(right, row)?;
-            if let Ok(left_number) = str_to_number(&left_value) {
-                if let Ok(right_number) = str_to_number(&right_value) {
-                    return match operator.as_str() {
-                        "=" => Ok(left_number == right_number),
-                        ">" => Ok(left_number > right_number),
-                        "<" => Ok(left_number < right_number),
-                        ">=" => Ok(left_number >= right_number),
-                        "<=" => Ok(left_number <= right_number),
-                        _ => Err(CustomError::GenericError {
-                            message: format!("Invalid operator: {}", operator),
-                        }),
-                    };
-                }
-            }
+            let ordering = evaluate_operand(left, row)?.compare(&evaluate_operand(right, row)?);
             match operator.as_str() {
-                "=" => Ok(left_value == right_value),
-                ">" => Ok(left_value > right_value),
-                "<" => Ok(left_value < right_value),
-                ">=" => Ok(left_value >= right_value),
-                "<=" => Ok(left_value <= right_value),
+                "=" => Ok(ordering == Ordering::Equal),
+                ">" => Ok(ordering == Ordering::Greater),
+                "<" => Ok(ordering == Ordering::Less),
+                ">=" => Ok(ordering != Ordering::Less),
+                "<=" => Ok(ordering != Ordering::Greater),
                 _ => Err(CustomError::GenericError {
                     message: format!("Invalid operator: {}", operator),
                 }),
@@ -91,31 +274,44 @@ pub fn evaluate_expression(
     }
 }
 
-fn str_to_number(s: &str) -> Result<i32, CustomError> {
-    if let Ok(number) = s.parse::<i32>() {
-        Ok(number)
-    } else {
-        Err(CustomError::GenericError {
-            message: format!("Invalid number: {}", s),
-        })
-    }
-}
-
-fn evaluate_operand(
+/// Evalúa un operando dado un HashMap de columnas y valores, retornando el valor que produce.
+pub fn evaluate_operand(
     operand: &Operand,
-    row: &HashMap<String, String>,
-) -> Result<String, CustomError> {
+    row: &HashMap<String, Value>,
+) -> Result<Value, CustomError> {
     match operand {
         Operand::Column(column_name) => {
             if let Some(value) = row.get(column_name) {
-                Ok(value.to_string())
+                Ok(value.clone())
             } else {
                 Err(CustomError::GenericError {
                     message: format!("Column not found: {}", column_name),
                 })
             }
         }
-        Operand::String(value) | Operand::Integer(value) => Ok(value.to_string()),
+        Operand::Literal(value) => Ok(value.clone()),
+        Operand::Parameter(parameter) => Err(unbound_parameter_error(parameter)),
+        Operand::Aggregate(aggregate) => match row.get(&aggregate.to_string()) {
+            Some(value) => Ok(value.clone()),
+            None => Err(CustomError::InvalidSyntax {
+                message: format!("Aggregate {} can't be used here", aggregate),
+            }),
+        },
+        Operand::Arithmetic {
+            left,
+            operator,
+            right,
+        } => evaluate_operand(left, row)?.arithmetic(*operator, &evaluate_operand(right, row)?),
+        Operand::Function {
+            function,
+            arguments,
+        } => {
+            let mut values = vec![];
+            for argument in arguments {
+                values.push(evaluate_operand(argument, row)?);
+            }
+            function.apply(&values)
+        }
     }
 }
 
@@ -126,49 +322,131 @@ mod tests {
     #[test]
     fn test_evaluate_expression() {
         let mut row = HashMap::new();
-        row.insert("column1".to_string(), "value1".to_string());
-        row.insert("column2".to_string(), "value2".to_string());
+        row.insert("column1".to_string(), Value::from_csv("value1"));
+        row.insert("column2".to_string(), Value::from_csv("value2"));
 
         let expression = Expression::Comparison {
             left: Operand::Column("column1".to_string()),
             operator: "=".to_string(),
-            right: Operand::String("value1".to_string()),
+            right: Operand::Literal(Value::Text("value1".to_string())),
         };
-        assert_eq!(evaluate_expression(&expression, &row).unwrap(), true);
+        assert!(evaluate_expression(&expression, &row).unwrap());
 
         let expression = Expression::Comparison {
             left: Operand::Column("column1".to_string()),
             operator: "=".to_string(),
-            right: Operand::String("value2".to_string()),
+            right: Operand::Literal(Value::Text("value2".to_string())),
         };
-        assert_eq!(evaluate_expression(&expression, &row).unwrap(), false);
+        assert!(!evaluate_expression(&expression, &row).unwrap());
 
         let expression = Expression::Comparison {
             left: Operand::Column("column1".to_string()),
             operator: ">".to_string(),
-            right: Operand::String("value2".to_string()),
+            right: Operand::Literal(Value::Text("value2".to_string())),
         };
-        assert_eq!(evaluate_expression(&expression, &row).unwrap(), false);
+        assert!(!evaluate_expression(&expression, &row).unwrap());
 
         let expression = Expression::Comparison {
             left: Operand::Column("column1".to_string()),
             operator: ">=".to_string(),
-            right: Operand::String("value2".to_string()),
+            right: Operand::Literal(Value::Text("value2".to_string())),
         };
-        assert_eq!(evaluate_expression(&expression, &row).unwrap(), false);
+        assert!(!evaluate_expression(&expression, &row).unwrap());
 
         let expression = Expression::Comparison {
             left: Operand::Column("column1".to_string()),
             operator: ">=".to_string(),
-            right: Operand::String("value1".to_string()),
+            right: Operand::Literal(Value::Text("value1".to_string())),
         };
-        assert_eq!(evaluate_expression(&expression, &row).unwrap(), true);
+        assert!(evaluate_expression(&expression, &row).unwrap());
 
         let expression = Expression::Comparison {
             left: Operand::Column("column1".to_string()),
             operator: "<".to_string(),
-            right: Operand::String("value2".to_string()),
+            right: Operand::Literal(Value::Text("value2".to_string())),
+        };
+        assert!(evaluate_expression(&expression, &row).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_expression_with_numbers() {
+        let mut row = HashMap::new();
+        row.insert("Price".to_string(), Value::from_csv("1.25"));
+        row.insert("Stock".to_string(), Value::from_csv("100"));
+
+        let expression = Expression::Comparison {
+            left: Operand::Column("Price".to_string()),
+            operator: "<".to_string(),
+            right: Operand::Literal(Value::Integer(2)),
+        };
+        assert!(evaluate_expression(&expression, &row).unwrap());
+
+        let expression = Expression::Comparison {
+            left: Operand::Column("Stock".to_string()),
+            operator: ">".to_string(),
+            right: Operand::Literal(Value::Integer(28)),
+        };
+        assert!(evaluate_expression(&expression, &row).unwrap());
+
+        let expression = Expression::Comparison {
+            left: Operand::Column("Stock".to_string()),
+            operator: "=".to_string(),
+            right: Operand::Literal(Value::Integer(10000000000)),
+        };
+        assert!(!evaluate_expression(&expression, &row).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_computed_operand() {
+        let mut row = HashMap::new();
+        row.insert("Price".to_string(), Value::from_csv("2.5"));
+        row.insert("Stock".to_string(), Value::from_csv("4"));
+        row.insert("Name".to_string(), Value::from_csv("pen"));
+
+        let operand = Operand::Arithmetic {
+            left: Box::new(Operand::Column("Price".to_string())),
+            operator: '*',
+            right: Box::new(Operand::Column("Stock".to_string())),
+        };
+        assert_eq!(operand.to_string(), "Price * Stock");
+
+        let sum = Operand::Arithmetic {
+            left: Box::new(Operand::Column("Price".to_string())),
+            operator: '+',
+            right: Box::new(Operand::Literal(Value::Integer(1))),
+        };
+        let grouped_left = Operand::Arithmetic {
+            left: Box::new(sum.clone()),
+            operator: '*',
+            right: Box::new(Operand::Column("Stock".to_string())),
+        };
+        assert_eq!(grouped_left.to_string(), "(Price + 1) * Stock");
+        let grouped_right = Operand::Arithmetic {
+            left: Box::new(Operand::Column("Stock".to_string())),
+            operator: '-',
+            right: Box::new(sum),
+        };
+        assert_eq!(grouped_right.to_string(), "Stock - (Price + 1)");
+        assert_eq!(
+            evaluate_operand(&operand, &row).unwrap(),
+            Value::Float(10.0)
+        );
+
+        let operand = Operand::Function {
+            function: ScalarFunction::Concat,
+            arguments: vec![
+                Operand::Function {
+                    function: ScalarFunction::Upper,
+                    arguments: vec![Operand::Column("Name".to_string())],
+                },
+                Operand::Literal(Value::Text("-".to_string())),
+                Operand::Column("Stock".to_string()),
+            ],
         };
-        assert_eq!(evaluate_expression(&expression, &row).unwrap(), true);
+        assert_eq!(operand.to_string(), "CONCAT(UPPER(Name), '-', Stock)");
+        assert_eq!(
+            evaluate_operand(&operand, &row).unwrap(),
+            Value::Text("PEN-4".to_string())
+        );
     }
 }
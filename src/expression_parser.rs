@@ -1,6 +1,9 @@
+use super::aggregate::{Aggregate, AggregateFunction};
 use super::custom_error::CustomError;
 use super::expression::{Expression, Operand};
+use super::scalar_function::ScalarFunction;
 use super::tokenizer::Token;
+use super::value::Value;
 use std::iter::Peekable;
 use std::slice::Iter;
 
@@ -63,7 +66,13 @@ fn parse_not_expression(tokens: &mut Peekable<Iter<Token>>) -> Result<Expression
 
 fn parse_primary_expression(tokens: &mut Peekable<Iter<Token>>) -> Result<Expression, CustomError> {
     if let Some(Token::Symbol('(')) = tokens.peek() {
-        // Si se abre paréntesis, se parsea la expresión que está adentro por completo
+        // El paréntesis puede abrir un operando aritmético, como en (Price + 1) > 2
+        let mut lookahead = tokens.clone();
+        if let Ok(comparison) = parse_comparison_expression(&mut lookahead) {
+            *tokens = lookahead;
+            return Ok(comparison);
+        }
+        // Si no, se parsea la expresión que está adentro por completo
         tokens.next();
         let expression = parse_expression(tokens)?;
         if let Some(Token::Symbol(')')) = tokens.next() {
@@ -83,7 +92,12 @@ fn parse_comparison_expression(
 ) -> Result<Expression, CustomError> {
     if let Some(token) = tokens.peek() {
         match token {
-            Token::Identifier(_) | Token::String(_) | Token::Integer(_) => {
+            token
+                if matches!(
+                    token,
+                    Token::Identifier(_) | Token::Keyword(_) | Token::Symbol('(')
+                ) || is_literal(token) =>
+            {
                 // Se parsea un operando
                 let left = parse_operand(tokens)?;
                 if let Some(Token::ComparisonOperator(op)) = tokens.next() {
@@ -108,12 +122,65 @@ fn parse_comparison_expression(
     })
 }
 
-fn parse_operand(tokens: &mut Peekable<Iter<Token>>) -> Result<Operand, CustomError> {
+/// Parsea un operando dado un iterador de tokens, retornando un Operand que se estructura en forma de árbol.
+/// El orden de precedencia de los operadores aritméticos es el siguiente:
+/// *, / y %, luego + y -
+pub fn parse_operand(tokens: &mut Peekable<Iter<Token>>) -> Result<Operand, CustomError> {
+    let mut operand = parse_multiplicative_operand(tokens)?; // entra en la de siguiente precedencia
+    while let Some(Token::Symbol(operator @ ('+' | '-'))) = tokens.peek() {
+        tokens.next();
+        let right = parse_multiplicative_operand(tokens)?;
+        operand = Operand::Arithmetic {
+            left: Box::new(operand),
+            operator: *operator,
+            right: Box::new(right),
+        };
+    }
+    Ok(operand)
+}
+
+fn parse_multiplicative_operand(
+    tokens: &mut Peekable<Iter<Token>>,
+) -> Result<Operand, CustomError> {
+    let mut operand = parse_primary_operand(tokens)?;
+    while let Some(Token::Symbol(operator @ ('*' | '/' | '%'))) = tokens.peek() {
+        tokens.next();
+        let right = parse_primary_operand(tokens)?;
+        operand = Operand::Arithmetic {
+            left: Box::new(operand),
+            operator: *operator,
+            right: Box::new(right),
+        };
+    }
+    Ok(operand)
+}
+
+fn parse_primary_operand(tokens: &mut Peekable<Iter<Token>>) -> Result<Operand, CustomError> {
+    if let Some(Token::Symbol('(')) = tokens.peek() {
+        // Si se abre paréntesis, se parsea el operando que está adentro por completo
+        tokens.next();
+        let operand = parse_operand(tokens)?;
+        if let Some(Token::Symbol(')')) = tokens.next() {
+            return Ok(operand);
+        } else {
+            return Err(CustomError::InvalidSyntax {
+                message: "Missing closing ')'".to_string(),
+            });
+        }
+    }
+    if let Some(aggregate) = parse_aggregate(tokens)? {
+        return Ok(Operand::Aggregate(aggregate));
+    }
+    if let Some(function) = parse_function(tokens)? {
+        return Ok(function);
+    }
     if let Some(token) = tokens.next() {
         match token {
-            Token::Identifier(string) => return Ok(Operand::Column(string.to_string())),
-            Token::String(string) => return Ok(Operand::String(string.to_string())),
-            Token::Integer(int) => return Ok(Operand::Integer(int.to_string())),
+            literal if is_literal(literal) => return parse_value(literal),
+            // Una palabra clave que no es un literal solo puede ser el nombre de una columna, como key
+            Token::Identifier(string) | Token::Keyword(string) => {
+                return Ok(Operand::Column(string.to_string()))
+            }
             other => {
                 return Err(CustomError::InvalidSyntax {
                     message: format!("Invalid operand {:?}", other),
@@ -126,13 +193,160 @@ fn parse_operand(tokens: &mut Peekable<Iter<Token>>) -> Result<Operand, CustomEr
     })
 }
 
+/// Parsea una función de agregación como COUNT(*), SUM(Price * Stock) o COUNT(DISTINCT column), si es lo que sigue en los tokens.
+/// El operando de la función no puede contener otra función de agregación.
+/// Si los tokens no empiezan con el nombre de una función seguido de '(', no se consume ninguno y se retorna None.
+pub fn parse_aggregate(
+    tokens: &mut Peekable<Iter<Token>>,
+) -> Result<Option<Aggregate>, CustomError> {
+    let mut lookahead = tokens.clone();
+    let function = match (lookahead.next(), lookahead.next()) {
+        (Some(Token::Identifier(name)), Some(Token::Symbol('('))) => {
+            match AggregateFunction::from_name(name) {
+                Some(function) => function,
+                None => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+    tokens.next();
+    tokens.next();
+    let distinct = matches!(tokens.peek(), Some(Token::Keyword(keyword)) if keyword.eq_ignore_ascii_case("DISTINCT"));
+    if distinct {
+        tokens.next();
+    }
+    let operand = match tokens.peek() {
+        Some(Token::Symbol('*')) if function == AggregateFunction::Count && !distinct => {
+            tokens.next();
+            None
+        }
+        None | Some(Token::Symbol('*' | ')')) => {
+            return Err(CustomError::InvalidSyntax {
+                message: format!("Expected column name in {}()", function),
+            })
+        }
+        _ => {
+            let operand = parse_operand(tokens)?;
+            if !operand.aggregates().is_empty() {
+                return Err(CustomError::InvalidSyntax {
+                    message: format!("Aggregate functions can't be nested in {}()", function),
+                });
+            }
+            Some(Box::new(operand))
+        }
+    };
+    if !matches!(tokens.next(), Some(Token::Symbol(')'))) {
+        return Err(CustomError::InvalidSyntax {
+            message: format!("Missing closing ')' in {}()", function),
+        });
+    }
+    Ok(Some(Aggregate {
+        function,
+        operand,
+        distinct,
+    }))
+}
+
+/// Parsea una función escalar como UPPER(name) o ROUND(Price * 1.21, 2), si es lo que sigue en los tokens.
+/// Si los tokens no empiezan con un nombre seguido de '(', no se consume ninguno y se retorna None.
+fn parse_function(tokens: &mut Peekable<Iter<Token>>) -> Result<Option<Operand>, CustomError> {
+    let mut lookahead = tokens.clone();
+    let function = match (lookahead.next(), lookahead.next()) {
+        (Some(Token::Identifier(name)), Some(Token::Symbol('('))) => {
+            match ScalarFunction::from_name(name) {
+                Some(function) => function,
+                None => {
+                    return Err(CustomError::InvalidSyntax {
+                        message: format!("Unknown function {}", name),
+                    })
+                }
+            }
+        }
+        _ => return Ok(None),
+    };
+    tokens.next();
+    tokens.next();
+    let mut arguments = vec![parse_operand(tokens)?];
+    while let Some(Token::Symbol(',')) = tokens.peek() {
+        tokens.next();
+        arguments.push(parse_operand(tokens)?);
+    }
+    if !matches!(tokens.next(), Some(Token::Symbol(')'))) {
+        return Err(CustomError::InvalidSyntax {
+            message: format!("Missing closing ')' in {}()", function),
+        });
+    }
+    function.check_arguments(arguments.len())?;
+    Ok(Some(Operand::Function {
+        function,
+        arguments,
+    }))
+}
+
+/// Retorna el nombre que representa un token en una posición donde se espera el nombre de una tabla o columna.
+/// Además de los identificadores y strings se aceptan palabras clave, para poder usar nombres como table o key.
+pub fn name_of(token: &Token) -> Option<&str> {
+    match token {
+        Token::Identifier(name) | Token::String(name) | Token::Keyword(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// Indica si un token es un valor literal: un String, un número, un parámetro o alguna de las palabras clave NULL, TRUE y FALSE.
+pub fn is_literal(token: &Token) -> bool {
+    match token {
+        Token::String(_) | Token::Integer(_) | Token::Float(_) | Token::Parameter(_) => true,
+        Token::Keyword(keyword) => {
+            ["NULL", "TRUE", "FALSE"].contains(&keyword.to_uppercase().as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Convierte un token literal en el Operand correspondiente: un parámetro es un Operand::Parameter,
+/// y los demás literales un Operand::Literal con su Value.
+pub fn parse_value(token: &Token) -> Result<Operand, CustomError> {
+    match token {
+        Token::Parameter(parameter) => Ok(Operand::Parameter(parameter.clone())),
+        literal => Ok(Operand::Literal(parse_literal(literal)?)),
+    }
+}
+
+/// Convierte un token literal en el Value correspondiente. Un parámetro no tiene Value, por lo que es un error.
+/// Los Strings se mantienen como Text, sin inferir su tipo.
+pub fn parse_literal(token: &Token) -> Result<Value, CustomError> {
+    match token {
+        Token::String(string) => Ok(Value::Text(string.to_string())),
+        Token::Integer(int) => match int.parse::<i64>() {
+            Ok(integer) => Ok(Value::Integer(integer)),
+            Err(_) => Err(CustomError::InvalidSyntax {
+                message: format!("Integer out of range: {}", int),
+            }),
+        },
+        Token::Float(float) => match float.parse::<f64>() {
+            Ok(float) if float.is_finite() => Ok(Value::Float(float)),
+            _ => Err(CustomError::InvalidSyntax {
+                message: format!("Number out of range: {}", float),
+            }),
+        },
+        Token::Keyword(keyword) if keyword.eq_ignore_ascii_case("NULL") => Ok(Value::Null),
+        Token::Keyword(keyword) if keyword.eq_ignore_ascii_case("TRUE") => Ok(Value::Boolean(true)),
+        Token::Keyword(keyword) if keyword.eq_ignore_ascii_case("FALSE") => {
+            Ok(Value::Boolean(false))
+        }
+        other => Err(CustomError::InvalidSyntax {
+            message: format!("Invalid value {:?}", other),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_parse_expression() {
-        let tokens = vec![
+        let tokens = [
             Token::Identifier("column1".to_string()),
             Token::ComparisonOperator("=".to_string()),
             Token::String("value1".to_string()),
@@ -146,14 +360,14 @@ mod tests {
             Expression::Comparison {
                 left: Operand::Column("column1".to_string()),
                 operator: "=".to_string(),
-                right: Operand::String("value1".to_string())
+                right: Operand::Literal(Value::Text("value1".to_string()))
             }
         );
     }
 
     #[test]
     fn test_parse_expression_invalid_syntax() {
-        let tokens = vec![Token::Identifier("column1".to_string())];
+        let tokens = [Token::Identifier("column1".to_string())];
 
         let result = parse_expression(&mut tokens.iter().peekable());
 
@@ -168,7 +382,7 @@ mod tests {
 
     #[test]
     fn test_parse_expression_missing_parenthesis() {
-        let tokens = vec![
+        let tokens = [
             Token::LogicalOperator("NOT".to_string()),
             Token::Symbol('('),
             Token::Identifier("column1".to_string()),
@@ -189,7 +403,7 @@ mod tests {
 
     #[test]
     fn test_parse_expression_invalid_operand() {
-        let tokens = vec![
+        let tokens = [
             Token::Identifier("column1".to_string()),
             Token::ComparisonOperator("=".to_string()),
             Token::LogicalOperator("AND".to_string()),
@@ -205,4 +419,111 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(
+            parse_literal(&Token::Integer("9780061120084".to_string())).unwrap(),
+            Value::Integer(9780061120084)
+        );
+        assert_eq!(
+            parse_literal(&Token::Keyword("NULL".to_string())).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            parse_literal(&Token::Integer("99999999999999999999".to_string()))
+                .err()
+                .unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Integer out of range: 99999999999999999999".to_string()
+            }
+        );
+        assert_eq!(
+            parse_literal(&Token::Float("-2.5e-3".to_string())).unwrap(),
+            Value::Float(-0.0025)
+        );
+        assert!(parse_literal(&Token::Float("1e400".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_parse_operand_arithmetic() {
+        let tokens = [
+            Token::Identifier("Price".to_string()),
+            Token::Symbol('+'),
+            Token::Identifier("Tax".to_string()),
+            Token::Symbol('*'),
+            Token::Symbol('('),
+            Token::Identifier("Stock".to_string()),
+            Token::Symbol('-'),
+            Token::Integer("1".to_string()),
+            Token::Symbol(')'),
+        ];
+
+        let result = parse_operand(&mut tokens.iter().peekable()).unwrap();
+
+        assert_eq!(
+            result,
+            Operand::Arithmetic {
+                left: Box::new(Operand::Column("Price".to_string())),
+                operator: '+',
+                right: Box::new(Operand::Arithmetic {
+                    left: Box::new(Operand::Column("Tax".to_string())),
+                    operator: '*',
+                    right: Box::new(Operand::Arithmetic {
+                        left: Box::new(Operand::Column("Stock".to_string())),
+                        operator: '-',
+                        right: Box::new(Operand::Literal(Value::Integer(1))),
+                    }),
+                }),
+            }
+        );
+        assert_eq!(result.to_string(), "Price + Tax * (Stock - 1)");
+    }
+
+    #[test]
+    fn test_parse_expression_with_functions() {
+        let tokens = [
+            Token::Symbol('('),
+            Token::Identifier("Price".to_string()),
+            Token::Symbol('*'),
+            Token::Integer("2".to_string()),
+            Token::Symbol(')'),
+            Token::ComparisonOperator(">".to_string()),
+            Token::Identifier("LENGTH".to_string()),
+            Token::Symbol('('),
+            Token::Identifier("Name".to_string()),
+            Token::Symbol(')'),
+        ];
+
+        let result = parse_expression(&mut tokens.iter().peekable()).unwrap();
+
+        assert_eq!(
+            result,
+            Expression::Comparison {
+                left: Operand::Arithmetic {
+                    left: Box::new(Operand::Column("Price".to_string())),
+                    operator: '*',
+                    right: Box::new(Operand::Literal(Value::Integer(2))),
+                },
+                operator: ">".to_string(),
+                right: Operand::Function {
+                    function: ScalarFunction::Length,
+                    arguments: vec![Operand::Column("Name".to_string())],
+                },
+            }
+        );
+
+        let tokens = [
+            Token::Identifier("FOO".to_string()),
+            Token::Symbol('('),
+            Token::Identifier("Name".to_string()),
+            Token::Symbol(')'),
+        ];
+        assert_eq!(
+            parse_operand(&mut tokens.iter().peekable()),
+            Err(CustomError::InvalidSyntax {
+                message: "Unknown function FOO".to_string()
+            })
+        );
+    }
 }
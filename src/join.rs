@@ -0,0 +1,433 @@
+use super::command_processer::open_table_path;
+use super::custom_error::CustomError;
+use super::expression::{Expression, Operand};
+use super::row::Row;
+use super::row_parser::{parse_columns, parse_row, read_record};
+use super::schema::{column_definitions, ColumnDefinition, Schema};
+use super::statement::{Join, JoinType};
+use super::value::{parse_date, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::BufReader;
+use std::rc::Rc;
+
+type RowIterator = Box<dyn Iterator<Item = Result<Row, CustomError>>>;
+
+/// Las posiciones de las filas de la derecha de un join por su clave.
+type JoinIndex = HashMap<Vec<String>, Vec<usize>>;
+
+/// Las filas que recorre un SELECT, leídas de una tabla o combinadas de varias con JOIN.
+pub(crate) struct RowSource {
+    /// Las columnas que se muestran con SELECT *, en orden.
+    pub columns: Vec<String>,
+    /// Todos los nombres con que se puede consultar una columna de las filas.
+    pub names: Vec<String>,
+    /// Las definiciones de las columnas de las tablas por cada nombre con que se consultan.
+    /// Las columnas calculadas no tienen definición.
+    pub definitions: HashMap<String, ColumnDefinition>,
+    pub rows: RowIterator,
+}
+
+impl RowSource {
+    /// Retorna la definición de una columna, o una sin tipo si la columna no pertenece a una tabla.
+    pub fn definition(&self, name: &str) -> ColumnDefinition {
+        self.definitions
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| ColumnDefinition::new(name))
+    }
+}
+
+/// Recorre las filas de una tabla sin guardarlas en memoria, interpretando cada valor según el tipo de su columna.
+pub(crate) fn scan_table(directory: &str, table_name: &str) -> Result<RowSource, CustomError> {
+    let table_path = format!("{}/{}.csv", directory, table_name);
+    let mut reader = BufReader::new(open_table_path(&table_path)?);
+    let columns = match read_record(&mut reader)? {
+        Some(header) => parse_columns(&header)?,
+        None => {
+            return Err(CustomError::InvalidTable {
+                message: "Couldn't read table file".to_string(),
+            })
+        }
+    };
+    let schema = Schema::load(directory, table_name)?;
+    let row_columns = column_definitions(schema.as_ref(), &columns);
+    let definitions = row_columns
+        .iter()
+        .map(|column| (column.name.to_string(), column.clone()))
+        .collect();
+    let rows = std::iter::from_fn(move || match read_record(&mut reader) {
+        Ok(Some(line)) => Some(parse_row(&row_columns, &line)),
+        Ok(None) => None,
+        Err(error) => Some(Err(error)),
+    });
+    Ok(RowSource {
+        names: columns.clone(),
+        columns,
+        definitions,
+        rows: Box::new(rows),
+    })
+}
+
+/// Una tabla de un SELECT con joins y el nombre con que se califican sus columnas.
+#[derive(Clone)]
+struct QualifiedTable {
+    qualifier: String,
+    columns: Vec<String>,
+    definitions: HashMap<String, ColumnDefinition>,
+}
+
+impl QualifiedTable {
+    fn qualified_columns(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|column| format!("{}.{}", self.qualifier, column))
+            .collect()
+    }
+}
+
+/// Los nombres de las columnas de todas las tablas de un SELECT con joins.
+/// Cada columna se llama <qualifier>.<column>, y también <column> si ninguna otra tabla tiene una columna con ese nombre.
+struct ColumnNames {
+    qualified: Vec<String>,
+    unqualified: HashMap<String, String>,
+    ambiguous: HashSet<String>,
+}
+
+impl ColumnNames {
+    fn new(tables: &[QualifiedTable]) -> ColumnNames {
+        let mut unqualified = HashMap::new();
+        let mut ambiguous = HashSet::new();
+        for table in tables {
+            for (column, qualified) in table.columns.iter().zip(table.qualified_columns()) {
+                if unqualified.insert(column.to_string(), qualified).is_some() {
+                    ambiguous.insert(column.to_string());
+                }
+            }
+        }
+        for column in &ambiguous {
+            unqualified.remove(column);
+        }
+        ColumnNames {
+            qualified: tables
+                .iter()
+                .flat_map(|table| table.qualified_columns())
+                .collect(),
+            unqualified,
+            ambiguous,
+        }
+    }
+
+    /// Retorna el nombre calificado de una columna, si existe.
+    fn resolve(&self, name: &str) -> Option<&str> {
+        match self.unqualified.get(name) {
+            Some(qualified) => Some(qualified),
+            None => self
+                .qualified
+                .iter()
+                .find(|qualified| *qualified == name)
+                .map(|qualified| qualified.as_str()),
+        }
+    }
+
+    /// Todos los nombres con que se puede consultar una columna: primero los calificados en orden, y luego los demás.
+    fn names(&self) -> Vec<String> {
+        let mut names = self.qualified.clone();
+        for qualified in &self.qualified {
+            let column = self
+                .unqualified
+                .iter()
+                .find(|(_, name)| *name == qualified)
+                .map(|(column, _)| column.to_string());
+            names.extend(column);
+        }
+        names
+    }
+
+    /// Retorna las definiciones de las columnas de todas las tablas por cada nombre con que se consultan.
+    fn definitions(&self, tables: &[QualifiedTable]) -> HashMap<String, ColumnDefinition> {
+        let mut definitions = HashMap::new();
+        for table in tables {
+            for (column, qualified) in table.columns.iter().zip(table.qualified_columns()) {
+                if let Some(definition) = table.definitions.get(column) {
+                    if self.unqualified.contains_key(column) {
+                        definitions.insert(column.to_string(), definition.clone());
+                    }
+                    definitions.insert(qualified, definition.clone());
+                }
+            }
+        }
+        definitions
+    }
+
+    /// Retorna una fila de la tabla con los nombres de sus columnas en el SELECT.
+    fn qualify(&self, table: &QualifiedTable, row: &Row) -> Row {
+        let mut qualified_row = Row::default();
+        for (column, qualified) in table.columns.iter().zip(table.qualified_columns()) {
+            if self.unqualified.contains_key(column) {
+                qualified_row.copy_value(column, row, column);
+            }
+            qualified_row.copy_value(&qualified, row, column);
+        }
+        qualified_row
+    }
+
+    /// Retorna una fila de la tabla con todas sus columnas en NULL, para completar un LEFT o RIGHT JOIN.
+    fn nulls(&self, table: &QualifiedTable) -> Row {
+        let values = table
+            .columns
+            .iter()
+            .map(|column| (column.to_string(), Value::Null))
+            .collect();
+        self.qualify(table, &Row::new(&table.columns, values))
+    }
+}
+
+/// Combina las filas de una tabla con las de las tablas de los joins, en orden.
+/// Las filas de la primera tabla se recorren sin guardarlas, y las de cada tabla de un join se guardan en memoria.
+/// Si alguna de las columnas referenciadas es ambigua, se retorna un error.
+pub(crate) fn join_tables(
+    directory: &str,
+    table_name: &str,
+    table_alias: &Option<String>,
+    joins: &[Join],
+    references: &[String],
+) -> Result<RowSource, CustomError> {
+    let source = scan_table(directory, table_name)?;
+    let mut tables = vec![QualifiedTable {
+        qualifier: table_alias.as_deref().unwrap_or(table_name).to_string(),
+        columns: source.columns.clone(),
+        definitions: source.definitions.clone(),
+    }];
+    let mut joined_rows = vec![];
+    for join in joins {
+        // las filas de cada tabla de un join se recorren una vez por cada fila de la izquierda
+        let joined = scan_table(directory, &join.table_name)?;
+        tables.push(QualifiedTable {
+            qualifier: join.qualifier().to_string(),
+            columns: joined.columns,
+            definitions: joined.definitions,
+        });
+        joined_rows.push(joined.rows.collect::<Result<Vec<Row>, CustomError>>()?);
+    }
+    let names = Rc::new(ColumnNames::new(&tables));
+    for reference in references {
+        if names.ambiguous.contains(reference) {
+            CustomError::error_invalid_column(&format!("Column {} is ambiguous", reference))?;
+        }
+    }
+    let first_table = tables[0].clone();
+    let first_names = Rc::clone(&names);
+    let mut rows: RowIterator = Box::new(
+        source
+            .rows
+            .map(move |row| Ok(first_names.qualify(&first_table, &row?))),
+    );
+    let mut left_nulls = names.nulls(&tables[0]);
+    for ((join, table), right_rows) in joins.iter().zip(&tables[1..]).zip(joined_rows) {
+        let right_rows: Vec<Row> = right_rows
+            .iter()
+            .map(|row| names.qualify(table, row))
+            .collect();
+        let right_nulls = names.nulls(table);
+        let hash_keys = equality_keys(&join.condition, &names, &left_nulls, &right_nulls);
+        let mut joined = JoinedRows {
+            left: rows,
+            join_type: join.join_type,
+            condition: join.condition.clone(),
+            matched: vec![false; right_rows.len()],
+            index: None,
+            right_rows,
+            left_nulls: left_nulls.clone(),
+            right_nulls: right_nulls.clone(),
+            pending: VecDeque::new(),
+            left_finished: false,
+        };
+        if !hash_keys.is_empty() {
+            joined.build_index(hash_keys);
+        }
+        rows = Box::new(joined);
+        left_nulls.extend(&right_nulls);
+    }
+    let row_names = names.names();
+    Ok(RowSource {
+        columns: names.qualified.clone(),
+        names: row_names.clone(),
+        definitions: names.definitions(&tables),
+        rows: Box::new(rows.map(move |row| Ok(row?.project(&row_names)))),
+    })
+}
+
+/// Retorna los pares (columna de la izquierda, columna de la derecha) de las igualdades entre columnas
+/// que forman la condición de un join unidas con AND, con las que se puede hacer un hash join.
+fn equality_keys(
+    condition: &Expression,
+    names: &ColumnNames,
+    left_nulls: &Row,
+    right_nulls: &Row,
+) -> Vec<(String, String)> {
+    match condition {
+        Expression::And { left, right } => {
+            let mut keys = equality_keys(left, names, left_nulls, right_nulls);
+            keys.extend(equality_keys(right, names, left_nulls, right_nulls));
+            keys
+        }
+        Expression::Comparison {
+            left: Operand::Column(first),
+            operator,
+            right: Operand::Column(second),
+        } if operator == "=" => match (names.resolve(first), names.resolve(second)) {
+            (Some(first), Some(second))
+                if left_nulls.get(first).is_some() && right_nulls.get(second).is_some() =>
+            {
+                vec![(first.to_string(), second.to_string())]
+            }
+            (Some(first), Some(second))
+                if right_nulls.get(first).is_some() && left_nulls.get(second).is_some() =>
+            {
+                vec![(second.to_string(), first.to_string())]
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// Normaliza un valor para el hash join, de modo que dos valores iguales según Value::compare
+/// tengan la misma clave. Dos valores distintos pueden compartir clave, por lo que la condición se evalúa igual.
+fn join_key(value: &Value) -> String {
+    // un Text se compara como fecha, número o booleano con los valores de ese tipo
+    let value = match value {
+        Value::Text(text) => parse_date(text).map_or_else(|| Value::from_csv(text), Value::Date),
+        value => value.clone(),
+    };
+    match value {
+        Value::Null => String::new(),
+        Value::Boolean(boolean) => format!("b{}", boolean),
+        Value::Date(date) => format!("d{}", date),
+        value => match value.as_f64() {
+            Some(number) => format!("n{}", number + 0.0), // sumar 0.0 convierte -0.0 en 0.0
+            None => format!("t{}", value),
+        },
+    }
+}
+
+/// Las filas de un join, que combinan cada fila de la izquierda con las filas de la derecha que cumplen la condición.
+/// Si la condición incluye igualdades entre columnas de ambos lados, solo se prueban las filas de la derecha
+/// con los mismos valores, buscándolas en un índice. Si no, se prueban todas.
+struct JoinedRows {
+    left: RowIterator,
+    join_type: JoinType,
+    condition: Expression,
+    right_rows: Vec<Row>,
+    /// Las columnas de la izquierda que forman la clave, y las filas de la derecha por clave.
+    index: Option<(Vec<String>, JoinIndex)>,
+    /// Si cada fila de la derecha se combinó con alguna, para completar un RIGHT JOIN.
+    matched: Vec<bool>,
+    left_nulls: Row,
+    right_nulls: Row,
+    pending: VecDeque<Row>,
+    left_finished: bool,
+}
+
+impl JoinedRows {
+    fn build_index(&mut self, keys: Vec<(String, String)>) {
+        let (left_keys, right_keys): (Vec<String>, Vec<String>) = keys.into_iter().unzip();
+        let mut index = JoinIndex::new();
+        for (position, row) in self.right_rows.iter().enumerate() {
+            index
+                .entry(key_of(row, &right_keys))
+                .or_default()
+                .push(position);
+        }
+        self.index = Some((left_keys, index));
+    }
+
+    /// Agrega a pending las combinaciones de una fila de la izquierda.
+    fn join_row(&mut self, left_row: Row) -> Result<(), CustomError> {
+        let candidates: Vec<usize> = match &self.index {
+            Some((left_keys, index)) => index
+                .get(&key_of(&left_row, left_keys))
+                .cloned()
+                .unwrap_or_default(),
+            None => (0..self.right_rows.len()).collect(),
+        };
+        let mut found = false;
+        for position in candidates {
+            let mut row = left_row.clone();
+            row.extend(&self.right_rows[position]);
+            if row.check_condition(&self.condition)? {
+                found = true;
+                self.matched[position] = true;
+                self.pending.push_back(row);
+            }
+        }
+        if !found && self.join_type == JoinType::Left {
+            let mut row = left_row;
+            row.extend(&self.right_nulls);
+            self.pending.push_back(row);
+        }
+        Ok(())
+    }
+}
+
+fn key_of(row: &Row, columns: &[String]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| join_key(row.get(column).unwrap_or(&Value::Null)))
+        .collect()
+}
+
+impl Iterator for JoinedRows {
+    type Item = Result<Row, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.pending.pop_front() {
+                return Some(Ok(row));
+            }
+            if self.left_finished {
+                return None;
+            }
+            match self.left.next() {
+                Some(Ok(left_row)) => {
+                    if let Err(error) = self.join_row(left_row) {
+                        return Some(Err(error));
+                    }
+                }
+                Some(Err(error)) => return Some(Err(error)),
+                None => {
+                    self.left_finished = true;
+                    if self.join_type == JoinType::Right {
+                        // las filas de la derecha sin combinaciones se completan con NULL a la izquierda
+                        for (position, row) in self.right_rows.iter().enumerate() {
+                            if !self.matched[position] {
+                                let mut unmatched = self.left_nulls.clone();
+                                unmatched.extend(row);
+                                self.pending.push_back(unmatched);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_key_matches_equal_values() {
+        let key = |raw: &str| join_key(&Value::from_csv(raw));
+        assert_eq!(key("1"), key("1.0"));
+        assert_eq!(key("-0.0"), key("0"));
+        assert_eq!(key("TRUE"), key("true"));
+        assert_ne!(key("1"), key("'1'"));
+        assert_ne!(key(""), key("0"));
+        assert_eq!(
+            join_key(&Value::Text("7".to_string())),
+            join_key(&Value::Integer(7))
+        );
+    }
+}
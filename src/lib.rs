@@ -1,8 +1,25 @@
+pub mod aggregate;
 pub mod command_parser;
 pub mod command_processer;
 pub mod custom_error;
+pub mod database;
+pub mod distinct;
 pub mod expression;
 pub mod expression_parser;
+pub mod join;
+pub mod lock;
+pub mod output_format;
+pub mod prepared;
+pub mod repl;
 pub mod row;
 pub mod row_parser;
+pub mod rows;
+pub mod scalar_function;
+pub mod schema;
+pub mod script;
+pub mod statement;
+pub mod storage;
 pub mod tokenizer;
+pub mod unique_index;
+pub mod value;
+pub mod wal;
@@ -0,0 +1,237 @@
+use super::custom_error::CustomError;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::ErrorKind;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Tiempo entre dos intentos de tomar un lock ocupado.
+const RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Un lock consultivo sobre una tabla, que se libera al descartarlo.
+///
+/// Los locks se toman sobre un archivo <directory>/.<table_name>.lock y no sobre la tabla misma,
+/// ya que las tablas se reemplazan por archivos nuevos al modificarse.
+/// Son consultivos: solo protegen de otros procesos que también los tomen.
+/// El archivo existe solo mientras algún proceso tiene el lock: quien lo libera último lo elimina, por lo que no quedan
+/// archivos de lock en el directorio, tampoco de tablas que no existen.
+/// Un lock sin archivo no protege nada, y se usa cuando no hay una tabla que proteger.
+pub struct TableLock {
+    /// El archivo de lock abierto y su path.
+    file: Option<(File, String)>,
+}
+
+impl Drop for TableLock {
+    fn drop(&mut self) {
+        if let Some((file, path)) = &self.file {
+            // solo con el lock exclusivo se sabe que ningún otro proceso tiene el lock, y quien esté esperando
+            // por él vuelve a abrir el archivo al ver que se eliminó
+            if file.unlock().is_ok() && file.try_lock().is_ok() && is_current(file, path) {
+                let _ = fs::remove_file(path); // un archivo de lock que queda no afecta a los demás locks
+            }
+        }
+    }
+}
+
+fn lock_path(directory: &str, table_name: &str) -> String {
+    format!("{}/.{}.lock", directory, table_name)
+}
+
+fn table_locked_error(table_name: &str) -> CustomError {
+    CustomError::TableLocked {
+        message: format!("Table {} is locked by another process", table_name),
+    }
+}
+
+/// Toma un lock sobre una tabla, compartido para leerla o exclusivo para modificarla.
+/// Si otro proceso tiene el lock, reintenta hasta que pase el timeout; con un timeout de cero falla en el primer intento.
+///
+/// Si la tabla no existe y no se puede crear su archivo de lock, no se toma ningún lock y el Statement reporta el error.
+pub fn lock_table(
+    directory: &str,
+    table_name: &str,
+    exclusive: bool,
+    timeout: Duration,
+) -> Result<TableLock, CustomError> {
+    let start = Instant::now();
+    loop {
+        let file = match open_lock_file(directory, table_name, exclusive)? {
+            Some(file) => file,
+            None => return Ok(TableLock { file: None }),
+        };
+        wait_for_lock(&file, table_name, exclusive, start, timeout)?;
+        let path = lock_path(directory, table_name);
+        if is_current(&file, &path) {
+            return Ok(TableLock {
+                file: Some((file, path)),
+            });
+        }
+        // el archivo se eliminó mientras se esperaba, porque la tabla se borró o se renombró
+    }
+}
+
+/// Abre el archivo de lock de una tabla, o retorna None si no hace falta tomar el lock.
+/// Para un lock compartido se abre en modo lectura, así se pueden leer tablas de un directorio de solo lectura.
+fn open_lock_file(
+    directory: &str,
+    table_name: &str,
+    exclusive: bool,
+) -> Result<Option<File>, CustomError> {
+    let path = lock_path(directory, table_name);
+    let table_exists = fs::metadata(format!("{}/{}.csv", directory, table_name)).is_ok();
+    if !exclusive {
+        if let Ok(file) = File::open(&path) {
+            return Ok(Some(file));
+        }
+        if !table_exists {
+            // no hay nada que leer, y no se deja un archivo de lock para una tabla inexistente
+            return Ok(None);
+        }
+    }
+    match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+    {
+        Ok(file) => Ok(Some(file)),
+        // si no existe el directorio, ni la tabla ni su lock pueden existir
+        Err(_) if !table_exists => Ok(None),
+        // si no se puede crear el archivo, ningún proceso puede modificar la tabla y alcanza con leerla
+        Err(_) if !exclusive => Ok(None),
+        Err(_) => Err(CustomError::GenericError {
+            message: format!("Couldn't open lock file for table {}", table_name),
+        }),
+    }
+}
+
+fn wait_for_lock(
+    file: &File,
+    table_name: &str,
+    exclusive: bool,
+    start: Instant,
+    timeout: Duration,
+) -> Result<(), CustomError> {
+    loop {
+        let result = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+        match result {
+            Ok(()) => return Ok(()),
+            Err(TryLockError::WouldBlock) if start.elapsed() < timeout => {
+                thread::sleep(RETRY_INTERVAL.min(timeout - start.elapsed()));
+            }
+            Err(TryLockError::WouldBlock) => return Err(table_locked_error(table_name)),
+            Err(TryLockError::Error(_)) => {
+                return Err(CustomError::GenericError {
+                    message: format!("Couldn't lock table {}", table_name),
+                })
+            }
+        }
+    }
+}
+
+/// Indica si el archivo abierto sigue siendo el archivo de lock de la tabla, y no uno que se eliminó.
+#[cfg(unix)]
+fn is_current(file: &File, path: &str) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(opened), Ok(current)) => opened.dev() == current.dev() && opened.ino() == current.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_current(_file: &File, path: &str) -> bool {
+    fs::metadata(path).is_ok()
+}
+
+/// Elimina el archivo de lock de una tabla que se borró o se renombró.
+/// Debe llamarse con el lock exclusivo de la tabla tomado; quien esté esperando por él vuelve a abrir el archivo.
+pub fn remove_lock_file(directory: &str, table_name: &str) -> Result<(), CustomError> {
+    match fs::remove_file(lock_path(directory, table_name)) {
+        Err(error) if error.kind() != ErrorKind::NotFound => Err(CustomError::GenericError {
+            message: format!("Couldn't remove lock file for table {}", table_name),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Toma locks sobre varias tablas, siempre en orden alfabético para que dos procesos no se esperen mutuamente.
+pub fn lock_tables(
+    directory: &str,
+    table_names: &[String],
+    exclusive: bool,
+    timeout: Duration,
+) -> Result<Vec<TableLock>, CustomError> {
+    let mut table_names = table_names.to_vec();
+    table_names.sort();
+    table_names.dedup();
+    table_names
+        .iter()
+        .map(|table_name| lock_table(directory, table_name, exclusive, timeout))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive_lock_conflicts() {
+        let directory = "test_lock_conflicts";
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(format!("{}/items.csv", directory), "id\n").unwrap();
+        let shared = lock_table(directory, "items", false, Duration::ZERO).unwrap();
+        assert!(lock_table(directory, "items", false, Duration::ZERO).is_ok());
+        assert_eq!(
+            lock_table(directory, "items", true, Duration::from_millis(30))
+                .err()
+                .unwrap(),
+            CustomError::TableLocked {
+                message: "Table items is locked by another process".to_string()
+            }
+        );
+        drop(shared);
+        assert!(lock_table(directory, "items", true, Duration::ZERO).is_ok());
+        // al liberar el último lock se elimina su archivo
+        assert!(std::fs::metadata(lock_path(directory, "items")).is_err());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_lock_missing_table() {
+        let directory = "test_lock_missing_table";
+        assert!(lock_table(directory, "items", false, Duration::ZERO).is_ok());
+        assert!(lock_table(directory, "items", true, Duration::ZERO).is_ok());
+        std::fs::create_dir_all(directory).unwrap();
+        assert!(lock_table(directory, "items", false, Duration::ZERO).is_ok());
+        assert!(std::fs::metadata(lock_path(directory, "items")).is_err());
+        // el archivo de lock de una tabla inexistente solo existe mientras se tiene el lock
+        let exclusive = lock_table(directory, "items", true, Duration::ZERO).unwrap();
+        assert!(std::fs::metadata(lock_path(directory, "items")).is_ok());
+        drop(exclusive);
+        assert!(std::fs::metadata(lock_path(directory, "items")).is_err());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_removed_lock_file_is_reopened() {
+        let directory = "test_lock_removed";
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(format!("{}/items.csv", directory), "id\n").unwrap();
+        let exclusive = lock_table(directory, "items", true, Duration::ZERO).unwrap();
+        let waiter = thread::spawn(move || {
+            lock_table(directory, "items", false, Duration::from_secs(5)).unwrap()
+        });
+        thread::sleep(Duration::from_millis(50));
+        remove_lock_file(directory, "items").unwrap();
+        drop(exclusive);
+        let shared = waiter.join().unwrap();
+        // el lector tiene el lock del archivo nuevo, así que un escritor no puede tomarlo
+        assert!(lock_table(directory, "items", true, Duration::ZERO).is_err());
+        drop(shared);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
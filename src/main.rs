@@ -1,27 +1,146 @@
-use sql_rustico::command_processer::process_command;
 use sql_rustico::custom_error::CustomError;
+use sql_rustico::database::Database;
+use sql_rustico::output_format::OutputFormat;
+use sql_rustico::repl::run_repl;
+use sql_rustico::script::{run_script, run_statement};
 use std::env;
+use std::io::Read;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage:
+  sql_rustico <directory> \"<SQL command>\" [--format <format>]
+  sql_rustico <directory> -f <script.sql | -> [--continue-on-error] [--format <format>]
+  sql_rustico <directory> [--format <format>]
+  sql_rustico --help
+
+Sin comando se inicia un REPL interactivo. Con -f se ejecuta un script, o la entrada estándar con \"-f -\".
+Los resultados de los SELECT se escriben en el formato elegido: csv (por defecto), table, json, ndjson o markdown.";
+
+/// Código de salida para argumentos inválidos, distinto de los códigos de CustomError.
+const USAGE_EXIT_CODE: u8 = 64;
+
+/// Lee un script desde un archivo, o desde la entrada estándar si el path es "-".
+fn read_script(path: &str) -> Result<String, CustomError> {
+    let mut script = String::new();
+    let result = if path == "-" {
+        std::io::stdin().read_to_string(&mut script).map(|_| ())
+    } else {
+        std::fs::read_to_string(path).map(|contents| script = contents)
+    };
+    if result.is_err() {
+        return Err(CustomError::GenericError {
+            message: format!("Couldn't read script: {}", path),
+        });
+    }
+    Ok(script)
+}
+
+/// Escribe el error en stderr y retorna su código de salida.
+fn report_error(error: CustomError) -> ExitCode {
+    eprintln!("{}", error);
+    ExitCode::from(error.exit_code())
+}
+
+/// Saca de los argumentos la opción --format y su valor, y retorna el formato elegido o CSV si no se eligió ninguno.
+fn take_format(args: &mut Vec<String>) -> Result<OutputFormat, &'static str> {
+    let index = match args.iter().position(|arg| arg == "--format") {
+        Some(index) => index,
+        None => return Ok(OutputFormat::default()),
+    };
+    let output_format = match args.get(index + 1) {
+        Some(name) => OutputFormat::from_name(name).ok_or("Unknown output format")?,
+        None => return Err("No format provided after --format"),
+    };
+    args.drain(index..index + 2);
+    Ok(output_format)
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{}\n{}", message, USAGE);
+    ExitCode::from(USAGE_EXIT_CODE)
+}
 
 /// Recibe los argumentos de la línea de comandos y los procesa.
 /// Se espera como argumentos el directorio de las tablas y el comando SQL a ejecutar.
+/// Si solo se recibe el directorio, se inicia un REPL interactivo sobre sus tablas.
+/// Con -f se ejecutan en orden los comandos de un script, leído de un archivo o de la entrada estándar con "-f -".
+/// Por defecto el script se detiene en el primer error, salvo que se agregue --continue-on-error.
+/// Con --format se elige el formato de los resultados de los SELECT.
+///
+/// Los errores se escriben en stderr, y el código de salida es el de CustomError::exit_code,
+/// o 64 si los argumentos son inválidos.
 ///
 /// # Ejemplo
 /// ```sh
 /// cargo run tables/ "SELECT * FROM table1;"
+/// cargo run tables/
+/// cargo run tables/ -f migrations.sql --continue-on-error
+/// cargo run tables/ "SELECT * FROM table1;" --format json
 /// ```
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        println!(
-            "{}",
-            CustomError::GenericError {
-                message: ("No command provided".to_string()),
-            }
-        );
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().collect();
+    if args
+        .iter()
+        .skip(1)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
     }
+    let output_format = match take_format(&mut args) {
+        Ok(output_format) => output_format,
+        Err(message) => return usage_error(message),
+    };
+    let directory = match args.get(1) {
+        Some(directory) => directory,
+        None => return usage_error("No directory provided"),
+    };
+    // sin comando se inicia el REPL, y con -f se ejecuta un script
+    let (command, script) = match args.get(2).map(|arg| arg.as_str()) {
+        None => (None, None),
+        Some("-f") => match &args[3..] {
+            [path] => (None, Some((path, true))),
+            [path, flag] if flag == "--continue-on-error" => (None, Some((path, false))),
+            [] => return usage_error("No script provided after -f"),
+            _ => return usage_error("Unexpected arguments after script"),
+        },
+        Some(_) if args.len() > 3 => return usage_error("Unexpected arguments after command"),
+        Some(command) => (Some(command), None),
+    };
+    let mut database = match Database::open(directory) {
+        Ok(database) => database,
+        Err(error) => return report_error(error),
+    };
+    database.set_output_format(output_format);
     let mut stdout = std::io::stdout();
-    let command_process_result = process_command(&args, &mut stdout);
-    if let Err(error) = command_process_result {
-        println!("{}", error);
+    let result = match (command, script) {
+        (Some(command), _) => run_statement(&database, command, &mut stdout),
+        (None, Some((path, stop_on_error))) => match read_script(path) {
+            Ok(script) => {
+                // los errores de los comandos del script se informan en stderr con su número
+                return match run_script(
+                    &database,
+                    &script,
+                    stop_on_error,
+                    &mut stdout,
+                    &mut std::io::stderr(),
+                ) {
+                    Ok(()) => ExitCode::SUCCESS,
+                    Err(error) => ExitCode::from(error.exit_code()),
+                };
+            }
+            Err(error) => Err(error),
+        },
+        (None, None) => run_repl(
+            &database,
+            std::io::stdin().lock(),
+            &mut stdout,
+            &mut std::io::stderr(),
+        ),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => report_error(error),
     }
 }
@@ -0,0 +1,411 @@
+use super::custom_error::CustomError;
+use super::row_parser::format_field;
+use super::value::Value;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// Los formatos en que se puede escribir el resultado de un SELECT.
+pub enum OutputFormat {
+    /// CSV según RFC 4180, con comillas solo en los valores que las necesitan.
+    #[default]
+    Csv,
+    /// Tabla ASCII con las columnas alineadas.
+    Table,
+    /// Un array JSON con un objeto por fila.
+    Json,
+    /// Un objeto JSON por línea.
+    Ndjson,
+    /// Tabla de Markdown.
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Retorna el formato correspondiente a un nombre, sin importar mayúsculas:
+    /// csv, table, json, ndjson o markdown.
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(OutputFormat::Csv),
+            "table" => Some(OutputFormat::Table),
+            "json" => Some(OutputFormat::Json),
+            "ndjson" => Some(OutputFormat::Ndjson),
+            "markdown" | "md" => Some(OutputFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    /// Crea el RowSink que escribe filas en este formato sobre output.
+    pub fn sink<'a, W: Write>(&self, output: &'a mut W) -> Box<dyn RowSink + 'a> {
+        match self {
+            OutputFormat::Csv => Box::new(CsvSink { output }),
+            OutputFormat::Table => Box::new(TableSink {
+                output,
+                columns: vec![],
+                rows: vec![],
+            }),
+            OutputFormat::Json => Box::new(JsonSink {
+                output,
+                columns: vec![],
+                rows_written: 0,
+                array: true,
+            }),
+            OutputFormat::Ndjson => Box::new(JsonSink {
+                output,
+                columns: vec![],
+                rows_written: 0,
+                array: false,
+            }),
+            OutputFormat::Markdown => Box::new(MarkdownSink { output }),
+        }
+    }
+}
+
+/// Un RowSink recibe el resultado de un SELECT y lo escribe en algún formato.
+/// Primero recibe las columnas con write_header, luego cada fila con write_row, y por último finish.
+/// Las filas llegan con sus valores tipados, y cada formato decide cómo mostrar cada tipo.
+pub trait RowSink {
+    fn write_header(&mut self, columns: &[String]) -> Result<(), CustomError>;
+    fn write_row(&mut self, values: &[Value]) -> Result<(), CustomError>;
+    fn finish(&mut self) -> Result<(), CustomError>;
+}
+
+fn write_output<W: Write + ?Sized>(output: &mut W, text: &str) -> Result<(), CustomError> {
+    if output.write_all(text.as_bytes()).is_err() {
+        return CustomError::error_generic("Couldn't write output");
+    }
+    Ok(())
+}
+
+struct CsvSink<'a, W: Write> {
+    output: &'a mut W,
+}
+
+/// Retorna el texto con que se muestra un valor. Los Float se muestran con a lo sumo 15 dígitos significativos,
+/// para que el error de redondeo de una operación no aparezca en el resultado: 0.1 * 3 se muestra como 0.3
+/// y no como 0.30000000000000004. Este redondeo es solo para mostrar: las tablas guardan el valor completo.
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Float(float) => {
+            let rounded = format!("{:.14e}", float).parse::<f64>().unwrap_or(*float);
+            format!("{:?}", rounded)
+        }
+        value => value.to_string(),
+    }
+}
+
+impl<W: Write> CsvSink<'_, W> {
+    fn write_record(&mut self, values: &[String]) -> Result<(), CustomError> {
+        let fields: Vec<String> = values.iter().map(|value| format_field(value)).collect();
+        write_output(self.output, &format!("{}\n", fields.join(",")))
+    }
+}
+
+impl<W: Write> RowSink for CsvSink<'_, W> {
+    fn write_header(&mut self, columns: &[String]) -> Result<(), CustomError> {
+        self.write_record(columns)
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<(), CustomError> {
+        let texts: Vec<String> = values.iter().map(display_value).collect();
+        self.write_record(&texts)
+    }
+
+    fn finish(&mut self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+/// Para alinear las columnas se necesita el ancho de todos los valores,
+/// por lo que las filas se guardan en memoria hasta el final.
+struct TableSink<'a, W: Write> {
+    output: &'a mut W,
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl<W: Write> TableSink<'_, W> {
+    fn write_separator(&mut self, widths: &[usize]) -> Result<(), CustomError> {
+        let dashes: Vec<String> = widths.iter().map(|width| "-".repeat(width + 2)).collect();
+        write_output(self.output, &format!("+{}+\n", dashes.join("+")))
+    }
+
+    fn write_line(&mut self, values: &[String], widths: &[usize]) -> Result<(), CustomError> {
+        let cells: Vec<String> = values
+            .iter()
+            .zip(widths)
+            .map(|(value, width)| {
+                format!(" {}{} ", value, " ".repeat(width - value.chars().count()))
+            })
+            .collect();
+        write_output(self.output, &format!("|{}|\n", cells.join("|")))
+    }
+}
+
+/// Los saltos de línea de un valor se muestran escapados para no romper la tabla.
+fn table_cell(value: &str) -> String {
+    value.replace('\r', "\\r").replace('\n', "\\n")
+}
+
+impl<W: Write> RowSink for TableSink<'_, W> {
+    fn write_header(&mut self, columns: &[String]) -> Result<(), CustomError> {
+        self.columns = columns.iter().map(|column| table_cell(column)).collect();
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<(), CustomError> {
+        self.rows.push(
+            values
+                .iter()
+                .map(|value| table_cell(&display_value(value)))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), CustomError> {
+        if self.columns.is_empty() {
+            // una tabla sin encabezado no tiene nada que mostrar
+            return Ok(());
+        }
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .map(|column| column.chars().count())
+            .collect();
+        for row in &self.rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+        let columns = std::mem::take(&mut self.columns);
+        let rows = std::mem::take(&mut self.rows);
+        self.write_separator(&widths)?;
+        self.write_line(&columns, &widths)?;
+        self.write_separator(&widths)?;
+        for row in &rows {
+            self.write_line(row, &widths)?;
+        }
+        if !rows.is_empty() {
+            self.write_separator(&widths)?;
+        }
+        Ok(())
+    }
+}
+
+/// Escribe un String como string JSON, escapando comillas, barras y caracteres de control.
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Convierte un valor en un valor JSON según su tipo: Null es null, los números y booleanos mantienen su tipo,
+/// y los Text son siempre strings, aunque su texto parezca un número. Los Float que no son finitos no existen
+/// en JSON y se escriben como null.
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::Integer(integer) => integer.to_string(),
+        Value::Float(float) if float.is_finite() => display_value(value),
+        Value::Float(_) => "null".to_string(),
+        Value::Text(text) | Value::Date(text) => json_string(text),
+    }
+}
+
+struct JsonSink<'a, W: Write> {
+    output: &'a mut W,
+    columns: Vec<String>,
+    rows_written: usize,
+    /// Si es true se escribe un array JSON, si no, un objeto por línea.
+    array: bool,
+}
+
+impl<W: Write> RowSink for JsonSink<'_, W> {
+    fn write_header(&mut self, columns: &[String]) -> Result<(), CustomError> {
+        self.columns = columns.to_vec();
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<(), CustomError> {
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| format!("{}:{}", json_string(column), json_value(value)))
+            .collect();
+        let object = format!("{{{}}}", fields.join(","));
+        let line = match (self.array, self.rows_written) {
+            (true, 0) => format!("[\n{}", object),
+            (true, _) => format!(",\n{}", object),
+            (false, _) => format!("{}\n", object),
+        };
+        self.rows_written += 1;
+        write_output(self.output, &line)
+    }
+
+    fn finish(&mut self) -> Result<(), CustomError> {
+        match (self.array, self.rows_written) {
+            (true, 0) => write_output(self.output, "[]\n"),
+            (true, _) => write_output(self.output, "\n]\n"),
+            (false, _) => Ok(()),
+        }
+    }
+}
+
+struct MarkdownSink<'a, W: Write> {
+    output: &'a mut W,
+}
+
+/// Escapa los caracteres que romperían una celda de una tabla de Markdown.
+fn markdown_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+impl<W: Write> MarkdownSink<'_, W> {
+    fn write_line(&mut self, cells: &[String]) -> Result<(), CustomError> {
+        write_output(self.output, &format!("| {} |\n", cells.join(" | ")))
+    }
+}
+
+impl<W: Write> RowSink for MarkdownSink<'_, W> {
+    fn write_header(&mut self, columns: &[String]) -> Result<(), CustomError> {
+        let header: Vec<String> = columns.iter().map(|column| markdown_cell(column)).collect();
+        self.write_line(&header)?;
+        self.write_line(&vec!["---".to_string(); columns.len()])
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<(), CustomError> {
+        let cells: Vec<String> = values
+            .iter()
+            .map(|value| markdown_cell(&display_value(value)))
+            .collect();
+        self.write_line(&cells)
+    }
+
+    fn finish(&mut self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+
+    fn render(format: OutputFormat, rows: &[Vec<Value>]) -> String {
+        let mut output = vec![];
+        let mut sink = format.sink(&mut output);
+        let columns = vec!["id".to_string(), "name".to_string()];
+        sink.write_header(&columns).unwrap();
+        for row in rows {
+            sink.write_row(row).unwrap();
+        }
+        sink.finish().unwrap();
+        drop(sink);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_csv_quotes_values() {
+        assert_eq!(
+            render(
+                OutputFormat::Csv,
+                &[vec![Value::Integer(1), text("Garcia, Ltd.")]]
+            ),
+            "id,name\n1,\"Garcia, Ltd.\"\n"
+        );
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        assert_eq!(
+            render(
+                OutputFormat::Table,
+                &[
+                    vec![Value::Integer(1), text("first")],
+                    vec![Value::Integer(10), Value::Null]
+                ]
+            ),
+            "+----+-------+\n| id | name  |\n+----+-------+\n| 1  | first |\n| 10 |       |\n+----+-------+\n"
+        );
+    }
+
+    #[test]
+    fn test_json_and_ndjson_types() {
+        assert_eq!(
+            render(
+                OutputFormat::Json,
+                &[
+                    vec![Value::Integer(1), text("say \"hi\"")],
+                    vec![Value::Float(2.5), Value::Null]
+                ]
+            ),
+            "[\n{\"id\":1,\"name\":\"say \\\"hi\\\"\"},\n{\"id\":2.5,\"name\":null}\n]\n"
+        );
+        assert_eq!(render(OutputFormat::Json, &[]), "[]\n");
+        assert_eq!(
+            render(
+                OutputFormat::Ndjson,
+                &[vec![text("007"), Value::Boolean(true)]]
+            ),
+            "{\"id\":\"007\",\"name\":true}\n"
+        );
+        // un Text conserva su tipo aunque su texto parezca un número o un booleano
+        assert_eq!(
+            render(
+                OutputFormat::Ndjson,
+                &[vec![text("9780306406157"), text("true")]]
+            ),
+            "{\"id\":\"9780306406157\",\"name\":\"true\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_floats_are_rounded_for_display() {
+        assert_eq!(
+            render(
+                OutputFormat::Csv,
+                &[vec![
+                    Value::Float(0.1 * 3.0),
+                    Value::Float(1.2345678901234567)
+                ]]
+            ),
+            "id,name\n0.3,1.23456789012346\n"
+        );
+        assert_eq!(
+            render(
+                OutputFormat::Ndjson,
+                &[vec![Value::Float(3.0), Value::Float(-2.5e-3)]]
+            ),
+            "{\"id\":3.0,\"name\":-0.0025}\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_escapes_cells() {
+        assert_eq!(
+            render(
+                OutputFormat::Markdown,
+                &[vec![Value::Integer(1), text("a|b\nc")]]
+            ),
+            "| id | name |\n| --- | --- |\n| 1 | a\\|b<br>c |\n"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use super::command_parser::parse_statement;
+use super::custom_error::CustomError;
+use super::expression::Operand;
+use super::statement::Statement;
+use super::tokenizer::{tokenize, Token};
+use super::value::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Un parámetro de un comando preparado, que ocupa el lugar de un valor literal.
+pub enum Parameter {
+    /// Un parámetro posicional, escrito como ? o como $1, $2, etc. Las posiciones empiezan en 1.
+    Positional(usize),
+    /// Un parámetro con nombre, escrito como :name.
+    Named(String),
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Parameter::Positional(position) => write!(f, "${}", position),
+            Parameter::Named(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
+/// Un comando SQL parseado una sola vez, que puede ejecutarse varias veces con distintos valores para sus parámetros.
+///
+/// Los valores se asignan con bind o bind_named y se reemplazan directamente en el Statement ya parseado,
+/// por lo que nunca se interpretan como SQL.
+///
+/// # Ejemplo
+/// ```no_run
+/// use sql_rustico::database::Database;
+/// use sql_rustico::value::Value;
+///
+/// let database = Database::open("tables/")?;
+/// let mut statement = database.prepare("SELECT * FROM supermarket WHERE Product = :product;")?;
+/// statement.bind_named("product", Value::Text("x' OR 'a' = 'a".to_string()))?;
+/// let rows = database.query_prepared(&statement)?;
+/// # Ok::<(), sql_rustico::custom_error::CustomError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    statement: Statement,
+    parameters: Vec<Parameter>,
+    bindings: HashMap<Parameter, Value>,
+}
+
+impl PreparedStatement {
+    /// Tokeniza y parsea un comando SQL que puede tener parámetros.
+    pub fn new(command: &str) -> Result<PreparedStatement, CustomError> {
+        let tokens = tokenize(command)?;
+        let mut parameters = vec![];
+        for token in &tokens {
+            if let Token::Parameter(parameter) = token {
+                if !parameters.contains(parameter) {
+                    parameters.push(parameter.clone());
+                }
+            }
+        }
+        let statement = parse_statement(&tokens)?;
+        Ok(PreparedStatement {
+            statement,
+            parameters,
+            bindings: HashMap::new(),
+        })
+    }
+
+    /// Retorna los parámetros del comando, sin repetir, en el orden en que aparecen.
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// Asigna el valor de un parámetro posicional, contando desde 1.
+    pub fn bind(&mut self, position: usize, value: Value) -> Result<(), CustomError> {
+        self.bind_parameter(Parameter::Positional(position), value)
+    }
+
+    /// Asigna el valor de un parámetro con nombre, sin los ':' iniciales.
+    pub fn bind_named(&mut self, name: &str, value: Value) -> Result<(), CustomError> {
+        self.bind_parameter(Parameter::Named(name.to_string()), value)
+    }
+
+    /// Borra los valores asignados a los parámetros.
+    pub fn clear_bindings(&mut self) {
+        self.bindings.clear();
+    }
+
+    fn bind_parameter(&mut self, parameter: Parameter, value: Value) -> Result<(), CustomError> {
+        if !self.parameters.contains(&parameter) {
+            return Err(CustomError::InvalidSyntax {
+                message: format!("Unknown parameter {}", parameter),
+            });
+        }
+        self.bindings.insert(parameter, value);
+        Ok(())
+    }
+
+    /// Retorna el Statement con los valores asignados en lugar de sus parámetros.
+    /// Falla si algún parámetro no tiene un valor asignado.
+    pub fn statement(&self) -> Result<Statement, CustomError> {
+        if let Some(parameter) = self
+            .parameters
+            .iter()
+            .find(|parameter| !self.bindings.contains_key(parameter))
+        {
+            return Err(unbound_parameter_error(parameter));
+        }
+        let mut statement = self.statement.clone();
+        for operand in statement.parameters_mut() {
+            if let Some(bound) = match operand {
+                Operand::Parameter(parameter) => self.bindings.get(parameter),
+                _ => None,
+            } {
+                *operand = Operand::Literal(bound.clone());
+            }
+        }
+        Ok(statement)
+    }
+}
+
+/// Retorna el error de un parámetro que no tiene un valor asignado.
+pub fn unbound_parameter_error(parameter: &Parameter) -> CustomError {
+    CustomError::InvalidSyntax {
+        message: format!("No value bound for parameter {}", parameter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+
+    #[test]
+    fn test_bind_positional_and_named() {
+        let mut prepared =
+            PreparedStatement::new("UPDATE items SET name = ? WHERE id = ? AND kind = :kind;")
+                .unwrap();
+        assert_eq!(
+            prepared.parameters(),
+            &[
+                Parameter::Positional(1),
+                Parameter::Positional(2),
+                Parameter::Named("kind".to_string())
+            ]
+        );
+        prepared
+            .bind(1, Value::Text("x' OR 1 = 1".to_string()))
+            .unwrap();
+        prepared.bind(2, Value::Integer(3)).unwrap();
+        assert_eq!(
+            prepared.statement().err().unwrap(),
+            CustomError::InvalidSyntax {
+                message: "No value bound for parameter :kind".to_string()
+            }
+        );
+        prepared.bind_named("kind", Value::Null).unwrap();
+        let mut set_values = HashMap::new();
+        set_values.insert(
+            "name".to_string(),
+            Operand::Literal(Value::Text("x' OR 1 = 1".to_string())),
+        );
+        assert_eq!(
+            prepared.statement().unwrap(),
+            Statement::Update {
+                table_name: "items".to_string(),
+                set_values,
+                condition: Expression::And {
+                    left: Box::new(Expression::Comparison {
+                        left: Operand::Column("id".to_string()),
+                        operator: "=".to_string(),
+                        right: Operand::Literal(Value::Integer(3)),
+                    }),
+                    right: Box::new(Expression::Comparison {
+                        left: Operand::Column("kind".to_string()),
+                        operator: "=".to_string(),
+                        right: Operand::Literal(Value::Null),
+                    }),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parameters_are_operands() {
+        let prepared = PreparedStatement::new("DELETE FROM items WHERE id = :id;").unwrap();
+        assert_eq!(
+            prepared.statement,
+            Statement::Delete {
+                table_name: "items".to_string(),
+                condition: Expression::Comparison {
+                    left: Operand::Column("id".to_string()),
+                    operator: "=".to_string(),
+                    right: Operand::Parameter(Parameter::Named("id".to_string())),
+                },
+            }
+        );
+        assert_eq!(
+            PreparedStatement::new("CREATE TABLE items (id INTEGER DEFAULT ?);")
+                .err()
+                .unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Parameter $1 can't be used as DEFAULT".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_bind_unknown_parameter() {
+        let mut prepared =
+            PreparedStatement::new("DELETE FROM items WHERE id = $2 OR id = $2;").unwrap();
+        assert_eq!(prepared.parameters(), &[Parameter::Positional(2)]);
+        assert!(prepared.bind(1, Value::Integer(1)).is_err());
+        assert!(prepared.bind(2, Value::Integer(1)).is_ok());
+    }
+}
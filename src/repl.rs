@@ -0,0 +1,175 @@
+use super::custom_error::CustomError;
+use super::database::Database;
+use super::script::{run_statement, split_statements};
+use std::io::{BufRead, Write};
+
+const PROMPT: &str = "sql> ";
+/// Prompt para las líneas siguientes de un comando que todavía no terminó con ';'.
+const CONTINUATION_PROMPT: &str = "...> ";
+const HELP: &str = "\
+.tables            Lista las tablas del directorio
+.schema <table>    Muestra la definición de una tabla
+.help              Muestra esta ayuda
+.quit              Sale del programa";
+
+fn output_error() -> CustomError {
+    CustomError::GenericError {
+        message: "Couldn't write output".to_string(),
+    }
+}
+
+/// Ejecuta un ciclo interactivo sobre la base de datos, leyendo comandos de input y escribiendo los resultados en output
+/// y los errores en errors.
+///
+/// Un comando puede ocupar varias líneas y se ejecuta al terminar con ';', y una línea puede tener varios comandos.
+/// Los errores se muestran sin terminar el ciclo.
+/// Las líneas que empiezan con '.' son comandos del REPL: .tables, .schema <table>, .help y .quit.
+/// El ciclo termina con .quit o al terminarse input.
+pub fn run_repl<R: BufRead, W: Write, E: Write>(
+    database: &Database,
+    input: R,
+    output: &mut W,
+    errors: &mut E,
+) -> Result<(), CustomError> {
+    let mut buffer = String::new();
+    let mut lines = input.lines();
+    loop {
+        let prompt = if buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        write!(output, "{}", prompt).map_err(|_| output_error())?;
+        output.flush().map_err(|_| output_error())?;
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(_)) => return CustomError::error_generic("Couldn't read input"),
+            None => break,
+        };
+        if buffer.is_empty() && line.trim_start().starts_with('.') {
+            match run_dot_command(database, line.trim(), output) {
+                Ok(true) => {}
+                Ok(false) => return Ok(()),
+                Err(error) => writeln!(errors, "{}", error).map_err(|_| output_error())?,
+            }
+            continue;
+        }
+        if buffer.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        buffer.push_str(&line);
+        buffer.push('\n');
+        let (statements, remainder) = split_statements(&buffer);
+        for statement in statements {
+            if let Err(error) = run_statement(database, &statement, output) {
+                writeln!(errors, "{}", error).map_err(|_| output_error())?;
+            }
+        }
+        // lo que sigue al último ';' es el principio del próximo comando
+        buffer = if remainder.trim().is_empty() {
+            String::new()
+        } else {
+            remainder
+        };
+    }
+    writeln!(output).map_err(|_| output_error())?;
+    if !buffer.trim().is_empty() {
+        // el input terminó con un comando incompleto, que se ejecuta para mostrar su error
+        if let Err(error) = run_statement(database, &buffer, output) {
+            writeln!(errors, "{}", error).map_err(|_| output_error())?;
+        }
+    }
+    Ok(())
+}
+
+/// Ejecuta un comando del REPL. Retorna false si el REPL debe terminar.
+fn run_dot_command<W: Write>(
+    database: &Database,
+    line: &str,
+    output: &mut W,
+) -> Result<bool, CustomError> {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some(".quit") | Some(".exit"), None, _) => return Ok(false),
+        (Some(".help"), None, _) => writeln!(output, "{}", HELP).map_err(|_| output_error())?,
+        (Some(".tables"), None, _) => {
+            for table_name in database.table_names()? {
+                writeln!(output, "{}", table_name).map_err(|_| output_error())?;
+            }
+        }
+        (Some(".schema"), Some(table_name), None) => {
+            let columns: Vec<String> = database
+                .table_columns(table_name)?
+                .iter()
+                .map(|column| column.to_string())
+                .collect();
+            writeln!(
+                output,
+                "CREATE TABLE {} ({});",
+                table_name,
+                columns.join(", ")
+            )
+            .map_err(|_| output_error())?;
+        }
+        (Some(".schema"), _, _) => CustomError::error_invalid_syntax("Usage: .schema <table>")?,
+        (Some(command), _, _) => {
+            CustomError::error_invalid_syntax(&format!("Unknown command: {}", command))?
+        }
+        (None, _, _) => {}
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn run(directory: &str, input: &str) -> (String, String) {
+        let database = Database::open(directory).unwrap();
+        let mut output = vec![];
+        let mut errors = vec![];
+        run_repl(&database, input.as_bytes(), &mut output, &mut errors).unwrap();
+        (
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(errors).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_repl_multiline_statement_and_errors() {
+        let directory = "test_repl_statements";
+        fs::create_dir_all(directory).unwrap();
+        fs::write(format!("{}/items.csv", directory), "id,name\n1,first\n").unwrap();
+        let (output, errors) = run(
+            directory,
+            "SELECT name\nFROM items\nWHERE id = 1;\nSELECT * FROM missing;\n.quit\nSELECT * FROM items;\n",
+        );
+        assert_eq!(output, "sql> ...> ...> name\nfirst\nsql> sql> ");
+        assert_eq!(errors, "ERROR: Couldn't open table file\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_repl_dot_commands() {
+        let directory = "test_repl_dot_commands";
+        fs::create_dir_all(directory).unwrap();
+        fs::write(format!("{}/items.csv", directory), "id,name\n").unwrap();
+        fs::write(
+            format!("{}/items.schema", directory),
+            "id INTEGER NOT NULL\n",
+        )
+        .unwrap();
+        fs::write(format!("{}/others.csv", directory), "a\n").unwrap();
+        let (output, errors) = run(directory, ".tables\n.schema items\n.schema\n.unknown\n");
+        assert_eq!(
+            output,
+            "sql> items\nothers\nsql> CREATE TABLE items (id INTEGER NOT NULL, name);\nsql> sql> sql> \n"
+        );
+        assert_eq!(
+            errors,
+            "INVALID_SYNTAX: Usage: .schema <table>\nINVALID_SYNTAX: Unknown command: .unknown\n"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,17 +1,22 @@
 use super::custom_error::CustomError;
-use super::expression::{evaluate_expression, Expression};
+use super::expression::{evaluate_expression, evaluate_operand, Expression, Operand};
+use super::output_format::RowSink;
+use super::row_parser::format_field;
+use super::schema::ColumnDefinition;
+use super::value::Value;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 
-/// Valor por defecto para una columna vacía.
-const DEFAULT_VALUE: &str = "";
-
-/// Una fila en esta implementación es un conjunto de valrores asociados a columnas. Convenientemente tiene un vector de columnas además de un HashMap de valores para tener referencia de orden de las columnas.
+/// Una fila en esta implementación es un conjunto de valores tipados asociados a columnas. Convenientemente tiene un vector de columnas además de un HashMap de valores para tener referencia de orden de las columnas.
+/// Los valores se convierten a texto solo al escribir la fila. Las celdas leídas de un CSV conservan su texto original
+/// mientras no se modifiquen, para escribirlas tal como estaban: 2.50 se interpreta como 2.5, pero se vuelve a escribir 2.50.
 /// Puede escribirse en un archivo CSV actualizando su estado según una condición dada.
+#[derive(Debug, Clone, Default)]
 pub struct Row {
     columns_in_order: Vec<String>,
-    values: HashMap<String, String>,
+    values: HashMap<String, Value>,
+    texts: HashMap<String, String>,
 }
 
 fn write_result(writer: &mut BufWriter<File>, string: &str) -> Result<(), CustomError> {
@@ -21,37 +26,50 @@ fn write_result(writer: &mut BufWriter<File>, string: &str) -> Result<(), Custom
     Ok(())
 }
 
-fn print_result<W: Write>(output: &mut W, string: &str) -> Result<(), CustomError> {
-    if write!(output, "{}", string).is_err() {
-        return CustomError::error_generic("Error writing output");
-    }
-    Ok(())
-}
-
 impl Row {
     /// Crea una nueva fila dado un vector de columnas y un HashMap de valores.
-    pub fn new(columns: &[String], values: HashMap<String, String>) -> Row {
-        let mut columns_in_order = Vec::new();
-        for item in columns.iter() {
-            columns_in_order.push(item.to_string());
+    pub fn new(columns: &[String], values: HashMap<String, Value>) -> Row {
+        Row {
+            columns_in_order: columns.to_vec(),
+            values,
+            texts: HashMap::new(),
+        }
+    }
+
+    /// Crea una fila con las celdas de una tabla, interpretando cada una según el tipo de su columna.
+    pub fn from_table(columns: &[ColumnDefinition], fields: Vec<String>) -> Row {
+        let mut row = Row::default();
+        for (column, text) in columns.iter().zip(fields) {
+            row.columns_in_order.push(column.name.to_string());
+            row.values
+                .insert(column.name.to_string(), column.value_from_csv(&text));
+            row.texts.insert(column.name.to_string(), text);
         }
+        row
+    }
+
+    /// Crea una fila con el texto de las celdas de un CSV, infiriendo el tipo de cada valor.
+    pub fn from_csv(columns: &[String], texts: HashMap<String, String>) -> Row {
+        let values = texts
+            .iter()
+            .map(|(column, text)| (column.to_string(), Value::from_csv(text)))
+            .collect();
         Row {
-            columns_in_order,
+            columns_in_order: columns.to_vec(),
             values,
+            texts,
         }
     }
 
-    /// Se escribe a un archivo CSV.
+    /// Se escribe a un archivo CSV, agregando comillas a los valores que lo necesiten.
+    /// Las columnas sin valor se escriben vacías.
     pub fn write_row(&self, writer: &mut BufWriter<File>) -> Result<(), CustomError> {
         let last_index = self.columns_in_order.len() - 1;
 
         for (actual_index, column) in self.columns_in_order.iter().enumerate() {
-            // Para cada columna en orden, si el valor existe en el HashMap se escribe, de lo contrario se escribe un valor por defecto.
-            let value_option = self.values.get(column);
-            if let Some(value) = value_option {
-                write_result(writer, value)?;
-            } else {
-                write_result(writer, DEFAULT_VALUE)?;
+            // Para cada columna en orden, si el valor existe se escribe su texto, de lo contrario se deja vacía.
+            if let Some(text) = self.text(column) {
+                write_result(writer, &format_field(&text))?;
             }
             if actual_index != last_index {
                 write_result(writer, ",")?;
@@ -63,39 +81,40 @@ impl Row {
     }
 
     /// Actualiza los valores de una fila si cumple con una condición dada, dado un HashMap de columnas y valores a actualizar.
+    /// Retorna si la fila fue actualizada.
     pub fn update_and_write_row(
         &mut self,
-        update_values: &HashMap<String, String>,
+        update_values: &HashMap<String, Value>,
         condition: &Expression,
         writer: &mut BufWriter<File>,
-    ) -> Result<(), CustomError> {
+    ) -> Result<bool, CustomError> {
         let expression_is_true = evaluate_expression(condition, &self.values)?;
         if expression_is_true {
             // Si la condición es verdadera, se actualizan los valores.
-            for column_to_update in update_values.keys() {
-                update_if_present(
-                    &mut self.values,
-                    column_to_update,
-                    update_values[column_to_update].as_str(),
-                )?;
+            for (column, value) in update_values {
+                if !self.values.contains_key(column) {
+                    CustomError::error_invalid_column("Column does not exist")?;
+                }
+                self.set_value(column, value.clone());
             }
         }
         self.write_row(writer)?;
-        Ok(())
+        Ok(expression_is_true)
     }
 
-    /// Se escribe a un archivo CSV si cumple con una condición dada, de lo contrario se omite.
+    /// Se escribe a un archivo CSV si no cumple con una condición dada, de lo contrario se omite.
+    /// Retorna si la fila fue eliminada.
     pub fn delete_or_write_row(
         &self,
         condition: &Expression,
         writer: &mut BufWriter<File>,
-    ) -> Result<(), CustomError> {
+    ) -> Result<bool, CustomError> {
         let expression_is_true: bool = evaluate_expression(condition, &self.values)?;
         if !expression_is_true {
             // Si la condición es falsa, se escribe la fila.
             self.write_row(writer)?;
         }
-        Ok(())
+        Ok(expression_is_true)
     }
 
     /// Verifica si la fila cumple con una condición dada, devolviendo un booleano.
@@ -104,68 +123,115 @@ impl Row {
         Ok(result)
     }
 
+    /// Evalúa un operando sobre la fila, retornando el valor que produce.
+    pub fn evaluate(&self, operand: &Operand) -> Result<Value, CustomError> {
+        evaluate_operand(operand, &self.values)
+    }
+
+    /// Asigna el valor de una columna, agregándola al final de la fila si no existe.
+    pub fn set_value(&mut self, column: &str, value: Value) {
+        if !self.columns_in_order.iter().any(|name| name == column) {
+            self.columns_in_order.push(column.to_string());
+        }
+        self.texts.remove(column);
+        self.values.insert(column.to_string(), value);
+    }
+
+    /// Copia el valor de una columna de otra fila con el nombre recibido, conservando su texto original.
+    /// Si la otra fila no tiene la columna, no se copia nada.
+    pub fn copy_value(&mut self, name: &str, row: &Row, column: &str) {
+        if let Some(value) = row.get(column) {
+            self.set_value(name, value.clone());
+        }
+        if let Some(text) = row.texts.get(column) {
+            self.texts.insert(name.to_string(), text.to_string());
+        }
+    }
+
+    /// Agrega al final de la fila todas las columnas de otra fila, con sus valores.
+    pub fn extend(&mut self, row: &Row) {
+        for column in &row.columns_in_order {
+            self.copy_value(column, row, column);
+        }
+    }
+
+    /// Retorna una fila con solo las columnas recibidas, en ese orden.
+    pub fn project(&self, columns: &[String]) -> Row {
+        let mut row = Row::default();
+        for column in columns {
+            row.copy_value(column, self, column);
+        }
+        row
+    }
+
     /// Retorna un Option con el valor correspondiente a la columna de la fila.
     /// Si la columna no existe, se retorna None.
-    pub fn get(&self, column: &str) -> Option<&String> {
-        if let Some(value) = self.values.get(column) {
-            Some(value)
-        } else {
-            None
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        self.values.get(column)
+    }
+
+    /// Retorna el texto con que se escribe el valor de una columna en un CSV: el texto original si se leyó de uno
+    /// y no se modificó, o el valor convertido a texto.
+    pub fn text(&self, column: &str) -> Option<String> {
+        match self.texts.get(column) {
+            Some(text) => Some(text.to_string()),
+            None => self.values.get(column).map(|value| value.to_string()),
         }
     }
 
     /// Retorna un Ordering según la comparación de dos filas por una columna dada.
+    /// Los valores se comparan como Value, con el mismo orden que se usa al evaluar condiciones.
     pub fn cmp_by_column(&self, column: &str, row: &Row) -> core::cmp::Ordering {
         match (self.get(column), row.get(column)) {
             (None, None) => core::cmp::Ordering::Equal,
             (None, Some(_)) => core::cmp::Ordering::Less,
             (Some(_), None) => core::cmp::Ordering::Greater,
-            (Some(value1), Some(value2)) => value1.cmp(value2),
+            (Some(value1), Some(value2)) => value1.compare(value2),
         }
     }
 
-    /// Imprime una fila en standard output, dado un vector de columnas a imprimir.
-    pub fn print_row<W: Write>(
-        &self,
-        columns_to_print: &[String],
-        output: &mut W,
-    ) -> Result<(), CustomError> {
-        for (index, column) in columns_to_print.iter().enumerate() {
+    /// Retorna el texto de los valores de la fila para las columnas recibidas, en el mismo orden.
+    /// Los valores que faltan se completan con el valor por defecto.
+    pub fn selected_values(&self, columns: &[String]) -> Result<Vec<String>, CustomError> {
+        let mut values = vec![];
+        for column in columns {
             if !self.columns_in_order.contains(column) {
                 CustomError::error_invalid_column(
                     format!("Column {} does not exist", column).as_str(),
                 )?;
             }
-            if let Some(value) = self.values.get(column) {
-                print_result(output, value)?;
-            } else {
-                print_result(output, DEFAULT_VALUE)?;
-            }
-            if index != columns_to_print.len() - 1 {
-                print_result(output, ",")?;
+            values.push(self.text(column).unwrap_or_default());
+        }
+        Ok(values)
+    }
+
+    /// Retorna los valores de la fila para las columnas recibidas, en el mismo orden.
+    /// Los valores que faltan se completan con Null.
+    pub fn selected(&self, columns: &[String]) -> Result<Vec<Value>, CustomError> {
+        let mut values = vec![];
+        for column in columns {
+            if !self.columns_in_order.contains(column) {
+                CustomError::error_invalid_column(
+                    format!("Column {} does not exist", column).as_str(),
+                )?;
             }
+            values.push(self.get(column).cloned().unwrap_or(Value::Null));
         }
-        print_result(output, "\n")?;
-        Ok(())
+        Ok(values)
     }
-}
 
-fn update_if_present(
-    map: &mut HashMap<String, String>,
-    key: &str,
-    value: &str,
-) -> Result<(), CustomError> {
-    if map.get(key).is_some() {
-        map.insert(key.to_string(), value.to_string());
-        Ok(())
-    } else {
-        CustomError::error_invalid_column("Column does not exist")
+    /// Imprime una fila en el RowSink recibido, dado un vector de columnas a imprimir.
+    pub fn print_row(
+        &self,
+        columns_to_print: &[String],
+        sink: &mut dyn RowSink,
+    ) -> Result<(), CustomError> {
+        sink.write_row(&self.selected(columns_to_print)?)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::expression::Operand;
     use super::*;
 
     const COLULMN1: &str = "column1";
@@ -179,7 +245,7 @@ mod tests {
         let mut values = HashMap::new();
         values.insert(COLULMN1.to_string(), COLULMN1.to_string());
         values.insert(COLUMN2.to_string(), COLUMN2.to_string());
-        Row::new(&columns, values)
+        Row::from_csv(&columns, values)
     }
 
     fn create_row_with_values() -> Row {
@@ -187,7 +253,7 @@ mod tests {
         let mut values = HashMap::new();
         values.insert(COLULMN1.to_string(), VALUE1.to_string());
         values.insert(COLUMN2.to_string(), VALUE2.to_string());
-        Row::new(&columns, values)
+        Row::from_csv(&columns, values)
     }
 
     #[test]
@@ -225,11 +291,11 @@ mod tests {
         let mut writer = BufWriter::new(file);
 
         let mut update_values = HashMap::new();
-        update_values.insert(COLULMN1.to_string(), NEWVALUE1.to_string());
+        update_values.insert(COLULMN1.to_string(), Value::Text(NEWVALUE1.to_string()));
         let condition = Expression::Comparison {
             left: Operand::Column(COLULMN1.to_string()),
             operator: "=".to_string(),
-            right: Operand::String(VALUE1.to_string()),
+            right: Operand::Literal(Value::Text(VALUE1.to_string())),
         };
 
         row_not_to_update
@@ -260,13 +326,15 @@ mod tests {
         let condition = Expression::Comparison {
             left: Operand::Column(COLULMN1.to_string()),
             operator: "=".to_string(),
-            right: Operand::String(VALUE1.to_string()),
+            right: Operand::Literal(Value::Text(VALUE1.to_string())),
         };
 
-        row_not_to_delete
+        assert!(!row_not_to_delete
             .delete_or_write_row(&condition, &mut writer)
-            .unwrap();
-        row_to_delete.delete_or_write_row(&condition, &mut writer).unwrap();
+            .unwrap());
+        assert!(row_to_delete
+            .delete_or_write_row(&condition, &mut writer)
+            .unwrap());
         writer.flush().unwrap();
         let contents = std::fs::read_to_string(test_path).unwrap();
         std::fs::remove_file(test_path).unwrap();
@@ -281,21 +349,51 @@ mod tests {
         let condition = Expression::Comparison {
             left: Operand::Column(COLULMN1.to_string()),
             operator: "=".to_string(),
-            right: Operand::String(VALUE1.to_string()),
+            right: Operand::Literal(Value::Text(VALUE1.to_string())),
         };
 
         let result_true = row_true.check_condition(&condition).unwrap();
         let result_false = row_false.check_condition(&condition).unwrap();
 
-        assert_eq!(result_true, true);
-        assert_eq!(result_false, false);
+        assert!(result_true);
+        assert!(!result_false);
     }
 
     #[test]
     fn test_get() {
         let row = create_row_with_values();
         let value = row.get(COLULMN1).unwrap();
-        assert_eq!(value, VALUE1);
+        assert_eq!(value, &Value::Text(VALUE1.to_string()));
+    }
+
+    #[test]
+    fn test_from_csv_keeps_text() {
+        let columns = vec![COLULMN1.to_string()];
+        let mut values = HashMap::new();
+        values.insert(COLULMN1.to_string(), "2.50".to_string());
+        let mut row = Row::from_csv(&columns, values);
+        assert_eq!(row.get(COLULMN1), Some(&Value::Float(2.5)));
+        assert_eq!(row.get(COLUMN2), None);
+        assert_eq!(row.text(COLULMN1), Some("2.50".to_string()));
+        row.set_value(COLULMN1, Value::Float(3.5));
+        assert_eq!(row.text(COLULMN1), Some("3.5".to_string()));
+    }
+
+    #[test]
+    fn test_set_computed_value() {
+        let mut row = create_row_with_values();
+        let operand = Operand::Arithmetic {
+            left: Box::new(Operand::Literal(Value::Integer(2))),
+            operator: '*',
+            right: Box::new(Operand::Literal(Value::Integer(3))),
+        };
+        let value = row.evaluate(&operand).unwrap();
+        row.set_value("product", value);
+        assert_eq!(
+            row.selected_values(&[COLUMN2.to_string(), "product".to_string()])
+                .unwrap(),
+            vec![VALUE2.to_string(), "6".to_string()]
+        );
     }
 
     #[test]
@@ -311,4 +409,20 @@ mod tests {
         assert_eq!(result2, core::cmp::Ordering::Less);
         assert_eq!(result3, core::cmp::Ordering::Equal);
     }
+
+    #[test]
+    fn test_cmp_by_column_numeric() {
+        let columns = vec![COLULMN1.to_string()];
+        let mut values = HashMap::new();
+        values.insert(COLULMN1.to_string(), "100".to_string());
+        let row1 = Row::from_csv(&columns, values);
+        let mut values = HashMap::new();
+        values.insert(COLULMN1.to_string(), "28".to_string());
+        let row2 = Row::from_csv(&columns, values);
+
+        assert_eq!(
+            row1.cmp_by_column(COLULMN1, &row2),
+            core::cmp::Ordering::Greater
+        );
+    }
 }
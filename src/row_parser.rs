@@ -1,25 +1,115 @@
 use super::custom_error::CustomError;
 use super::row::Row;
-use std::collections::HashMap;
+use super::schema::ColumnDefinition;
+use std::io::BufRead;
+
+/// Recorre un registro CSV separando sus campos según RFC 4180.
+/// Retorna los campos encontrados y un booleano que indica si el registro terminó fuera de comillas.
+fn scan_fields(record: &str) -> (Vec<String>, bool) {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut field_start = true; // flag para saber si estoy al principio de un campo
+    let mut chars = record.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            if ch == '"' {
+                if let Some('"') = chars.peek() {
+                    // una comilla doble escapada se agrega como una sola comilla
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(ch);
+            }
+        } else if ch == ',' {
+            fields.push(field);
+            field = String::new();
+            field_start = true;
+            continue;
+        } else if ch == '"' && field_start {
+            // solo se abren comillas al principio de un campo, en otro lugar son literales
+            in_quotes = true;
+        } else {
+            field.push(ch);
+        }
+        field_start = false;
+    }
+    fields.push(field);
+    (fields, !in_quotes)
+}
+
+/// Parsea un registro CSV y lo convierte en un vector con los valores de sus campos.
+/// Los campos pueden estar entre comillas dobles, en cuyo caso pueden contener comas, saltos de línea y comillas escapadas como "".
+pub fn parse_fields(record: &str) -> Result<Vec<String>, CustomError> {
+    let (fields, closed) = scan_fields(record);
+    if !closed {
+        CustomError::error_invalid_table("Unterminated quoted field")?;
+    }
+    Ok(fields)
+}
+
+/// Lee un registro CSV completo de un lector, sin el salto de línea final.
+/// Un registro puede ocupar varias líneas si algún campo entre comillas contiene saltos de línea.
+/// Si no quedan registros por leer, retorna None.
+pub fn read_record<R: BufRead>(reader: &mut R) -> Result<Option<String>, CustomError> {
+    let mut record = String::new();
+    loop {
+        match reader.read_line(&mut record) {
+            Ok(0) => break, // fin del archivo
+            Ok(_) => {
+                if scan_fields(&record).1 {
+                    // si el registro no quedó dentro de comillas, está completo
+                    break;
+                }
+            }
+            Err(_) => {
+                return Err(CustomError::GenericError {
+                    message: "Couldn't read table file".to_string(),
+                })
+            }
+        }
+    }
+    if record.is_empty() {
+        return Ok(None);
+    }
+    if record.ends_with('\n') {
+        record.pop();
+        if record.ends_with('\r') {
+            record.pop();
+        }
+    }
+    Ok(Some(record))
+}
+
+/// Convierte un valor en un campo CSV, agregando comillas solo si el valor contiene comas, comillas o saltos de línea.
+pub fn format_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
 
 /// Parsea una línea de un archivo CSV y la convierte en un vector de Strings.
 pub fn parse_columns(line: &str) -> Result<Vec<String>, CustomError> {
-    Ok(line.split(",").map(|s| s.trim().to_string()).collect())
+    Ok(parse_fields(line)?
+        .iter()
+        .map(|s| s.trim().to_string())
+        .collect())
 }
 
-/// Parsea una fila de un archivo CSV y la convierte en un objeto Row, dado un vector de columnas.
+/// Parsea una fila de un archivo CSV y la convierte en un objeto Row, dadas las definiciones de sus columnas.
+/// Cada valor se interpreta según el tipo de su columna.
 /// Si la cantidad de valores en la fila no coincide con la cantidad de columnas, retorna un error.
-pub fn parse_row(columns: &[String], line: &str) -> Result<Row, CustomError> {
-    let values: Vec<&str> = line.split(",").collect();
+pub fn parse_row(columns: &[ColumnDefinition], line: &str) -> Result<Row, CustomError> {
+    let values = parse_fields(line)?;
     if values.len() != columns.len() {
         CustomError::error_invalid_table("Columns size missmatch")?;
     }
-    let mut row_values: HashMap<String, String> = HashMap::new();
-    for (i, value) in values.iter().enumerate() {
-        row_values.insert(columns[i].to_string(), value.to_string());
-    }
-    let row = Row::new(columns, row_values);
-    Ok(row)
+    Ok(Row::from_table(columns, values))
 }
 
 #[cfg(test)]
@@ -28,7 +118,10 @@ mod tests {
 
     #[test]
     fn test_parse_row_invalid_columns_size() {
-        let columns = vec!["column1".to_string(), "column2".to_string()];
+        let columns = vec![
+            ColumnDefinition::new("column1"),
+            ColumnDefinition::new("column2"),
+        ];
         let line = format!("{},{},{}", "value1", "value2", "value3");
         let result = parse_row(&columns, &line);
         assert!(result.is_err());
@@ -39,4 +132,50 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_fields_with_quotes() {
+        let record = "1,\"Garcia's Bakery, Ltd.\",\"say \"\"hi\"\"\",\"two\nlines\"";
+        let fields = parse_fields(record).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                "1".to_string(),
+                "Garcia's Bakery, Ltd.".to_string(),
+                "say \"hi\"".to_string(),
+                "two\nlines".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_fields_unterminated_quote() {
+        let result = parse_fields("1,\"unterminated");
+        assert_eq!(
+            result.err().unwrap(),
+            CustomError::InvalidTable {
+                message: "Unterminated quoted field".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_record_with_embedded_newline() {
+        let input = "a,\"b\nc\"\r\nd,e\n";
+        let mut reader = std::io::BufReader::new(input.as_bytes());
+        assert_eq!(
+            read_record(&mut reader).unwrap(),
+            Some("a,\"b\nc\"".to_string())
+        );
+        assert_eq!(read_record(&mut reader).unwrap(), Some("d,e".to_string()));
+        assert_eq!(read_record(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_format_field() {
+        assert_eq!(format_field("plain"), "plain");
+        assert_eq!(format_field("a,b"), "\"a,b\"");
+        assert_eq!(format_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(format_field("two\nlines"), "\"two\nlines\"");
+    }
 }
@@ -0,0 +1,169 @@
+use super::custom_error::CustomError;
+use super::output_format::RowSink;
+use super::schema::ColumnDefinition;
+use super::value::Value;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+/// Cantidad de filas que la consulta puede leer por adelantado, antes de que quien la consume las pida.
+const ROWS_BUFFER: usize = 128;
+
+/// Lo que envía la consulta a sus Rows: primero los nombres de las columnas y después los valores de cada fila.
+#[derive(Debug)]
+enum Message {
+    Header(Vec<String>),
+    Row(Vec<Value>),
+}
+
+/// El resultado de una consulta: sus columnas y un iterador sobre sus filas, con los valores en el mismo orden que las columnas.
+///
+/// Las columnas que vienen de una tabla tienen la definición de su esquema, y las demás columnas no tienen tipo.
+/// Los valores llegan con el tipo que tienen en la consulta, sin pasar por texto.
+///
+/// La consulta se ejecuta en otro hilo, que lee las tablas a medida que se piden las filas y se adelanta como máximo
+/// ROWS_BUFFER filas, por lo que el resultado nunca se guarda completo en memoria. Un error al leer una fila se retorna
+/// en su lugar, y termina el iterador. Mientras existan las Rows, la consulta mantiene los locks compartidos de sus tablas:
+/// al descartarlas se detiene la consulta y se liberan los locks.
+#[derive(Debug)]
+pub struct Rows {
+    columns: Vec<ColumnDefinition>,
+    receiver: Option<Receiver<Message>>,
+    /// El hilo que ejecuta la consulta, hasta que se obtiene su resultado.
+    worker: Option<JoinHandle<Result<usize, CustomError>>>,
+}
+
+impl Rows {
+    /// Ejecuta la consulta en otro hilo, que escribe su resultado en el RowSink que recibe, y retorna sus Rows
+    /// una vez que la consulta escribió sus columnas. Si la consulta falla antes, se retorna su error.
+    /// Las columnas usan las definiciones de table_columns con el mismo nombre.
+    pub(crate) fn spawn<F>(
+        table_columns: Vec<ColumnDefinition>,
+        query: F,
+    ) -> Result<Rows, CustomError>
+    where
+        F: FnOnce(&mut dyn RowSink) -> Result<usize, CustomError> + Send + 'static,
+    {
+        let (sender, receiver) = sync_channel(ROWS_BUFFER);
+        let worker = thread::spawn(move || query(&mut RowSender { sender }));
+        let mut rows = Rows {
+            columns: vec![],
+            receiver: Some(receiver),
+            worker: Some(worker),
+        };
+        let names = match rows.receive() {
+            Some(Ok(Message::Header(names))) => names,
+            Some(Ok(Message::Row(_))) => return Err(query_error()),
+            Some(Err(error)) => return Err(error),
+            None => vec![],
+        };
+        rows.columns = names
+            .iter()
+            .map(|name| {
+                table_columns
+                    .iter()
+                    .find(|column| &column.name == name)
+                    .cloned()
+                    .unwrap_or_else(|| ColumnDefinition::new(name))
+            })
+            .collect();
+        Ok(rows)
+    }
+
+    /// Retorna las columnas del resultado.
+    pub fn columns(&self) -> &[ColumnDefinition] {
+        &self.columns
+    }
+
+    /// Retorna la posición de una columna en las filas del resultado, si existe.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column.name == name)
+    }
+
+    /// Recibe el siguiente mensaje de la consulta. Cuando la consulta termina retorna su error, si falló, y luego None.
+    fn receive(&mut self) -> Option<Result<Message, CustomError>> {
+        if let Ok(message) = self.receiver.as_ref()?.recv() {
+            return Some(Ok(message));
+        }
+        self.receiver = None;
+        match self.worker.take()?.join() {
+            Ok(Ok(_)) => None,
+            Ok(Err(error)) => Some(Err(error)),
+            Err(_) => Some(Err(query_error())),
+        }
+    }
+}
+
+impl Iterator for Rows {
+    type Item = Result<Vec<Value>, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receive()? {
+            Ok(Message::Row(row)) => Some(Ok(row)),
+            Ok(Message::Header(_)) => Some(Err(query_error())),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+impl Drop for Rows {
+    fn drop(&mut self) {
+        // sin el receptor la consulta no puede enviar más filas y termina, liberando los locks de sus tablas
+        self.receiver = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join(); // el resultado de una consulta descartada no interesa
+        }
+    }
+}
+
+fn query_error() -> CustomError {
+    CustomError::GenericError {
+        message: "Query stopped unexpectedly".to_string(),
+    }
+}
+
+/// Un RowSink que envía el resultado de una consulta a sus Rows, esperando si ya se adelantó ROWS_BUFFER filas.
+struct RowSender {
+    sender: SyncSender<Message>,
+}
+
+impl RowSender {
+    fn send(&self, message: Message) -> Result<(), CustomError> {
+        self.sender
+            .send(message)
+            .map_err(|_| CustomError::GenericError {
+                message: "Query result was discarded".to_string(),
+            })
+    }
+}
+
+impl RowSink for RowSender {
+    fn write_header(&mut self, columns: &[String]) -> Result<(), CustomError> {
+        self.send(Message::Header(columns.to_vec()))
+    }
+
+    fn write_row(&mut self, values: &[Value]) -> Result<(), CustomError> {
+        self.send(Message::Row(values.to_vec()))
+    }
+
+    fn finish(&mut self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
+
+/// Un RowSink que descarta el resultado de un SELECT.
+#[derive(Debug, Default)]
+pub(crate) struct DiscardRows;
+
+impl RowSink for DiscardRows {
+    fn write_header(&mut self, _columns: &[String]) -> Result<(), CustomError> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, _values: &[Value]) -> Result<(), CustomError> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), CustomError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use super::custom_error::CustomError;
+use super::value::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Las funciones escalares soportadas, que calculan un valor por fila: UPPER, LOWER, LENGTH, ABS, ROUND, COALESCE y CONCAT.
+pub enum ScalarFunction {
+    Upper,
+    Lower,
+    Length,
+    Abs,
+    Round,
+    Coalesce,
+    Concat,
+}
+
+impl ScalarFunction {
+    /// Retorna la función correspondiente a un nombre, sin importar mayúsculas.
+    pub fn from_name(name: &str) -> Option<ScalarFunction> {
+        match name.to_uppercase().as_str() {
+            "UPPER" => Some(ScalarFunction::Upper),
+            "LOWER" => Some(ScalarFunction::Lower),
+            "LENGTH" => Some(ScalarFunction::Length),
+            "ABS" => Some(ScalarFunction::Abs),
+            "ROUND" => Some(ScalarFunction::Round),
+            "COALESCE" => Some(ScalarFunction::Coalesce),
+            "CONCAT" => Some(ScalarFunction::Concat),
+            _ => None,
+        }
+    }
+
+    /// Verifica que la función acepte la cantidad de argumentos recibida.
+    /// ROUND recibe uno o dos, COALESCE y CONCAT al menos uno, y el resto exactamente uno.
+    pub fn check_arguments(&self, count: usize) -> Result<(), CustomError> {
+        let valid = match self {
+            ScalarFunction::Round => count == 1 || count == 2,
+            ScalarFunction::Coalesce | ScalarFunction::Concat => count >= 1,
+            _ => count == 1,
+        };
+        if !valid {
+            CustomError::error_invalid_syntax(&format!(
+                "Wrong number of arguments for {}: {}",
+                self, count
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Aplica la función a los valores de sus argumentos.
+    /// Salvo COALESCE y CONCAT, que omiten los Null, un argumento Null da como resultado Null.
+    pub fn apply(&self, arguments: &[Value]) -> Result<Value, CustomError> {
+        self.check_arguments(arguments.len())?;
+        let argument = &arguments[0];
+        match self {
+            ScalarFunction::Coalesce => Ok(arguments
+                .iter()
+                .find(|argument| **argument != Value::Null)
+                .cloned()
+                .unwrap_or(Value::Null)),
+            ScalarFunction::Concat => Ok(Value::Text(
+                arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect(),
+            )),
+            _ if arguments.contains(&Value::Null) => Ok(Value::Null),
+            ScalarFunction::Upper => Ok(Value::Text(argument.to_string().to_uppercase())),
+            ScalarFunction::Lower => Ok(Value::Text(argument.to_string().to_lowercase())),
+            ScalarFunction::Length => {
+                Ok(Value::Integer(argument.to_string().chars().count() as i64))
+            }
+            ScalarFunction::Abs => match self.number(argument)? {
+                Value::Integer(integer) => Ok(integer
+                    .checked_abs()
+                    .map(Value::Integer)
+                    .unwrap_or(Value::Float((integer as f64).abs()))),
+                number => Ok(Value::Float(number.as_f64().unwrap_or(0.0).abs())),
+            },
+            ScalarFunction::Round => {
+                let number = self.number(argument)?.as_f64().unwrap_or(0.0);
+                let digits = match arguments.get(1).map(|digits| self.number(digits)) {
+                    Some(Ok(Value::Integer(digits))) => digits.clamp(-308, 308) as i32,
+                    Some(Ok(_)) => {
+                        return Err(CustomError::InvalidSyntax {
+                            message: format!("ROUND digits must be an integer: {}", arguments[1]),
+                        })
+                    }
+                    Some(Err(error)) => return Err(error),
+                    None => 0,
+                };
+                let scale = 10f64.powi(digits);
+                Ok(Value::Float((number * scale).round() / scale))
+            }
+        }
+    }
+
+    /// Interpreta un argumento como número, convirtiendo los Text numéricos como en una celda CSV.
+    fn number(&self, argument: &Value) -> Result<Value, CustomError> {
+        let number = match argument {
+            Value::Text(text) => Value::from_csv(text),
+            other => other.clone(),
+        };
+        match number {
+            Value::Integer(_) | Value::Float(_) => Ok(number),
+            _ => Err(CustomError::InvalidColumn {
+                message: format!("Can't apply {} to non-numeric value '{}'", self, argument),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ScalarFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ScalarFunction::Upper => "UPPER",
+            ScalarFunction::Lower => "LOWER",
+            ScalarFunction::Length => "LENGTH",
+            ScalarFunction::Abs => "ABS",
+            ScalarFunction::Round => "ROUND",
+            ScalarFunction::Coalesce => "COALESCE",
+            ScalarFunction::Concat => "CONCAT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(
+            ScalarFunction::Upper
+                .apply(&[Value::Text("pen".to_string())])
+                .unwrap(),
+            Value::Text("PEN".to_string())
+        );
+        assert_eq!(
+            ScalarFunction::Round
+                .apply(&[Value::Float(2.345), Value::Integer(1)])
+                .unwrap(),
+            Value::Float(2.3)
+        );
+        assert_eq!(
+            ScalarFunction::Coalesce
+                .apply(&[Value::Null, Value::Integer(0)])
+                .unwrap(),
+            Value::Integer(0)
+        );
+        assert_eq!(
+            ScalarFunction::Concat
+                .apply(&[Value::Text("a".to_string()), Value::Null, Value::Integer(1)])
+                .unwrap(),
+            Value::Text("a1".to_string())
+        );
+        assert_eq!(
+            ScalarFunction::Length.apply(&[Value::Null]).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_check_arguments() {
+        assert!(ScalarFunction::Upper.check_arguments(2).is_err());
+        assert!(ScalarFunction::Round.check_arguments(2).is_ok());
+        assert!(ScalarFunction::Concat.check_arguments(0).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use super::command_parser::parse_column_definition;
+use super::custom_error::CustomError;
+use super::storage::replace_file;
+use super::tokenizer::{tokenize, Token};
+use super::value::{parse_date, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Los tipos que puede declarar una columna en el esquema de una tabla.
+pub enum ColumnType {
+    Integer,
+    Real,
+    Text,
+    Boolean,
+    /// Fechas con formato YYYY-MM-DD o DD/MM/YYYY, que se guardan y se comparan con formato YYYY-MM-DD.
+    Date,
+}
+
+impl ColumnType {
+    /// Retorna el tipo correspondiente a un nombre, sin importar mayúsculas.
+    pub fn from_name(name: &str) -> Option<ColumnType> {
+        match name.to_uppercase().as_str() {
+            "INTEGER" => Some(ColumnType::Integer),
+            "REAL" => Some(ColumnType::Real),
+            "TEXT" => Some(ColumnType::Text),
+            "BOOLEAN" => Some(ColumnType::Boolean),
+            "DATE" => Some(ColumnType::Date),
+            _ => None,
+        }
+    }
+
+    /// Indica si el texto de una celda CSV no vacía es un valor válido para este tipo.
+    pub fn accepts(&self, raw: &str) -> bool {
+        match self {
+            ColumnType::Integer => matches!(Value::from_csv(raw), Value::Integer(_)),
+            ColumnType::Real => Value::from_csv(raw).as_f64().is_some(),
+            ColumnType::Text => true,
+            ColumnType::Boolean => matches!(Value::from_csv(raw), Value::Boolean(_)),
+            ColumnType::Date => parse_date(raw).is_some(),
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColumnType::Integer => "INTEGER",
+            ColumnType::Real => "REAL",
+            ColumnType::Text => "TEXT",
+            ColumnType::Boolean => "BOOLEAN",
+            ColumnType::Date => "DATE",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// La definición de una columna: su nombre, su tipo y sus restricciones.
+/// Una columna sin tipo acepta cualquier valor.
+pub struct ColumnDefinition {
+    pub name: String,
+    pub column_type: Option<ColumnType>,
+    pub not_null: bool,
+    pub default: Option<Value>,
+    /// Una clave primaria no admite valores repetidos ni NULL.
+    pub primary_key: bool,
+    /// Una columna UNIQUE no admite valores repetidos, pero sí varios NULL.
+    pub unique: bool,
+}
+
+impl ColumnDefinition {
+    /// Crea la definición de una columna sin tipo ni restricciones.
+    pub fn new(name: &str) -> ColumnDefinition {
+        ColumnDefinition {
+            name: name.to_string(),
+            column_type: None,
+            not_null: false,
+            default: None,
+            primary_key: false,
+            unique: false,
+        }
+    }
+
+    /// Indica si la definición declara un tipo o alguna restricción.
+    pub fn has_constraints(&self) -> bool {
+        self.column_type.is_some() || self.not_null || self.default.is_some() || self.is_unique()
+    }
+
+    /// Indica si la columna no admite valores repetidos, ya sea por ser PRIMARY KEY o UNIQUE.
+    pub fn is_unique(&self) -> bool {
+        self.primary_key || self.unique
+    }
+
+    /// Interpreta el texto de una celda CSV según el tipo de la columna: en una columna TEXT es siempre Text,
+    /// en una columna DATE es Date con formato YYYY-MM-DD, y en una columna REAL es siempre Float.
+    /// Sin tipo, o si el texto no es del tipo declarado, se infiere con Value::from_csv.
+    pub fn value_from_csv(&self, raw: &str) -> Value {
+        let value = Value::from_csv(raw);
+        match (self.column_type, &value) {
+            (_, Value::Null) => value,
+            (Some(ColumnType::Text), _) => Value::Text(raw.to_string()),
+            (Some(ColumnType::Date), _) => match parse_date(raw) {
+                Some(date) => Value::Date(date),
+                None => Value::Text(raw.to_string()),
+            },
+            (Some(ColumnType::Real), Value::Integer(integer)) => Value::Float(*integer as f64),
+            _ => value,
+        }
+    }
+
+    /// Retorna el valor tal como se guarda en la columna: una fecha se guarda siempre con formato YYYY-MM-DD,
+    /// sin importar con qué formato se escribió. Los demás valores se guardan tal como se escribieron.
+    pub fn stored_value(&self, value: &Value) -> Value {
+        match self.column_type {
+            Some(ColumnType::Date) => self.value_from_csv(&value.to_string()),
+            _ => value.clone(),
+        }
+    }
+
+    /// Verifica que el texto de una celda CSV cumpla con el tipo y las restricciones de la columna.
+    pub fn validate(&self, raw: &str) -> Result<(), CustomError> {
+        if raw.is_empty() {
+            if self.not_null || self.primary_key {
+                CustomError::error_invalid_column(&format!("Column {} can't be NULL", self.name))?;
+            }
+            return Ok(());
+        }
+        if let Some(column_type) = self.column_type {
+            if !column_type.accepts(raw) {
+                CustomError::error_invalid_column(&format!(
+                    "Invalid value '{}' for column {} of type {}",
+                    raw, self.name, column_type
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ColumnDefinition {
+    /// Muestra la definición con la misma sintaxis que se usa en CREATE TABLE.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if tokenize(&self.name).ok() == Some(vec![Token::Identifier(self.name.to_string())]) {
+            write!(f, "{}", self.name)?;
+        } else {
+            // si el nombre no se tokeniza como identificador, se escribe entre comillas
+            write!(f, "'{}'", self.name)?;
+        }
+        if let Some(column_type) = self.column_type {
+            write!(f, " {}", column_type)?;
+        }
+        if self.not_null {
+            write!(f, " NOT NULL")?;
+        }
+        if let Some(default) = &self.default {
+            write!(f, " DEFAULT {}", default.to_sql_literal())?;
+        }
+        if self.primary_key {
+            write!(f, " PRIMARY KEY")?;
+        }
+        if self.unique {
+            write!(f, " UNIQUE")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// El esquema de una tabla, guardado junto a ella en un archivo <table_name>.schema.
+/// Cada línea del archivo es la definición de una columna, con la sintaxis de CREATE TABLE:
+/// <column> <type> NOT NULL DEFAULT <value> PRIMARY KEY UNIQUE
+/// Las columnas de la tabla que no aparecen en el esquema no tienen tipo ni restricciones.
+pub struct Schema {
+    pub columns: Vec<ColumnDefinition>,
+}
+
+/// Retorna el path del archivo de esquema de una tabla.
+pub fn schema_path(directory: &str, table_name: &str) -> String {
+    format!("{}/{}.schema", directory, table_name)
+}
+
+/// Retorna la definición de cada columna de una tabla, en el orden recibido.
+/// Las columnas que no están en el esquema, o todas si la tabla no tiene esquema, no tienen tipo ni restricciones.
+pub fn column_definitions(schema: Option<&Schema>, columns: &[String]) -> Vec<ColumnDefinition> {
+    columns
+        .iter()
+        .map(|column| {
+            schema
+                .and_then(|schema| schema.column(column))
+                .cloned()
+                .unwrap_or_else(|| ColumnDefinition::new(column))
+        })
+        .collect()
+}
+
+impl Schema {
+    /// Lee el esquema de una tabla. Si la tabla no tiene archivo de esquema, retorna None.
+    pub fn load(directory: &str, table_name: &str) -> Result<Option<Schema>, CustomError> {
+        let contents = match fs::read_to_string(schema_path(directory, table_name)) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(None),
+        };
+        let mut columns = vec![];
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let tokens = tokenize(line)?;
+            let mut iter = tokens.iter().peekable();
+            let definition = parse_column_definition(&mut iter)?;
+            if iter.next().is_some() {
+                CustomError::error_invalid_table(&format!(
+                    "Invalid schema for table {}: {}",
+                    table_name, line
+                ))?;
+            }
+            columns.push(definition);
+        }
+        Ok(Some(Schema { columns }))
+    }
+
+    /// Escribe el esquema de una tabla en su archivo de esquema, reemplazándolo de forma atómica.
+    pub fn save(&self, directory: &str, table_name: &str) -> Result<(), CustomError> {
+        replace_file(directory, &format!("{}.schema", table_name), |writer| {
+            for column in &self.columns {
+                if writeln!(writer, "{}", column).is_err() {
+                    CustomError::error_invalid_table("Couldn't write schema file")?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Retorna la definición de una columna, si el esquema la incluye.
+    pub fn column(&self, name: &str) -> Option<&ColumnDefinition> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Retorna la definición de una columna para poder modificarla, si el esquema la incluye.
+    pub fn column_mut(&mut self, name: &str) -> Option<&mut ColumnDefinition> {
+        self.columns.iter_mut().find(|column| column.name == name)
+    }
+
+    /// Retorna las definiciones de las columnas que no admiten valores repetidos.
+    pub fn unique_columns(&self) -> Vec<ColumnDefinition> {
+        self.columns
+            .iter()
+            .filter(|column| column.is_unique())
+            .cloned()
+            .collect()
+    }
+
+    /// Verifica que un valor sea válido para una columna.
+    pub fn validate_value(&self, column: &str, raw: &str) -> Result<(), CustomError> {
+        match self.column(column) {
+            Some(definition) => definition.validate(raw),
+            None => Ok(()),
+        }
+    }
+
+    /// Completa los valores de una fila nueva con los valores por defecto de las columnas que no los tienen,
+    /// verifica que todos los valores sean válidos para sus columnas y los convierte al valor que se guarda.
+    pub fn prepare_row(&self, values: &mut HashMap<String, Value>) -> Result<(), CustomError> {
+        for definition in &self.columns {
+            if let (false, Some(default)) =
+                (values.contains_key(&definition.name), &definition.default)
+            {
+                values.insert(definition.name.to_string(), default.clone());
+            }
+            let raw = values
+                .get(&definition.name)
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+            definition.validate(&raw)?;
+            if let Some(value) = values.get_mut(&definition.name) {
+                *value = definition.stored_value(value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_schema() -> Schema {
+        Schema {
+            columns: vec![
+                ColumnDefinition {
+                    name: "Price".to_string(),
+                    column_type: Some(ColumnType::Real),
+                    not_null: true,
+                    default: None,
+                    primary_key: false,
+                    unique: false,
+                },
+                ColumnDefinition {
+                    name: "Stock".to_string(),
+                    column_type: Some(ColumnType::Integer),
+                    not_null: false,
+                    default: Some(Value::Integer(0)),
+                    primary_key: false,
+                    unique: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_validate_value() {
+        let schema = create_schema();
+        assert!(schema.validate_value("Price", "1.25").is_ok());
+        assert!(schema.validate_value("Other", "anything").is_ok());
+        assert_eq!(
+            schema.validate_value("Price", "abc").err().unwrap(),
+            CustomError::InvalidColumn {
+                message: "Invalid value 'abc' for column Price of type REAL".to_string()
+            }
+        );
+        assert_eq!(
+            schema.validate_value("Price", "").err().unwrap(),
+            CustomError::InvalidColumn {
+                message: "Column Price can't be NULL".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_prepare_row_with_default() {
+        let schema = create_schema();
+        let mut values = HashMap::new();
+        values.insert("Price".to_string(), Value::Text("2.50".to_string()));
+        schema.prepare_row(&mut values).unwrap();
+        assert_eq!(values.get("Stock"), Some(&Value::Integer(0)));
+    }
+
+    #[test]
+    fn test_value_from_csv_uses_column_type() {
+        let mut code = ColumnDefinition::new("Code");
+        assert_eq!(code.value_from_csv("007"), Value::Integer(7));
+        code.column_type = Some(ColumnType::Text);
+        assert_eq!(code.value_from_csv("007"), Value::Text("007".to_string()));
+        assert_eq!(code.value_from_csv(""), Value::Null);
+        let schema = create_schema();
+        let price = schema.column("Price").unwrap();
+        assert_eq!(price.value_from_csv("3"), Value::Float(3.0));
+    }
+
+    #[test]
+    fn test_valid_dates() {
+        assert!(ColumnType::Date.accepts("2023-09-02"));
+        assert!(ColumnType::Date.accepts("29/02/2024"));
+        assert!(!ColumnType::Date.accepts("29/02/2023"));
+        assert!(!ColumnType::Date.accepts("2023-13-01"));
+        assert!(!ColumnType::Date.accepts("yesterday"));
+    }
+
+    #[test]
+    fn test_dates_are_stored_as_iso() {
+        let mut birth = ColumnDefinition::new("Birth");
+        birth.column_type = Some(ColumnType::Date);
+        assert_eq!(
+            birth.value_from_csv("04/03/1995"),
+            Value::Date("1995-03-04".to_string())
+        );
+        assert_eq!(
+            birth.stored_value(&Value::Text("04/03/1995".to_string())),
+            Value::Date("1995-03-04".to_string())
+        );
+        assert_eq!(birth.stored_value(&Value::Null), Value::Null);
+        let schema = Schema {
+            columns: vec![birth],
+        };
+        let mut values = HashMap::new();
+        values.insert("Birth".to_string(), Value::Text("04/03/1995".to_string()));
+        schema.prepare_row(&mut values).unwrap();
+        assert_eq!(
+            values.get("Birth").map(|value| value.to_string()),
+            Some("1995-03-04".to_string())
+        );
+    }
+
+    #[test]
+    fn test_column_definition_display() {
+        let schema = create_schema();
+        assert_eq!(schema.columns[0].to_string(), "Price REAL NOT NULL");
+        assert_eq!(
+            ColumnDefinition::new("full name").to_string(),
+            "'full name'"
+        );
+        let mut id = ColumnDefinition::new("ID");
+        id.column_type = Some(ColumnType::Integer);
+        id.primary_key = true;
+        assert_eq!(id.to_string(), "ID INTEGER PRIMARY KEY");
+    }
+
+    #[test]
+    fn test_primary_key_is_not_null() {
+        let mut id = ColumnDefinition::new("ID");
+        id.primary_key = true;
+        assert_eq!(
+            id.validate("").err().unwrap(),
+            CustomError::InvalidColumn {
+                message: "Column ID can't be NULL".to_string()
+            }
+        );
+    }
+}
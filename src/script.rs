@@ -0,0 +1,154 @@
+use super::command_parser::parse_statement;
+use super::custom_error::CustomError;
+use super::database::Database;
+use super::tokenizer::tokenize;
+use std::io::Write;
+
+/// Separa un texto con varios comandos SQL en los comandos que terminan con ';'.
+/// Los ';' dentro de strings no separan comandos, y los comentarios que empiezan con -- hasta el fin de la línea se descartan.
+/// Retorna los comandos completos, cada uno con su ';', y el texto que quedó después del último ';'.
+pub fn split_statements(script: &str) -> (Vec<String>, String) {
+    let mut statements = vec![];
+    let mut statement = String::new();
+    let mut in_string = false;
+    let mut escapes = false; // si el string actual se escribió como E'...', donde \' no lo cierra
+    let mut previous = [' ', ' ']; // los dos últimos caracteres fuera de un string
+    let mut chars = script.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if in_string {
+            // una comilla escapada como '' cierra y vuelve a abrir el string, por lo que no hace falta tratarla aparte
+            statement.push(ch);
+            if escapes && ch == '\\' {
+                statement.extend(chars.next());
+            } else {
+                in_string = ch != '\'';
+            }
+        } else if ch == '-' && chars.peek() == Some(&'-') {
+            // el comentario se reemplaza por el salto de línea que lo termina
+            for ch in chars.by_ref() {
+                if ch == '\n' {
+                    statement.push('\n');
+                    break;
+                }
+            }
+        } else if ch == ';' {
+            statement.push(ch);
+            statements.push(statement.trim().to_string());
+            statement = String::new();
+        } else {
+            in_string = ch == '\'';
+            // la E de un E'...' no puede ser el final de un nombre
+            escapes = in_string
+                && matches!(previous[1], 'E' | 'e')
+                && !(previous[0].is_alphanumeric() || ['_', '-'].contains(&previous[0]));
+            statement.push(ch);
+        }
+        previous = [previous[1], ch];
+    }
+    (statements, statement)
+}
+
+/// Ejecuta un comando SQL sobre la base de datos.
+pub fn run_statement<W: Write>(
+    database: &Database,
+    command: &str,
+    output: &mut W,
+) -> Result<(), CustomError> {
+    let tokens = tokenize(command)?;
+    let statement = parse_statement(&tokens)?;
+    database.execute(statement, output)?;
+    Ok(())
+}
+
+/// Ejecuta en orden los comandos de un script SQL, escribiendo sus resultados en output.
+///
+/// Cada comando que falla se informa en errors junto a su número dentro del script, contando desde 1.
+/// Si stop_on_error es true, la ejecución se detiene en el primer error; si no, se continúa con el comando siguiente.
+/// Retorna el último error encontrado, o Ok si todos los comandos se ejecutaron.
+/// Un texto sin ';' al final del script se ejecuta como un último comando, para informar su error.
+pub fn run_script<W: Write, E: Write>(
+    database: &Database,
+    script: &str,
+    stop_on_error: bool,
+    output: &mut W,
+    errors: &mut E,
+) -> Result<(), CustomError> {
+    let (mut statements, remainder) = split_statements(script);
+    if !remainder.trim().is_empty() {
+        statements.push(remainder.trim().to_string());
+    }
+    let mut result = Ok(());
+    for (index, statement) in statements.iter().enumerate() {
+        if let Err(error) = run_statement(database, statement, output) {
+            if writeln!(errors, "Statement {}: {}", index + 1, error).is_err() {
+                return CustomError::error_generic("Couldn't write output");
+            }
+            result = Err(error);
+            if stop_on_error {
+                break;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_split_statements() {
+        let script =
+            "SELECT * FROM a; -- comentario; con ;\nINSERT INTO a (b) VALUES ('x;y');\nSELECT";
+        let (statements, remainder) = split_statements(script);
+        assert_eq!(
+            statements,
+            vec![
+                "SELECT * FROM a;".to_string(),
+                "INSERT INTO a (b) VALUES ('x;y');".to_string(),
+            ]
+        );
+        assert_eq!(remainder, "\nSELECT");
+
+        let (statements, remainder) =
+            split_statements("INSERT INTO a (b) VALUES (E'x\\';y', 'it''s;');\nSELECT name'");
+        assert_eq!(
+            statements,
+            vec!["INSERT INTO a (b) VALUES (E'x\\';y', 'it''s;');".to_string()]
+        );
+        assert_eq!(remainder, "\nSELECT name'");
+    }
+
+    #[test]
+    fn test_run_script_stop_or_continue_on_error() {
+        let directory = "test_script_errors";
+        fs::create_dir_all(directory).unwrap();
+        fs::write(format!("{}/items.csv", directory), "id\n").unwrap();
+        let database = Database::open(directory).unwrap();
+        let script = "INSERT INTO items (id) VALUES (1);\nINSERT INTO missing (id) VALUES (2);\nINSERT INTO items (id) VALUES (3);";
+
+        let mut errors = vec![];
+        assert!(run_script(&database, script, true, &mut vec![], &mut errors).is_err());
+        assert_eq!(
+            String::from_utf8(errors).unwrap(),
+            "Statement 2: ERROR: Couldn't open table file\n"
+        );
+        assert_eq!(
+            fs::read_to_string(format!("{}/items.csv", directory)).unwrap(),
+            "id\n1\n"
+        );
+
+        let mut errors = vec![];
+        assert!(run_script(&database, script, false, &mut vec![], &mut errors).is_err());
+        assert_eq!(
+            String::from_utf8(errors).unwrap(),
+            "Statement 2: ERROR: Couldn't open table file\n"
+        );
+        assert_eq!(
+            fs::read_to_string(format!("{}/items.csv", directory)).unwrap(),
+            "id\n1\n1\n3\n"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
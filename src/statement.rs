@@ -0,0 +1,288 @@
+use super::expression::{Expression, Operand};
+use super::schema::ColumnDefinition;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Un Statement es un comando SQL ya parseado, listo para ser inspeccionado o ejecutado.
+pub enum Statement {
+    /// INSERT INTO <table_name> (<columns>) VALUES (<values>), ...;
+    /// Cada elemento de values es un HashMap columna -> valor.
+    Insert {
+        table_name: String,
+        columns: Vec<String>,
+        values: Vec<HashMap<String, Operand>>,
+    },
+    /// UPDATE <table_name> SET <set_values> WHERE <condition>;
+    Update {
+        table_name: String,
+        set_values: HashMap<String, Operand>,
+        condition: Expression,
+    },
+    /// DELETE FROM <table_name> WHERE <condition>;
+    Delete {
+        table_name: String,
+        condition: Expression,
+    },
+    /// SELECT DISTINCT <columns> FROM <table_name> <table_alias> <joins> WHERE <condition> GROUP BY <group_by> HAVING <having>
+    /// ORDER BY <order_by> LIMIT <limit>;
+    /// Si columns está vacío se seleccionan todas las columnas. Cada columna es una expresión que puede tener un alias,
+    /// y se llama con su alias o como se escribe la expresión.
+    /// Con un alias o con joins, las columnas de cada tabla se llaman <alias>.<columna>, o solo <columna> si no es ambigua.
+    /// Si hay funciones de agregación o group_by no está vacío, se retorna una fila por grupo, que se filtran con having.
+    /// Las filas se ordenan según cada elemento de order_by, donde cada uno solo desempata a los anteriores.
+    /// Si hay limit, solo se retornan las filas que indica, luego de ordenarlas.
+    /// Con distinct, las filas repetidas del resultado se retornan una sola vez.
+    Select {
+        distinct: bool,
+        columns: Vec<SelectColumn>,
+        table_name: String,
+        table_alias: Option<String>,
+        joins: Vec<Join>,
+        condition: Expression,
+        group_by: Vec<Operand>,
+        having: Box<Expression>,
+        order_by: Vec<OrderBy>,
+        limit: Option<Limit>,
+    },
+    /// CREATE TABLE IF NOT EXISTS <table_name> (<column definitions>);
+    CreateTable {
+        table_name: String,
+        columns: Vec<ColumnDefinition>,
+        if_not_exists: bool,
+    },
+    /// DROP TABLE IF EXISTS <table_name>;
+    DropTable { table_name: String, if_exists: bool },
+    /// ALTER TABLE <table_name> <operation>;
+    AlterTable {
+        table_name: String,
+        operation: AlterOperation,
+    },
+    /// BEGIN TRANSACTION;
+    Begin,
+    /// COMMIT;
+    Commit,
+    /// ROLLBACK;
+    Rollback,
+}
+
+impl Statement {
+    /// Retorna el nombre del comando SQL del Statement.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Statement::Insert { .. } => "INSERT",
+            Statement::Update { .. } => "UPDATE",
+            Statement::Delete { .. } => "DELETE",
+            Statement::Select { .. } => "SELECT",
+            Statement::CreateTable { .. } => "CREATE TABLE",
+            Statement::DropTable { .. } => "DROP TABLE",
+            Statement::AlterTable { .. } => "ALTER TABLE",
+            Statement::Begin => "BEGIN",
+            Statement::Commit => "COMMIT",
+            Statement::Rollback => "ROLLBACK",
+        }
+    }
+
+    /// Indica si el Statement es un SELECT que agrupa filas, ya sea por tener GROUP BY o funciones de agregación.
+    pub fn is_aggregate(&self) -> bool {
+        match self {
+            Statement::Select {
+                columns,
+                group_by,
+                having,
+                ..
+            } => {
+                !group_by.is_empty()
+                    || !having.aggregates().is_empty()
+                    || columns
+                        .iter()
+                        .any(|column| !column.operand.aggregates().is_empty())
+            }
+            _ => false,
+        }
+    }
+
+    /// Indica si el Statement solo lee tablas, sin modificarlas.
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Statement::Select { .. })
+    }
+
+    /// Retorna los nombres de las tablas que lee o modifica el Statement.
+    pub fn table_names(&self) -> Vec<String> {
+        match self {
+            Statement::Insert { table_name, .. }
+            | Statement::Update { table_name, .. }
+            | Statement::Delete { table_name, .. }
+            | Statement::CreateTable { table_name, .. }
+            | Statement::DropTable { table_name, .. } => vec![table_name.to_string()],
+            Statement::AlterTable {
+                table_name,
+                operation: AlterOperation::RenameTable { new_name },
+            } => vec![table_name.to_string(), new_name.to_string()],
+            Statement::AlterTable { table_name, .. } => vec![table_name.to_string()],
+            Statement::Select {
+                table_name, joins, ..
+            } => {
+                let mut table_names = vec![table_name.to_string()];
+                for join in joins {
+                    if !table_names.contains(&join.table_name) {
+                        table_names.push(join.table_name.to_string());
+                    }
+                }
+                table_names
+            }
+            Statement::Begin | Statement::Commit | Statement::Rollback => vec![],
+        }
+    }
+
+    /// Retorna referencias a todos los parámetros del Statement, para poder reemplazarlos por sus valores.
+    pub(crate) fn parameters_mut(&mut self) -> Vec<&mut Operand> {
+        let mut parameters = vec![];
+        match self {
+            Statement::Insert { values: rows, .. } => {
+                for row in rows {
+                    for operand in row.values_mut() {
+                        operand.collect_parameters_mut(&mut parameters);
+                    }
+                }
+            }
+            Statement::Update {
+                set_values,
+                condition,
+                ..
+            } => {
+                for operand in set_values.values_mut() {
+                    operand.collect_parameters_mut(&mut parameters);
+                }
+                condition.collect_parameters_mut(&mut parameters);
+            }
+            Statement::Delete { condition, .. } => {
+                condition.collect_parameters_mut(&mut parameters)
+            }
+            Statement::Select {
+                columns,
+                joins,
+                condition,
+                having,
+                ..
+            } => {
+                for column in columns {
+                    column.operand.collect_parameters_mut(&mut parameters);
+                }
+                for join in joins {
+                    join.condition.collect_parameters_mut(&mut parameters);
+                }
+                condition.collect_parameters_mut(&mut parameters);
+                having.collect_parameters_mut(&mut parameters);
+            }
+            _ => {}
+        }
+        parameters
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Las operaciones que puede realizar un ALTER TABLE sobre una tabla.
+pub enum AlterOperation {
+    /// ADD COLUMN <column definition>, donde las filas existentes toman el valor DEFAULT de la definición.
+    AddColumn { column: ColumnDefinition },
+    /// DROP COLUMN <column>
+    DropColumn { column: String },
+    /// RENAME COLUMN <from> TO <to>
+    RenameColumn { from: String, to: String },
+    /// RENAME TO <new_name>
+    RenameTable { new_name: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// LIMIT <count> OFFSET <offset>: se saltean las primeras offset filas del resultado y se retornan las count siguientes.
+pub struct Limit {
+    pub count: usize,
+    pub offset: usize,
+}
+
+impl Limit {
+    /// Retorna cuántas filas del resultado hay que obtener para retornar las del límite.
+    pub fn end(&self) -> usize {
+        self.count.saturating_add(self.offset)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Los tipos de JOIN entre las filas de un SELECT y las de otra tabla.
+pub enum JoinType {
+    /// Solo las combinaciones que cumplen la condición.
+    Inner,
+    /// Además, cada fila de la izquierda sin combinaciones, con NULL en las columnas de la derecha.
+    Left,
+    /// Además, cada fila de la derecha sin combinaciones, con NULL en las columnas de la izquierda.
+    Right,
+    /// Todas las combinaciones, sin condición.
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// [INNER | LEFT | RIGHT | CROSS] JOIN <table_name> <alias> ON <condition>
+/// Las columnas de la tabla se califican con su alias, o con su nombre si no tiene.
+pub struct Join {
+    pub join_type: JoinType,
+    pub table_name: String,
+    pub alias: Option<String>,
+    pub condition: Expression,
+}
+
+impl Join {
+    /// Retorna el nombre con que se califican las columnas de la tabla.
+    pub fn qualifier(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.table_name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// El sentido en que ORDER BY ordena las filas según una de sus claves.
+pub enum SortDirection {
+    /// ASC, de menor a mayor según Value::compare. Es el sentido por defecto.
+    Ascending,
+    /// DESC, de mayor a menor.
+    Descending,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// <operand> [ASC | DESC]: una clave de ORDER BY. El operando es una columna, que puede ser el alias de una columna
+/// del resultado, o una función de agregación de un SELECT agrupado.
+pub struct OrderBy {
+    pub operand: Operand,
+    pub direction: SortDirection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// <operand> AS <alias>: una columna del resultado de un SELECT.
+pub struct SelectColumn {
+    pub operand: Operand,
+    pub alias: Option<String>,
+}
+
+impl SelectColumn {
+    /// Crea una columna del resultado que muestra una columna de la tabla, sin alias.
+    pub fn column(name: &str) -> SelectColumn {
+        SelectColumn {
+            operand: Operand::Column(name.to_string()),
+            alias: None,
+        }
+    }
+
+    /// Retorna el nombre de la columna en el resultado: su alias, o la expresión como se escribe.
+    pub fn name(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.to_string(),
+            None => self.operand.to_string(),
+        }
+    }
+
+    /// Indica si el valor de la columna debe calcularse para cada fila, por no ser una columna de la tabla con su nombre.
+    pub fn is_computed(&self) -> bool {
+        !matches!(
+            (&self.operand, &self.alias),
+            (Operand::Column(_), None) | (Operand::Aggregate(_), None)
+        )
+    }
+}
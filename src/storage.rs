@@ -0,0 +1,144 @@
+use super::custom_error::CustomError;
+use std::fs::{self, File, OpenOptions};
+use std::io::BufWriter;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// contador para que dos archivos temporales del mismo proceso nunca compartan nombre
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Elimina un archivo.
+pub fn remove_file(file_path: &str) -> Result<(), CustomError> {
+    if fs::remove_file(file_path).is_err() {
+        return CustomError::error_generic("Couldn't remove file");
+    }
+    Ok(())
+}
+
+/// Renombra un archivo. Si el destino existe, se reemplaza de forma atómica.
+pub fn rename_file(from: &str, to: &str) -> Result<(), CustomError> {
+    if fs::rename(from, to).is_err() {
+        return CustomError::error_generic("Couldn't rename file");
+    }
+    Ok(())
+}
+
+/// Crea un archivo temporal en el directorio recibido, con un nombre único formado por
+/// el nombre del archivo a reemplazar, el id del proceso, un contador y la hora actual.
+/// Retorna el path del archivo creado y el archivo abierto para escritura.
+pub fn create_temp_file(directory: &str, file_name: &str) -> Result<(String, File), CustomError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or_default();
+    let counter = TEMP_FILE_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let tmp_path = format!(
+        "{}/.{}.{}.{}.{}.tmp",
+        directory,
+        file_name,
+        process::id(),
+        counter,
+        nanos
+    );
+    // create_new falla si el archivo ya existe, por lo que nunca se pisa un temporal ajeno
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+    {
+        Ok(file) => Ok((tmp_path, file)),
+        Err(_) => Err(CustomError::GenericError {
+            message: "Couldn't create temporary file".to_string(),
+        }),
+    }
+}
+
+/// Retorna el directorio de archivos temporales del sistema, donde se guardan los datos intermedios de una consulta
+/// o de la construcción de un índice, que no deben quedar junto a las tablas.
+pub fn temp_directory() -> String {
+    std::env::temp_dir().to_string_lossy().to_string()
+}
+
+/// Sincroniza el directorio con el disco, para que un rename o la creación de un archivo sobrevivan a un corte de luz.
+pub fn sync_directory(directory: &str) -> Result<(), CustomError> {
+    if cfg!(unix)
+        && File::open(directory)
+            .and_then(|dir| dir.sync_all())
+            .is_err()
+    {
+        // en otros sistemas no se puede abrir un directorio como archivo
+        return CustomError::error_generic("Couldn't sync directory");
+    }
+    Ok(())
+}
+
+/// Reemplaza el contenido de un archivo de forma atómica.
+/// write_file escribe el contenido nuevo en un archivo temporal del mismo directorio, que se sincroniza con el disco
+/// y luego se renombra sobre el archivo original, por lo que el archivo siempre tiene su contenido anterior o el nuevo completo.
+/// Si algo falla, el archivo original queda intacto y se elimina el temporal.
+pub fn replace_file<F>(directory: &str, file_name: &str, write_file: F) -> Result<(), CustomError>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), CustomError>,
+{
+    let file_path = format!("{}/{}", directory, file_name);
+    let (tmp_path, tmp_file) = create_temp_file(directory, file_name)?;
+    let mut writer = BufWriter::new(tmp_file);
+    let result = write_file(&mut writer)
+        .and_then(|_| sync_writer(writer))
+        .and_then(|_| rename_file(&tmp_path, &file_path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path); // el error original es más relevante que uno al limpiar
+        return result;
+    }
+    sync_directory(directory)
+}
+
+/// Vacía el buffer de un writer y sincroniza su archivo con el disco.
+pub fn sync_writer(writer: BufWriter<File>) -> Result<(), CustomError> {
+    match writer.into_inner() {
+        Ok(file) if file.sync_all().is_ok() => Ok(()),
+        _ => CustomError::error_generic("Couldn't write temporary file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_create_temp_file_unique_names() {
+        let directory = std::env::temp_dir();
+        let directory = directory.to_str().unwrap();
+        let (first_path, _) = create_temp_file(directory, "table.csv").unwrap();
+        let (second_path, _) = create_temp_file(directory, "table.csv").unwrap();
+        assert_ne!(first_path, second_path);
+        fs::remove_file(first_path).unwrap();
+        fs::remove_file(second_path).unwrap();
+    }
+
+    #[test]
+    fn test_replace_file_failure_keeps_original() {
+        let directory = "test_storage_replace";
+        let file_path = format!("{}/file.csv", directory);
+        fs::create_dir_all(directory).unwrap();
+        fs::write(&file_path, "original\n").unwrap();
+        let result = replace_file(directory, "file.csv", |writer| {
+            writeln!(writer, "partial").unwrap();
+            CustomError::error_generic("failed")
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "original\n");
+        assert_eq!(fs::read_dir(directory).unwrap().count(), 1); // no queda el temporal
+
+        replace_file(directory, "file.csv", |writer| {
+            writeln!(writer, "replaced").map_err(|_| CustomError::GenericError {
+                message: "write".to_string(),
+            })
+        })
+        .unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "replaced\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use super::custom_error::CustomError;
+use super::prepared::Parameter;
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -6,7 +7,12 @@ use std::str::Chars;
 /// Los Tokens son la unidad mínima de un comando SQL existen para facilitar su parseo.
 pub enum Token {
     /// Los Keywords son palabras clave de un comando SQL, esta implementación incluye:
-    /// INSERT, UPDATE, DELETE, SELECT, FROM, WHERE, SET, INTO, VALUES, ORDER, BY, DESC, ASC
+    /// INSERT, UPDATE, DELETE, SELECT, FROM, WHERE, SET, INTO, VALUES, ORDER, BY, DESC, ASC, NULL, TRUE, FALSE,
+    /// CREATE, DROP, TABLE, IF, EXISTS, ALTER, ADD, COLUMN, RENAME, TO, DEFAULT, PRIMARY, KEY, UNIQUE,
+    /// BEGIN, TRANSACTION, COMMIT, ROLLBACK, GROUP, HAVING, JOIN, INNER, LEFT, RIGHT, CROSS, OUTER, ON, AS,
+    /// LIMIT, OFFSET, DISTINCT
+    /// Se conserva la palabra tal como fue escrita, ya que en la posición de un nombre de tabla o columna
+    /// se interpreta como ese nombre. Al compararla como palabra clave no se distinguen mayúsculas.
     Keyword(String),
     /// Los LogicalOperators son operadores lógicos, en esta implementación incluye:
     /// AND, OR, NOT
@@ -15,63 +21,221 @@ pub enum Token {
     /// =, >, <, >=, <=
     ComparisonOperator(String),
     /// Los Identifiers son nombres de tablas o columnas, pueden ser alfanuméricos.
+    /// Una columna puede estar calificada con el nombre o alias de su tabla, como p.name.
     Identifier(String),
-    /// Los Strings son cadenas de texto llegadas entre comillas simples.
+    /// Los Strings son cadenas de texto llegadas entre comillas simples. Una comilla se escribe como ''.
     String(String),
-    /// Los Integers son números enteros.
+    /// Los Integers son números enteros, que pueden tener signo.
     Integer(String),
+    /// Los Floats son números con parte decimal o exponente, como 2.50, -3.5 o 1e-3.
+    Float(String),
+    /// Los Parameters ocupan el lugar de un valor en un comando preparado: ?, $1 o :name.
+    /// Cada ? toma la posición siguiente a la mayor usada antes en el comando.
+    Parameter(Parameter),
     /// Los Symbols son caracteres especiales, en esta implementación incluye:
-    /// , ( ) ; *
+    /// , ( ) ; * + - / %
+    /// Como un nombre puede contener '-', la resta se escribe con espacios: Price - Discount.
     Symbol(char),
 }
 
-fn tokenize_integer_or_identifier_starting_with_integer(chars: &mut Peekable<Chars>) -> Token {
+/// Agrega a token_value los dígitos siguientes, retornando si se agregó alguno.
+fn push_digits(chars: &mut Peekable<Chars>, token_value: &mut String) -> bool {
+    let mut pushed = false;
+    while let Some(&ch) = chars.peek().filter(|ch| ch.is_ascii_digit()) {
+        token_value.push(ch);
+        chars.next();
+        pushed = true;
+    }
+    pushed
+}
+
+/// Indica si el caracter siguiente es alguno de los esperados y el que lo sigue es un dígito, o un signo seguido de un dígito
+/// si se aceptan signos.
+fn followed_by_digits(chars: &Peekable<Chars>, expected: &[char], signed: bool) -> bool {
+    let mut lookahead = chars.clone();
+    if !lookahead.next().is_some_and(|ch| expected.contains(&ch)) {
+        return false;
+    }
+    match lookahead.next() {
+        Some('+') | Some('-') if signed => lookahead.next().is_some_and(|ch| ch.is_ascii_digit()),
+        Some(ch) => ch.is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Tokeniza un número, que puede tener signo, parte decimal y exponente, o un nombre que empieza con dígitos.
+/// Los números con parte decimal o exponente son Floats, y los demás Integers.
+fn tokenize_number_or_identifier_starting_with_integer(
+    chars: &mut Peekable<Chars>,
+) -> Result<Token, CustomError> {
     let mut token_value = String::new();
-    while let Some(&ch) = chars.peek() {
-        // este ciclo se termina cuando el caracter no es alfanumérico
-        if ch.is_ascii_digit() {
-            // si es un digito se agrega al string
+    if let Some(&sign) = chars.peek().filter(|ch| ['+', '-'].contains(ch)) {
+        if sign == '-' {
+            token_value.push(sign);
+        }
+        chars.next();
+    }
+    push_digits(chars, &mut token_value);
+    let mut is_float = false;
+    if followed_by_digits(chars, &['.'], false) {
+        // parte decimal
+        token_value.push('.');
+        chars.next();
+        push_digits(chars, &mut token_value);
+        is_float = true;
+    }
+    if followed_by_digits(chars, &['e', 'E'], true) {
+        // exponente
+        token_value.push('e');
+        chars.next();
+        if let Some(&sign) = chars.peek().filter(|ch| ['+', '-'].contains(ch)) {
+            token_value.push(sign);
+            chars.next();
+        }
+        push_digits(chars, &mut token_value);
+        is_float = true;
+    }
+    if let Some(&ch) = chars.peek().filter(|ch| ch.is_alphabetic() || **ch == '_') {
+        if token_value.contains(['.', '-']) {
+            return Err(CustomError::InvalidSyntax {
+                message: format!("Invalid number near: {}{}", token_value, ch),
+            });
+        }
+        // si se encontró una letra, es un identificador. Se agrega al string hasta que no sea alfanumérico
+        while let Some(&ch) = chars
+            .peek()
+            .filter(|ch| ch.is_alphanumeric() || **ch == '_')
+        {
             token_value.push(ch);
             chars.next();
-        } else if ch.is_alphabetic() {
-            // si se encontró una letra, es un identificador. Se agrega al string hasta que no sea alfanumérico y retorna un Token::Identifier
-            while let Some(&ch) = chars.peek() {
-                if ch.is_alphanumeric() {
-                    token_value.push(ch);
-                    chars.next();
-                } else {
-                    break;
-                }
-            }
-            return Token::Identifier(token_value); // se retorna un Token::Identifier
-        } else {
-            // si no es alfanumérico se termina el ciclo
-            break;
         }
+        return Ok(Token::Identifier(token_value));
+    }
+    if is_float {
+        Ok(Token::Float(token_value))
+    } else {
+        Ok(Token::Integer(token_value))
+    }
+}
+
+/// Indica si los caracteres siguientes empiezan un número, con un signo si signed es true.
+fn starts_number(chars: &Peekable<Chars>, signed: bool) -> bool {
+    let mut lookahead = chars.clone();
+    let mut ch = lookahead.next();
+    if signed && matches!(ch, Some('+') | Some('-')) {
+        ch = lookahead.next();
     }
-    Token::Integer(token_value) // si no se encontró una letra, es un número entero. Se retorna un Token::Integer
+    if ch == Some('.') {
+        ch = lookahead.next();
+    }
+    ch.is_some_and(|ch| ch.is_ascii_digit())
+}
+
+/// Indica si un token puede terminar un valor, en cuyo caso un + o - que lo siga no es el signo de un número.
+fn ends_value(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(Token::Identifier(_))
+            | Some(Token::String(_))
+            | Some(Token::Integer(_))
+            | Some(Token::Float(_))
+            | Some(Token::Parameter(_))
+            | Some(Token::Symbol(')'))
+    )
+}
+
+/// Indica si los caracteres siguientes son un '.' seguido del nombre de una columna, que califica a la palabra anterior.
+fn starts_qualified_name(chars: &Peekable<Chars>) -> bool {
+    let mut lookahead = chars.clone();
+    lookahead.next() == Some('.')
+        && lookahead
+            .next()
+            .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+}
+
+/// Indica si un '-' es el operador de resta: cuando sigue a un valor, o cuando no empieza un nombre.
+fn starts_subtraction(chars: &Peekable<Chars>, last_token: Option<&Token>) -> bool {
+    let mut lookahead = chars.clone();
+    lookahead.next();
+    ends_value(last_token)
+        || !lookahead
+            .next()
+            .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
 }
 
 fn tokenize_word(chars: &mut Peekable<Chars>) -> Token {
     let mut word = String::new();
+    let mut qualified = false;
     while let Some(&ch) = chars.peek() {
         // se agrega al string hasta que no sea alfanumérico
         if ch.is_alphanumeric() || ch == '_' || ch == '-' {
             word.push(ch);
             chars.next();
+        } else if !qualified && starts_qualified_name(chars) {
+            // tabla.columna es un único identificador
+            qualified = true;
+            word.push(ch);
+            chars.next();
         } else {
             break;
         }
     }
     let word_upper = word.to_uppercase();
-    if [
-        "INSERT", "UPDATE", "DELETE", "SELECT", "FROM", "WHERE", "SET", "INTO", "VALUES", "ORDER",
-        "BY", "DESC", "ASC",
+    if qualified {
+        Token::Identifier(word)
+    } else if [
+        "INSERT",
+        "UPDATE",
+        "DELETE",
+        "SELECT",
+        "FROM",
+        "WHERE",
+        "SET",
+        "INTO",
+        "VALUES",
+        "ORDER",
+        "BY",
+        "DESC",
+        "ASC",
+        "NULL",
+        "TRUE",
+        "FALSE",
+        "CREATE",
+        "DROP",
+        "TABLE",
+        "IF",
+        "EXISTS",
+        "ALTER",
+        "ADD",
+        "COLUMN",
+        "RENAME",
+        "TO",
+        "DEFAULT",
+        "PRIMARY",
+        "KEY",
+        "UNIQUE",
+        "BEGIN",
+        "TRANSACTION",
+        "COMMIT",
+        "ROLLBACK",
+        "GROUP",
+        "HAVING",
+        "JOIN",
+        "INNER",
+        "LEFT",
+        "RIGHT",
+        "CROSS",
+        "OUTER",
+        "ON",
+        "AS",
+        "LIMIT",
+        "OFFSET",
+        "DISTINCT",
     ]
     .contains(&word_upper.as_str())
     // si es una palabra clave se retorna un Token::Keyword
     {
-        Token::Keyword(word_upper)
+        Token::Keyword(word)
     } else if ["AND", "OR", "NOT"].contains(&word_upper.as_str()) {
         // si es un operador lógico se retorna un Token::LogicalOperator
         Token::LogicalOperator(word_upper)
@@ -81,20 +245,82 @@ fn tokenize_word(chars: &mut Peekable<Chars>) -> Token {
     }
 }
 
-fn tokenize_string(chars: &mut Peekable<Chars>) -> Token {
+/// Tokeniza un string entre comillas simples, donde una comilla doble '' representa una comilla.
+/// Si escapes es true, el string se escribió como E'...' y además acepta los escapes \n, \t, \r, \\ y \'.
+fn tokenize_string(chars: &mut Peekable<Chars>, escapes: bool) -> Result<Token, CustomError> {
     chars.next(); // salteo la comilla
     let mut string = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' if chars.peek() == Some(&'\'') => {
+                // una comilla doble dentro del string es una comilla escapada
+                string.push('\'');
+                chars.next();
+            }
+            '\'' => return Ok(Token::String(string)),
+            '\\' if escapes => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('r') => string.push('\r'),
+                Some(escaped @ ('\\' | '\'')) => string.push(escaped),
+                Some(other) => {
+                    return Err(CustomError::InvalidSyntax {
+                        message: format!("Invalid escape sequence: \\{}", other),
+                    })
+                }
+                None => break,
+            },
+            ch => string.push(ch),
+        }
+    }
+    Err(CustomError::InvalidSyntax {
+        message: "Unterminated string literal".to_string(),
+    })
+}
+
+/// Indica si los caracteres siguientes empiezan un string con escapes, escrito como E'...'.
+fn starts_escape_string(chars: &Peekable<Chars>) -> bool {
+    let mut lookahead = chars.clone();
+    matches!(lookahead.next(), Some('E') | Some('e')) && lookahead.next() == Some('\'')
+}
+
+fn tokenize_parameter(
+    chars: &mut Peekable<Chars>,
+    last_position: &mut usize,
+) -> Result<Token, CustomError> {
+    let prefix = chars.next();
+    let mut name = String::new();
     while let Some(&ch) = chars.peek() {
-        // se agrega al string hasta que se encuentre otra comilla
-        if ch != '\'' {
-            string.push(ch);
+        if ch.is_alphanumeric() || ch == '_' {
+            name.push(ch);
             chars.next();
         } else {
-            chars.next();
             break;
         }
     }
-    Token::String(string)
+    let parameter = match prefix {
+        Some('?') if name.is_empty() => Parameter::Positional(*last_position + 1),
+        Some('$') => match name.parse::<usize>() {
+            Ok(position) if position > 0 => Parameter::Positional(position),
+            _ => {
+                return Err(CustomError::InvalidSyntax {
+                    message: format!("Invalid parameter: ${}", name),
+                })
+            }
+        },
+        Some(':') if name.starts_with(|ch: char| ch.is_alphabetic() || ch == '_') => {
+            Parameter::Named(name)
+        }
+        _ => {
+            return Err(CustomError::InvalidSyntax {
+                message: format!("Invalid parameter near: {}", name),
+            })
+        }
+    };
+    if let Parameter::Positional(position) = parameter {
+        *last_position = (*last_position).max(position);
+    }
+    Ok(Token::Parameter(parameter))
 }
 
 fn tokenize_comparison_operator(chars: &mut Peekable<Chars>) -> Token {
@@ -123,22 +349,31 @@ fn tokenize_comparison_operator(chars: &mut Peekable<Chars>) -> Token {
 pub fn tokenize(input: &str) -> Result<Vec<Token>, CustomError> {
     let mut tokens = vec![];
     let mut chars = input.chars().peekable();
+    let mut last_position = 0; // la mayor posición de un parámetro posicional hasta ahora
 
     while let Some(&ch) = chars.peek() {
         if ch.is_whitespace() {
             // ignorar espacios y newlines fuera de comillas
             chars.next();
-        } else if ch.is_ascii_digit() {
-            tokens.push(tokenize_integer_or_identifier_starting_with_integer(
+        } else if starts_number(&chars, !ends_value(tokens.last())) {
+            tokens.push(tokenize_number_or_identifier_starting_with_integer(
                 &mut chars,
-            )); // números enteros o nombres que empiezan con un número
+            )?); // números o nombres que empiezan con un número
+        } else if starts_escape_string(&chars) {
+            chars.next(); // salteo la E
+            tokens.push(tokenize_string(&mut chars, true)?); // strings con escapes
+        } else if ch == '-' && starts_subtraction(&chars, tokens.last()) {
+            tokens.push(Token::Symbol(ch)); // resta
+            chars.next();
         } else if ch.is_alphabetic() || ch == '_' || ch == '-' {
             tokens.push(tokenize_word(&mut chars)); // palabras clave o nombres
         } else if ch == '\'' {
-            tokens.push(tokenize_string(&mut chars)); // strings
+            tokens.push(tokenize_string(&mut chars, false)?); // strings
+        } else if ['?', '$', ':'].contains(&ch) {
+            tokens.push(tokenize_parameter(&mut chars, &mut last_position)?); // parámetros
         } else if ['=', '>', '<'].contains(&ch) {
             tokens.push(tokenize_comparison_operator(&mut chars)); // operadores de comparacion
-        } else if [',', '(', ')', ';', '*'].contains(&ch) {
+        } else if [',', '(', ')', ';', '*', '+', '/', '%'].contains(&ch) {
             tokens.push(Token::Symbol(ch)); // símbolos especiales
             chars.next();
         } else {
@@ -189,4 +424,112 @@ mod tests {
         ];
         assert_eq!(tokenize(input).unwrap(), expected_output);
     }
+
+    #[test]
+    fn test_tokenize_escaped_strings() {
+        assert_eq!(
+            tokenize("'O''Brien' e'a\\tb\\n\\'c\\\\' ''").unwrap(),
+            vec![
+                Token::String("O'Brien".to_string()),
+                Token::String("a\tb\n'c\\".to_string()),
+                Token::String("".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize("SELECT * FROM a WHERE b = 'abc;").err().unwrap(),
+            CustomError::InvalidSyntax {
+                message: "Unterminated string literal".to_string()
+            }
+        );
+        assert!(tokenize("E'\\q'").is_err());
+        assert!(tokenize("'a\\'").is_ok());
+    }
+
+    #[test]
+    fn test_tokenize_numbers() {
+        assert_eq!(
+            tokenize("a < 2.50 AND b > -3 AND c = 1e3 AND d = -.5E-2 AND 1column = +7").unwrap(),
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::ComparisonOperator("<".to_string()),
+                Token::Float("2.50".to_string()),
+                Token::LogicalOperator("AND".to_string()),
+                Token::Identifier("b".to_string()),
+                Token::ComparisonOperator(">".to_string()),
+                Token::Integer("-3".to_string()),
+                Token::LogicalOperator("AND".to_string()),
+                Token::Identifier("c".to_string()),
+                Token::ComparisonOperator("=".to_string()),
+                Token::Float("1e3".to_string()),
+                Token::LogicalOperator("AND".to_string()),
+                Token::Identifier("d".to_string()),
+                Token::ComparisonOperator("=".to_string()),
+                Token::Float("-.5e-2".to_string()),
+                Token::LogicalOperator("AND".to_string()),
+                Token::Identifier("1column".to_string()),
+                Token::ComparisonOperator("=".to_string()),
+                Token::Integer("7".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize("(1, -2)").unwrap()[3],
+            Token::Integer("-2".to_string())
+        );
+        assert!(tokenize("1.5abc").is_err());
+    }
+
+    #[test]
+    fn test_tokenize_parameters() {
+        let input = "id = ? AND a = $3 AND b = ? AND c = :name_1";
+        let parameters: Vec<Token> = tokenize(input)
+            .unwrap()
+            .into_iter()
+            .filter(|token| matches!(token, Token::Parameter(_)))
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                Token::Parameter(Parameter::Positional(1)),
+                Token::Parameter(Parameter::Positional(3)),
+                Token::Parameter(Parameter::Positional(4)),
+                Token::Parameter(Parameter::Named("name_1".to_string())),
+            ]
+        );
+        assert!(tokenize("id = $0").is_err());
+        assert!(tokenize("id = :1").is_err());
+    }
+
+    #[test]
+    fn test_tokenize_qualified_columns() {
+        assert_eq!(
+            tokenize("p.name = d.id_2 ON").unwrap(),
+            vec![
+                Token::Identifier("p.name".to_string()),
+                Token::ComparisonOperator("=".to_string()),
+                Token::Identifier("d.id_2".to_string()),
+                Token::Keyword("ON".to_string()),
+            ]
+        );
+        assert!(tokenize("p.").is_err());
+    }
+
+    #[test]
+    fn test_tokenize_arithmetic_operators() {
+        assert_eq!(
+            tokenize("Price * 2 - Discount / 4 % 3 + first-name").unwrap(),
+            vec![
+                Token::Identifier("Price".to_string()),
+                Token::Symbol('*'),
+                Token::Integer("2".to_string()),
+                Token::Symbol('-'),
+                Token::Identifier("Discount".to_string()),
+                Token::Symbol('/'),
+                Token::Integer("4".to_string()),
+                Token::Symbol('%'),
+                Token::Integer("3".to_string()),
+                Token::Symbol('+'),
+                Token::Identifier("first-name".to_string()),
+            ]
+        );
+    }
 }
@@ -0,0 +1,747 @@
+use super::command_processer::open_table_path;
+use super::custom_error::CustomError;
+use super::row_parser::{parse_columns, parse_fields, read_record};
+use super::schema::ColumnDefinition;
+use super::storage::{
+    create_temp_file, remove_file, rename_file, replace_file, sync_writer, temp_directory,
+};
+use super::value::Value;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Lines, Read, Seek, SeekFrom, Write};
+use std::time::UNIX_EPOCH;
+
+/// Cantidad de claves sin ordenar que puede tener el índice antes de volver a ordenarlas junto con las demás.
+const MAX_PENDING_KEYS: usize = 1024;
+
+/// Memoria que pueden ocupar las claves al construir el índice. Si la tabla tiene más, se ordenan por partes
+/// en archivos temporales que luego se mezclan.
+pub const SORT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+fn index_error() -> CustomError {
+    CustomError::GenericError {
+        message: "Couldn't write index file".to_string(),
+    }
+}
+
+/// Retorna el path del índice de las columnas únicas de una tabla.
+pub fn index_path(directory: &str, table_name: &str) -> String {
+    format!("{}/{}.index", directory, table_name)
+}
+
+/// Elimina el índice de una tabla, si existe. Debe llamarse al eliminar la tabla, o cuando cambian sus columnas únicas
+/// y el índice deja de servir.
+pub fn remove_index(directory: &str, table_name: &str) -> Result<(), CustomError> {
+    let path = index_path(directory, table_name);
+    if fs::metadata(&path).is_ok() {
+        remove_file(&path)?;
+    }
+    Ok(())
+}
+
+/// Mueve el índice de una tabla junto con la tabla, si existe. El índice sigue correspondiendo a la tabla,
+/// ya que moverla no cambia su contenido ni su fecha de modificación.
+pub fn move_index(
+    from_directory: &str,
+    from_table: &str,
+    to_directory: &str,
+    to_table: &str,
+) -> Result<(), CustomError> {
+    let path = index_path(from_directory, from_table);
+    if fs::metadata(&path).is_ok() {
+        rename_file(&path, &index_path(to_directory, to_table))?;
+    }
+    Ok(())
+}
+
+/// Escapa los caracteres que separan las partes de una clave, para que cada clave ocupe una sola línea.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+/// Retorna el texto con que se indexa el valor de una columna única, o None si es NULL, que no se indexa.
+/// El valor se interpreta según el tipo de la columna, para que dos textos del mismo valor tengan la misma clave,
+/// como 2.50 y 2.5 en una columna REAL o 007 y 7 en una columna INTEGER.
+pub fn unique_value(column: &ColumnDefinition, value: &Value) -> Option<String> {
+    match column.value_from_csv(&value.to_string()) {
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Retorna la línea del índice que representa al valor de una columna.
+fn index_key(column: &str, value: &str) -> String {
+    format!("{}\t{}", escape(column), escape(value))
+}
+
+/// Identifica el contenido de una tabla por su tamaño y su fecha de modificación.
+fn table_stamp(directory: &str, table_name: &str) -> Result<(u64, u64), CustomError> {
+    let table_path = format!("{}/{}.csv", directory, table_name);
+    let metadata =
+        open_table_path(&table_path)?
+            .metadata()
+            .map_err(|_| CustomError::GenericError {
+                message: "Couldn't read table file".to_string(),
+            })?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    Ok((metadata.len(), modified))
+}
+
+/// Los valores de las columnas únicas que cambian al reescribir una tabla, como pares de columna y valor.
+/// Si cambian más de MAX_PENDING_KEYS valores dejan de guardarse, y el índice se reconstruye a partir de la tabla nueva,
+/// que cuesta lo mismo que mezclarlos con las claves ordenadas.
+#[derive(Debug, Default)]
+pub struct IndexChanges {
+    removed: Vec<(String, Value)>,
+    added: Vec<(String, Value)>,
+    overflowed: bool,
+}
+
+impl IndexChanges {
+    /// Registra que una fila de la tabla deja de tener el valor de una columna.
+    pub fn remove(&mut self, column: &str, value: &Value) {
+        if self.has_room() {
+            self.removed.push((column.to_string(), value.clone()));
+        }
+    }
+
+    /// Registra que una fila de la tabla pasa a tener el valor de una columna.
+    pub fn add(&mut self, column: &str, value: &Value) {
+        if self.has_room() {
+            self.added.push((column.to_string(), value.clone()));
+        }
+    }
+
+    fn has_room(&mut self) -> bool {
+        if self.removed.len() + self.added.len() >= MAX_PENDING_KEYS {
+            self.overflowed = true;
+            self.removed.clear();
+            self.added.clear();
+        }
+        !self.overflowed
+    }
+}
+
+/// El índice de las columnas únicas de una tabla, que se guarda junto a ella en <tabla>.index para verificar
+/// que un valor no se repita sin recorrer la tabla.
+///
+/// El archivo empieza con el tamaño y la fecha de modificación de la tabla a la que corresponde, el fin de sus claves
+/// ordenadas y los nombres de las columnas indexadas. Le siguen las claves ordenadas, una por línea con la columna
+/// y el valor, donde se busca con búsqueda binaria, y al final los cambios de las últimas escrituras sin ordenar,
+/// con un + delante de cada clave agregada y un - delante de cada clave eliminada, que se leen a memoria al abrir
+/// el índice. Los valores NULL no se indexan.
+///
+/// Si la tabla cambió sin que se actualice el índice, por ejemplo por un corte de luz entre la escritura de las
+/// filas y la del índice, el índice se reconstruye a partir de la tabla al abrirlo.
+pub struct UniqueIndex {
+    directory: String,
+    table_name: String,
+    columns: Vec<ColumnDefinition>,
+    reader: BufReader<File>,
+    /// Las posiciones del archivo donde empiezan y terminan las claves ordenadas.
+    sorted_start: u64,
+    sorted_end: u64,
+    /// Las claves agregadas que no están entre las ordenadas, y las ordenadas que se eliminaron.
+    added: HashSet<String>,
+    removed: HashSet<String>,
+}
+
+impl UniqueIndex {
+    /// Abre el índice de las columnas recibidas de una tabla, construyéndolo si no existe o no corresponde a la tabla.
+    pub fn open(
+        directory: &str,
+        table_name: &str,
+        columns: &[ColumnDefinition],
+    ) -> Result<UniqueIndex, CustomError> {
+        if let Some(index) = UniqueIndex::open_existing(directory, table_name, columns)? {
+            return Ok(index);
+        }
+        UniqueIndex::build(directory, table_name, columns, SORT_MEMORY_LIMIT)?;
+        match UniqueIndex::open_existing(directory, table_name, columns)? {
+            Some(index) => Ok(index),
+            None => Err(index_error()),
+        }
+    }
+
+    /// Abre el índice de una tabla solo si ya existe. Retorna None si no existe, si no corresponde al contenido actual
+    /// de la tabla o si indexa otras columnas.
+    pub fn open_existing(
+        directory: &str,
+        table_name: &str,
+        columns: &[ColumnDefinition],
+    ) -> Result<Option<UniqueIndex>, CustomError> {
+        let file = match File::open(index_path(directory, table_name)) {
+            Ok(file) => file,
+            Err(_) => return Ok(None),
+        };
+        let mut reader = BufReader::new(file);
+        let mut stamp = String::new();
+        let mut indexed_columns = String::new();
+        if reader.read_line(&mut stamp).is_err() || reader.read_line(&mut indexed_columns).is_err()
+        {
+            return Ok(None);
+        }
+        let numbers: Vec<u64> = stamp
+            .split_whitespace()
+            .filter_map(|number| number.parse().ok())
+            .collect();
+        let (size, modified) = table_stamp(directory, table_name)?;
+        if numbers.len() != 3
+            || numbers[..2] != [size, modified]
+            || indexed_columns.trim_end_matches('\n') != columns_line(columns)
+        {
+            return Ok(None);
+        }
+        let mut index = UniqueIndex {
+            directory: directory.to_string(),
+            table_name: table_name.to_string(),
+            columns: columns.to_vec(),
+            reader,
+            sorted_start: (stamp.len() + indexed_columns.len()) as u64,
+            sorted_end: numbers[2],
+            added: HashSet::new(),
+            removed: HashSet::new(),
+        };
+        if index
+            .reader
+            .seek(SeekFrom::Start(index.sorted_end))
+            .is_err()
+        {
+            return Ok(None);
+        }
+        let mut changes = vec![];
+        for line in (&mut index.reader).lines() {
+            match line {
+                Ok(line) => changes.push(line),
+                Err(_) => return Ok(None),
+            };
+        }
+        for change in changes {
+            match (change.strip_prefix('+'), change.strip_prefix('-')) {
+                (Some(key), _) => index.add_key(key.to_string()),
+                (_, Some(key)) => index.remove_key(key.to_string()),
+                _ => return Ok(None),
+            }
+        }
+        Ok(Some(index))
+    }
+
+    fn add_key(&mut self, key: String) {
+        if !self.removed.remove(&key) {
+            self.added.insert(key);
+        }
+    }
+
+    fn remove_key(&mut self, key: String) {
+        if !self.added.remove(&key) {
+            self.removed.insert(key);
+        }
+    }
+
+    /// Construye el índice recorriendo la tabla una vez. Las claves se ordenan en memoria de a memory_limit bytes,
+    /// y si no entran todas, cada parte ordenada se guarda en un archivo temporal y luego se mezclan.
+    fn build(
+        directory: &str,
+        table_name: &str,
+        columns: &[ColumnDefinition],
+        memory_limit: usize,
+    ) -> Result<(), CustomError> {
+        let table_path = format!("{}/{}.csv", directory, table_name);
+        let mut table_reader = BufReader::new(open_table_path(&table_path)?);
+        let table_columns = match read_record(&mut table_reader)? {
+            Some(header) => parse_columns(&header)?,
+            None => return CustomError::error_invalid_table("Couldn't read table file"),
+        };
+        let positions: Vec<(&ColumnDefinition, usize)> = columns
+            .iter()
+            .filter_map(|column| {
+                let position = table_columns.iter().position(|name| name == &column.name)?;
+                Some((column, position))
+            })
+            .collect();
+        let mut runs = SortedRuns::default();
+        let mut keys = vec![];
+        let mut memory_used = 0;
+        while let Some(line) = read_record(&mut table_reader)? {
+            let fields = parse_fields(&line)?;
+            for (column, position) in &positions {
+                let value = match fields.get(*position) {
+                    Some(raw) => column.value_from_csv(raw),
+                    None => continue,
+                };
+                if let Some(value) = unique_value(column, &value) {
+                    let key = index_key(&column.name, &value);
+                    memory_used += key.len();
+                    keys.push(key);
+                }
+            }
+            if memory_used > memory_limit {
+                runs.write_run(std::mem::take(&mut keys))?;
+                memory_used = 0;
+            }
+        }
+        if runs.is_empty() {
+            keys.sort();
+            return write_index(directory, table_name, columns, keys.into_iter().map(Ok));
+        }
+        runs.write_run(keys)?;
+        write_index(directory, table_name, columns, runs.merge()?)
+    }
+
+    /// Retorna la clave con que se indexa el valor de una columna, o None si es NULL o la columna no se indexa.
+    fn key(&self, column: &str, value: &Value) -> Option<String> {
+        let definition = self.columns.iter().find(|other| other.name == column)?;
+        Some(index_key(column, &unique_value(definition, value)?))
+    }
+
+    /// Indica si el índice tiene el valor de una columna.
+    pub fn contains(&mut self, column: &str, value: &Value) -> Result<bool, CustomError> {
+        let key = match self.key(column, value) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+        if self.added.contains(&key) {
+            return Ok(true);
+        }
+        if self.removed.contains(&key) {
+            return Ok(false);
+        }
+        let (mut low, mut high) = (self.sorted_start, self.sorted_end);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let (start, line) = self.line_from(middle)?;
+            if start >= high {
+                high = middle;
+                continue;
+            }
+            match line.as_str().cmp(key.as_str()) {
+                Ordering::Equal => return Ok(true),
+                Ordering::Less => low = start + line.len() as u64 + 1,
+                Ordering::Greater => high = middle,
+            }
+        }
+        Ok(false)
+    }
+
+    /// Retorna la primera línea que empieza en offset o después, junto con su posición en el archivo.
+    fn line_from(&mut self, offset: u64) -> Result<(u64, String), CustomError> {
+        let read_error = || CustomError::GenericError {
+            message: "Couldn't read index file".to_string(),
+        };
+        let mut start = offset;
+        if offset > self.sorted_start {
+            // se descarta el resto de la línea que contiene a offset, salvo que offset sea su comienzo
+            let mut skipped = vec![];
+            self.reader
+                .seek(SeekFrom::Start(offset - 1))
+                .and_then(|_| self.reader.read_until(b'\n', &mut skipped))
+                .map_err(|_| read_error())?;
+            start = offset - 1 + skipped.len() as u64;
+        } else {
+            self.reader
+                .seek(SeekFrom::Start(offset))
+                .map_err(|_| read_error())?;
+        }
+        let mut line = String::new();
+        self.reader.read_line(&mut line).map_err(|_| read_error())?;
+        Ok((start, line.trim_end_matches('\n').to_string()))
+    }
+
+    /// Agrega al índice los valores de las filas que se acaban de agregar a la tabla, como pares de columna y valor.
+    pub fn insert(self, values: &[(String, Value)]) -> Result<(), CustomError> {
+        self.update(&[], values)
+    }
+
+    /// Aplica al índice los cambios de una tabla que se acaba de reescribir. Si cambiaron demasiados valores,
+    /// el índice se reconstruye a partir de la tabla.
+    pub fn apply(self, changes: IndexChanges) -> Result<(), CustomError> {
+        if changes.overflowed {
+            return UniqueIndex::build(
+                &self.directory,
+                &self.table_name,
+                &self.columns,
+                SORT_MEMORY_LIMIT,
+            );
+        }
+        self.update(&changes.removed, &changes.added)
+    }
+
+    /// Quita y agrega claves al índice, y lo marca como correspondiente al contenido actual de la tabla.
+    /// Los cambios se agregan al final sin ordenar, salvo que superen MAX_PENDING_KEYS y se reescriba el índice ordenado.
+    fn update(
+        mut self,
+        removed: &[(String, Value)],
+        added: &[(String, Value)],
+    ) -> Result<(), CustomError> {
+        let mut changes = vec![];
+        for (column, value) in removed {
+            if let Some(key) = self.key(column, value) {
+                changes.push(format!("-{}", key));
+                self.remove_key(key);
+            }
+        }
+        for (column, value) in added {
+            if let Some(key) = self.key(column, value) {
+                changes.push(format!("+{}", key));
+                self.add_key(key);
+            }
+        }
+        if self.added.len() + self.removed.len() > MAX_PENDING_KEYS {
+            return self.merge();
+        }
+        let path = index_path(&self.directory, &self.table_name);
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(|_| index_error())?;
+        let mut writer = BufWriter::new(file);
+        for change in changes {
+            writeln!(writer, "{}", change).map_err(|_| index_error())?;
+        }
+        sync_writer(writer)?;
+        // la marca se actualiza al final, por lo que si algo falla antes el índice se reconstruye al abrirlo
+        let (size, modified) = table_stamp(&self.directory, &self.table_name)?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|_| index_error())?;
+        write!(
+            file,
+            "{:020} {:020} {:020}",
+            size, modified, self.sorted_end
+        )
+        .map_err(|_| index_error())?;
+        file.sync_all().map_err(|_| index_error())
+    }
+
+    /// Reescribe el índice mezclando sus claves ordenadas, sin las eliminadas, con las agregadas.
+    fn merge(mut self) -> Result<(), CustomError> {
+        let mut added: Vec<String> = self.added.drain().collect();
+        added.sort();
+        let removed = std::mem::take(&mut self.removed);
+        self.reader
+            .seek(SeekFrom::Start(self.sorted_start))
+            .map_err(|_| index_error())?;
+        let sorted = (&mut self.reader)
+            .take(self.sorted_end - self.sorted_start)
+            .lines()
+            .map(|line| line.map_err(|_| index_error()))
+            .filter(|line| !matches!(line, Ok(line) if removed.contains(line)));
+        let mut sorted = sorted.peekable();
+        let mut added = added.into_iter().peekable();
+        let merged = std::iter::from_fn(move || {
+            let take_sorted = match (sorted.peek(), added.peek()) {
+                (Some(Ok(line)), Some(key)) => line <= key,
+                (Some(_), _) => true,
+                (None, _) => false,
+            };
+            if take_sorted {
+                sorted.next()
+            } else {
+                added.next().map(Ok)
+            }
+        });
+        write_index(&self.directory, &self.table_name, &self.columns, merged)
+    }
+}
+
+/// Retorna la línea del índice con los nombres de las columnas indexadas.
+fn columns_line(columns: &[ColumnDefinition]) -> String {
+    let names: Vec<String> = columns.iter().map(|column| escape(&column.name)).collect();
+    names.join("\t")
+}
+
+/// Escribe el índice completo con las claves recibidas, que deben estar ordenadas, marcado como correspondiente
+/// al contenido actual de la tabla. El fin de las claves se escribe en la marca una vez que se escribieron todas.
+fn write_index<I>(
+    directory: &str,
+    table_name: &str,
+    columns: &[ColumnDefinition],
+    keys: I,
+) -> Result<(), CustomError>
+where
+    I: Iterator<Item = Result<String, CustomError>>,
+{
+    let (size, modified) = table_stamp(directory, table_name)?;
+    replace_file(directory, &format!("{}.index", table_name), |writer| {
+        writeln!(writer, "{:020} {:020} {:020}", size, modified, 0)
+            .and_then(|_| writeln!(writer, "{}", columns_line(columns)))
+            .map_err(|_| index_error())?;
+        for key in keys {
+            writeln!(writer, "{}", key?).map_err(|_| index_error())?;
+        }
+        let sorted_end = writer.stream_position().map_err(|_| index_error())?;
+        writer
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| write!(writer, "{:020} {:020} {:020}", size, modified, sorted_end))
+            .map_err(|_| index_error())
+    })
+}
+
+/// Las partes ordenadas de las claves de un índice que se guardaron en archivos temporales mientras se construye.
+/// Los archivos se eliminan al descartarlas.
+#[derive(Default)]
+struct SortedRuns {
+    paths: Vec<String>,
+}
+
+impl SortedRuns {
+    fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Ordena las claves y las guarda en un archivo temporal nuevo.
+    fn write_run(&mut self, mut keys: Vec<String>) -> Result<(), CustomError> {
+        keys.sort();
+        let (path, file) = create_temp_file(&temp_directory(), "index")?;
+        self.paths.push(path);
+        let mut writer = BufWriter::new(file);
+        for key in keys {
+            writeln!(writer, "{}", key).map_err(|_| index_error())?;
+        }
+        writer.flush().map_err(|_| index_error())
+    }
+
+    /// Retorna un iterador que mezcla las claves de todos los archivos en orden, leyendo una línea de cada uno a la vez.
+    fn merge(self) -> Result<MergedRuns, CustomError> {
+        let mut merged = MergedRuns {
+            readers: vec![],
+            heap: BinaryHeap::new(),
+            _runs: self,
+        };
+        for path in &merged._runs.paths {
+            let file = File::open(path).map_err(|_| index_error())?;
+            merged.readers.push(BufReader::new(file).lines());
+        }
+        for run in 0..merged.readers.len() {
+            merged.push_next(run)?;
+        }
+        Ok(merged)
+    }
+}
+
+impl Drop for SortedRuns {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path); // un temporal que no se pudo borrar no afecta al índice
+        }
+    }
+}
+
+/// Las claves de varias partes ordenadas, mezcladas en orden con un heap que tiene la siguiente clave de cada parte.
+struct MergedRuns {
+    readers: Vec<Lines<BufReader<File>>>,
+    heap: BinaryHeap<Reverse<(String, usize)>>,
+    /// Las partes se conservan hasta terminar de mezclarlas, para eliminar sus archivos al final.
+    _runs: SortedRuns,
+}
+
+impl MergedRuns {
+    fn push_next(&mut self, run: usize) -> Result<(), CustomError> {
+        if let Some(line) = self.readers[run].next() {
+            let key = line.map_err(|_| index_error())?;
+            self.heap.push(Reverse((key, run)));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for MergedRuns {
+    type Item = Result<String, CustomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((key, run)) = self.heap.pop()?;
+        match self.push_next(run) {
+            Ok(()) => Some(Ok(key)),
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnType;
+
+    fn create_table(directory: &str, contents: &str) {
+        fs::create_dir_all(directory).unwrap();
+        fs::write(format!("{}/items.csv", directory), contents).unwrap();
+    }
+
+    fn column(name: &str, column_type: ColumnType) -> ColumnDefinition {
+        let mut column = ColumnDefinition::new(name);
+        column.column_type = Some(column_type);
+        column.unique = true;
+        column
+    }
+
+    fn columns() -> Vec<ColumnDefinition> {
+        vec![
+            column("id", ColumnType::Integer),
+            column("code", ColumnType::Text),
+        ]
+    }
+
+    fn text(text: &str) -> Value {
+        Value::Text(text.to_string())
+    }
+
+    fn id(id: usize) -> (String, Value) {
+        ("id".to_string(), Value::Integer(id as i64))
+    }
+
+    #[test]
+    fn test_index_lookups() {
+        let directory = "test_index_lookups";
+        create_table(
+            directory,
+            "id,code,name\n3,c,x\n1,\"a\nb\",y\n2,,z\n10,a\\b\tc,w\n",
+        );
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        for id in [1, 2, 3, 10] {
+            assert!(index.contains("id", &Value::Integer(id)).unwrap());
+        }
+        assert!(index.contains("code", &text("a\nb")).unwrap());
+        assert!(index.contains("code", &text("a\\b\tc")).unwrap());
+        assert!(index.contains("code", &text("c")).unwrap());
+        assert!(!index.contains("code", &Value::Null).unwrap());
+        assert!(!index.contains("code", &text("a")).unwrap());
+        assert!(!index.contains("id", &Value::Integer(4)).unwrap());
+        assert!(!index.contains("name", &text("x")).unwrap());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_index_keys_follow_column_types() {
+        let directory = "test_index_keys_follow_column_types";
+        create_table(directory, "id,code,price\n007,007,2.50\n");
+        let mut columns = columns();
+        columns.push(column("price", ColumnType::Real));
+        let mut index = UniqueIndex::open(directory, "items", &columns).unwrap();
+        assert!(index.contains("id", &Value::Integer(7)).unwrap());
+        assert!(index.contains("id", &text("7")).unwrap());
+        assert!(index.contains("price", &Value::Float(2.5)).unwrap());
+        assert!(index.contains("price", &text("2.500")).unwrap());
+        // en una columna TEXT los textos se comparan tal cual
+        assert!(index.contains("code", &text("007")).unwrap());
+        assert!(!index.contains("code", &text("7")).unwrap());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_index_insert_and_merge() {
+        let directory = "test_index_insert_and_merge";
+        create_table(directory, "id,code\n1,a\n");
+        let index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        let mut table = OpenOptions::new()
+            .append(true)
+            .open(format!("{}/items.csv", directory))
+            .unwrap();
+        writeln!(table, "2,b").unwrap();
+        index.insert(&[id(2)]).unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        assert_eq!(index.added.len(), 1);
+        assert!(index.contains("id", &Value::Integer(2)).unwrap());
+        assert!(index.contains("code", &text("a")).unwrap());
+        // como la fila no se agregó al índice, su código no aparece hasta que se reconstruya
+        assert!(!index.contains("code", &text("b")).unwrap());
+        // al superar MAX_PENDING_KEYS las claves se vuelven a ordenar
+        let values: Vec<(String, Value)> = (3..MAX_PENDING_KEYS + 3).map(id).collect();
+        index.insert(&values).unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        assert!(index.added.is_empty());
+        for id in [1, 2, 3, 500, MAX_PENDING_KEYS as i64 + 2] {
+            assert!(index.contains("id", &Value::Integer(id)).unwrap());
+        }
+        assert!(!index.contains("id", &Value::Integer(0)).unwrap());
+        assert!(index.contains("code", &text("a")).unwrap());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_index_applies_table_changes() {
+        let directory = "test_index_applies_table_changes";
+        create_table(directory, "id,code\n1,a\n2,b\n3,c\n");
+        let index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        // la fila 1 se elimina y la 2 cambia de código
+        create_table(directory, "id,code\n2,z\n3,c\n");
+        let mut changes = IndexChanges::default();
+        changes.remove("id", &Value::Integer(1));
+        changes.remove("code", &text("a"));
+        changes.remove("code", &text("b"));
+        changes.add("code", &text("z"));
+        index.apply(changes).unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        assert!(!index.contains("id", &Value::Integer(1)).unwrap());
+        assert!(!index.contains("code", &text("b")).unwrap());
+        assert!(index.contains("code", &text("z")).unwrap());
+        assert!(index.contains("id", &Value::Integer(2)).unwrap());
+        // una clave eliminada puede volver a agregarse
+        fs::write(format!("{}/items.csv", directory), "id,code\n2,b\n3,c\n").unwrap();
+        let mut changes = IndexChanges::default();
+        changes.remove("code", &text("z"));
+        changes.add("code", &text("b"));
+        index.apply(changes).unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        assert!(index.contains("code", &text("b")).unwrap());
+        assert!(!index.contains("code", &text("z")).unwrap());
+        // con demasiados cambios el índice se reconstruye a partir de la tabla
+        fs::write(format!("{}/items.csv", directory), "id,code\n").unwrap();
+        let mut changes = IndexChanges::default();
+        for id in 0..MAX_PENDING_KEYS + 1 {
+            changes.remove("id", &Value::Integer(id as i64));
+        }
+        index.apply(changes).unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        assert_eq!(index.sorted_start, index.sorted_end);
+        assert!(!index.contains("code", &text("c")).unwrap());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_index_built_from_sorted_runs() {
+        let directory = "test_index_built_from_sorted_runs";
+        let mut contents = "id,code\n".to_string();
+        for id in (1..=200).rev() {
+            contents.push_str(&format!("{},code{}\n", id, id));
+        }
+        create_table(directory, &contents);
+        // con poca memoria las claves se ordenan en varios archivos temporales que luego se mezclan
+        UniqueIndex::build(directory, "items", &columns(), 64).unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        for id in 1..=200 {
+            assert!(index.contains("id", &Value::Integer(id)).unwrap());
+            assert!(index
+                .contains("code", &text(&format!("code{}", id)))
+                .unwrap());
+        }
+        assert!(!index.contains("id", &Value::Integer(201)).unwrap());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_index_rebuilt_when_table_changes() {
+        let directory = "test_index_rebuilt_when_table_changes";
+        create_table(directory, "id,code\n1,a\n");
+        UniqueIndex::open(directory, "items", &columns()).unwrap();
+        fs::write(format!("{}/items.csv", directory), "id,code\n1,a\n2,bb\n").unwrap();
+        let mut index = UniqueIndex::open(directory, "items", &columns()).unwrap();
+        assert!(index.contains("code", &text("bb")).unwrap());
+        // también se reconstruye si indexa otras columnas
+        let mut index_by_code = UniqueIndex::open(directory, "items", &columns()[1..]).unwrap();
+        assert!(!index_by_code.contains("id", &Value::Integer(2)).unwrap());
+        assert!(index_by_code.contains("code", &text("bb")).unwrap());
+        remove_index(directory, "items").unwrap();
+        assert!(fs::metadata(index_path(directory, "items")).is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
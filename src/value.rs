@@ -0,0 +1,372 @@
+use super::custom_error::CustomError;
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Un Value es un valor tipado, ya sea leído de una tabla o escrito como literal en un comando.
+///
+/// Las reglas de comparación entre valores son:
+/// - Integer y Float se comparan numéricamente entre sí.
+/// - Text se compara con Text según compare_text, y Boolean con Boolean siendo false < true.
+/// - Date se compara con Date cronológicamente. Un Text que es una fecha válida se convierte a Date antes de compararse
+///   con una Date, por ejemplo '01/02/1990' es posterior a la fecha 1990-01-31.
+/// - Un Text que puede interpretarse como otro tipo se convierte a ese tipo antes de compararse con un valor que no es Text,
+///   por ejemplo '28' = 28 y '' = NULL.
+/// - En cualquier otro caso se ordena por tipo: Null < Boolean < Integer y Float < Date < Text.
+pub enum Value {
+    Null,
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Boolean(bool),
+    /// Una fecha de una columna DATE, siempre con formato YYYY-MM-DD para que el orden de los textos sea el cronológico.
+    Date(String),
+}
+
+fn looks_like_number(raw: &str) -> bool {
+    // f64::from_str acepta "inf" o "NaN", que no se consideran números en las tablas
+    raw.chars().any(|ch| ch.is_ascii_digit())
+        && raw
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || ['.', '-', '+', 'e', 'E'].contains(&ch))
+}
+
+/// Interpreta una fecha con formato YYYY-MM-DD o DD/MM/YYYY, y la retorna con formato YYYY-MM-DD.
+/// Si el texto no es una fecha válida, retorna None.
+pub fn parse_date(raw: &str) -> Option<String> {
+    let parts: Vec<&str> = raw.split(['-', '/']).collect();
+    if parts.len() != 3 || parts.iter().any(|part| part.parse::<u32>().is_err()) {
+        return None;
+    }
+    let numbers: Vec<u32> = parts.iter().filter_map(|part| part.parse().ok()).collect();
+    let (year, month, day) = if raw.contains('-') && parts[0].len() == 4 {
+        (numbers[0], numbers[1], numbers[2]) // YYYY-MM-DD
+    } else if raw.contains('/') && parts[2].len() == 4 {
+        (numbers[2], numbers[1], numbers[0]) // DD/MM/YYYY
+    } else {
+        return None;
+    };
+    let leap_year = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap_year => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// Colación usada para comparar textos: primero sin distinguir mayúsculas de minúsculas,
+/// y en caso de empate por orden de bytes, de modo que solo dos textos idénticos son iguales.
+pub fn compare_text(left: &str, right: &str) -> Ordering {
+    left.to_lowercase()
+        .cmp(&right.to_lowercase())
+        .then_with(|| left.cmp(right))
+}
+
+impl Value {
+    /// Interpreta el texto de una celda CSV como un Value, infiriendo su tipo.
+    /// Una celda vacía es Null, y "true" o "false" sin importar mayúsculas son Boolean.
+    pub fn from_csv(raw: &str) -> Value {
+        if raw.is_empty() {
+            Value::Null
+        } else if let Ok(integer) = raw.parse::<i64>() {
+            Value::Integer(integer)
+        } else if let (true, Ok(float)) = (looks_like_number(raw), raw.parse::<f64>()) {
+            Value::Float(float)
+        } else if raw.eq_ignore_ascii_case("true") {
+            Value::Boolean(true)
+        } else if raw.eq_ignore_ascii_case("false") {
+            Value::Boolean(false)
+        } else {
+            Value::Text(raw.to_string())
+        }
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean(_) => 1,
+            Value::Integer(_) | Value::Float(_) => 2,
+            Value::Date(_) => 3,
+            Value::Text(_) => 4,
+        }
+    }
+
+    /// Retorna el valor como f64 si es numérico.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(integer) => Some(*integer as f64),
+            Value::Float(float) => Some(*float),
+            _ => None,
+        }
+    }
+
+    /// Retorna el valor como Date si es un Text con una fecha válida, o el mismo valor si no.
+    fn to_date(&self) -> Value {
+        match self {
+            Value::Text(text) => parse_date(text).map_or_else(|| self.clone(), Value::Date),
+            other => other.clone(),
+        }
+    }
+
+    /// Retorna el valor numérico que representa, convirtiendo los Text numéricos como en una celda CSV.
+    fn to_number(&self, operator: char) -> Result<Value, CustomError> {
+        let number = match self {
+            Value::Text(text) => Value::from_csv(text),
+            other => other.clone(),
+        };
+        match number {
+            Value::Integer(_) | Value::Float(_) => Ok(number),
+            _ => Err(CustomError::InvalidColumn {
+                message: format!("Can't apply {} to non-numeric value '{}'", operator, self),
+            }),
+        }
+    }
+
+    /// Aplica un operador aritmético (+, -, *, / o %) entre dos valores.
+    /// Si alguno es Null el resultado es Null. Entre dos Integer el resultado es Integer, con la división truncada,
+    /// salvo que el resultado no entre en un i64, en cuyo caso se calcula como Float.
+    pub fn arithmetic(&self, operator: char, other: &Value) -> Result<Value, CustomError> {
+        if *self == Value::Null || *other == Value::Null {
+            return Ok(Value::Null);
+        }
+        let (left, right) = (self.to_number(operator)?, other.to_number(operator)?);
+        if ['/', '%'].contains(&operator) && right.as_f64() == Some(0.0) {
+            CustomError::error_generic("Division by zero")?;
+        }
+        if let (Value::Integer(left), Value::Integer(right)) = (&left, &right) {
+            let result = match operator {
+                '+' => left.checked_add(*right),
+                '-' => left.checked_sub(*right),
+                '*' => left.checked_mul(*right),
+                '/' => left.checked_div(*right),
+                '%' => left.checked_rem(*right),
+                _ => None,
+            };
+            if let Some(result) = result {
+                return Ok(Value::Integer(result));
+            }
+        }
+        let (left, right) = (left.as_f64().unwrap_or(0.0), right.as_f64().unwrap_or(0.0));
+        match operator {
+            '+' => Ok(Value::Float(left + right)),
+            '-' => Ok(Value::Float(left - right)),
+            '*' => Ok(Value::Float(left * right)),
+            '/' => Ok(Value::Float(left / right)),
+            '%' => Ok(Value::Float(left % right)),
+            _ => Err(CustomError::GenericError {
+                message: format!("Invalid operator: {}", operator),
+            }),
+        }
+    }
+
+    /// Retorna el valor escrito como literal SQL, tal como puede aparecer en un comando.
+    /// Las comillas de un Text se duplican, y si tiene saltos de línea o tabulaciones se escribe como E'...'.
+    pub fn to_sql_literal(&self) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Text(text) if text.contains(['\n', '\r', '\t']) => {
+                let escaped = text
+                    .replace('\\', "\\\\")
+                    .replace('\'', "\\'")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r")
+                    .replace('\t', "\\t");
+                format!("E'{}'", escaped)
+            }
+            Value::Text(text) => format!("'{}'", text.replace('\'', "''")),
+            Value::Date(date) => format!("'{}'", date),
+            Value::Boolean(boolean) => boolean.to_string().to_uppercase(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Compara dos valores siguiendo las reglas de coerción descriptas en Value.
+    /// Es un orden total, por lo que puede usarse tanto para evaluar condiciones como para ordenar.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Integer(left), Value::Integer(right)) => left.cmp(right),
+            (Value::Boolean(left), Value::Boolean(right)) => left.cmp(right),
+            (Value::Text(left), Value::Text(right)) => compare_text(left, right),
+            (Value::Date(left), Value::Date(right)) => left.cmp(right),
+            (Value::Text(text), Value::Date(_)) | (Value::Date(_), Value::Text(text))
+                if parse_date(text).is_some() =>
+            {
+                self.to_date().compare(&other.to_date())
+            }
+            (Value::Text(text), _) if !matches!(Value::from_csv(text), Value::Text(_)) => {
+                Value::from_csv(text).compare(other)
+            }
+            (_, Value::Text(text)) if !matches!(Value::from_csv(text), Value::Text(_)) => {
+                self.compare(&Value::from_csv(text))
+            }
+            _ => match (self.as_f64(), other.as_f64()) {
+                (Some(left), Some(right)) => left.partial_cmp(&right).unwrap_or(Ordering::Equal),
+                _ => self.type_rank().cmp(&other.type_rank()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Muestra el valor tal como se escribe en una celda CSV. Los Float se escriben con todos los dígitos necesarios
+    /// para volver a leer el mismo número, y los Float enteros conservan el ".0", como 3.0.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, ""),
+            Value::Integer(integer) => write!(f, "{}", integer),
+            Value::Float(float) => write!(f, "{:?}", float),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Date(date) => write!(f, "{}", date),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_sql_literal_escapes_text() {
+        assert_eq!(
+            Value::Text("O'Brien".to_string()).to_sql_literal(),
+            "'O''Brien'"
+        );
+        assert_eq!(
+            Value::Text("a\\b\n'c'".to_string()).to_sql_literal(),
+            "E'a\\\\b\\n\\'c\\''"
+        );
+    }
+
+    #[test]
+    fn test_from_csv() {
+        assert_eq!(Value::from_csv(""), Value::Null);
+        assert_eq!(Value::from_csv("28"), Value::Integer(28));
+        assert_eq!(
+            Value::from_csv("9780061120084"),
+            Value::Integer(9780061120084)
+        );
+        assert_eq!(Value::from_csv("1.25"), Value::Float(1.25));
+        assert_eq!(Value::from_csv("TRUE"), Value::Boolean(true));
+        assert_eq!(Value::from_csv("inf"), Value::Text("inf".to_string()));
+        assert_eq!(Value::from_csv("P001"), Value::Text("P001".to_string()));
+    }
+
+    #[test]
+    fn test_compare_numbers() {
+        assert_eq!(
+            Value::Float(2.5).compare(&Value::Integer(2)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Integer(100).compare(&Value::Integer(28)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Text("28".to_string()).compare(&Value::Integer(28)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_compare_mixed_types() {
+        assert_eq!(
+            Value::Text("".to_string()).compare(&Value::Null),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Text("abc".to_string()).compare(&Value::Integer(1)),
+            Ordering::Greater
+        );
+        assert_eq!(Value::Null.compare(&Value::Boolean(false)), Ordering::Less);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2023-09-02"), Some("2023-09-02".to_string()));
+        assert_eq!(parse_date("2/9/2023"), Some("2023-09-02".to_string()));
+        assert_eq!(parse_date("29/02/2024"), Some("2024-02-29".to_string()));
+        assert_eq!(parse_date("29/02/2023"), None);
+        assert_eq!(parse_date("02-09-2023"), None);
+    }
+
+    #[test]
+    fn test_compare_dates() {
+        let date = |text: &str| Value::Date(text.to_string());
+        assert_eq!(
+            date("1995-03-04").compare(&date("2001-01-02")),
+            Ordering::Less
+        );
+        assert_eq!(
+            date("1995-03-04").compare(&Value::Text("01/01/1990".to_string())),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Text("04/03/1995".to_string()).compare(&date("1995-03-04")),
+            Ordering::Equal
+        );
+        assert_eq!(
+            date("1995-03-04").compare(&Value::Text("soon".to_string())),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_compare_text() {
+        assert_eq!(compare_text("apple", "Banana"), Ordering::Less);
+        assert_eq!(compare_text("Apple", "apple"), Ordering::Less);
+        assert_eq!(compare_text("apple", "apple"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(
+            Value::Integer(7)
+                .arithmetic('/', &Value::Integer(2))
+                .unwrap(),
+            Value::Integer(3)
+        );
+        assert_eq!(
+            Value::Text("2.5".to_string())
+                .arithmetic('*', &Value::Integer(4))
+                .unwrap(),
+            Value::Float(10.0)
+        );
+        assert_eq!(
+            Value::Null.arithmetic('+', &Value::Integer(1)).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            Value::Integer(i64::MAX)
+                .arithmetic('+', &Value::Integer(1))
+                .unwrap(),
+            Value::Float(i64::MAX as f64 + 1.0)
+        );
+        assert!(Value::Integer(1)
+            .arithmetic('%', &Value::Integer(0))
+            .is_err());
+        assert!(Value::Text("abc".to_string())
+            .arithmetic('-', &Value::Integer(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Value::Float(3.0).to_string(), "3.0");
+        assert_eq!(Value::Float(0.1 + 0.2).to_string(), "0.30000000000000004");
+        assert_eq!(
+            Value::Float(1.2345678901234567).to_string(),
+            "1.2345678901234567"
+        );
+        assert_eq!(Value::Float(-2.5e-3).to_string(), "-0.0025");
+        assert_eq!(Value::Null.to_string(), "");
+        assert_eq!(Value::Integer(-4).to_string(), "-4");
+    }
+}
@@ -0,0 +1,280 @@
+use super::custom_error::CustomError;
+use super::row_parser::{format_field, parse_fields, read_record};
+use super::statement::Statement;
+use super::storage::{remove_file, rename_file, replace_file, sync_directory};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Write};
+
+/// El estado en que estaba un archivo antes de aplicar un Statement, suficiente para volver a él.
+#[derive(Debug, PartialEq)]
+enum FileState {
+    /// El archivo no existía.
+    Missing,
+    /// El Statement solo agrega registros al final del archivo, que tenía este largo en bytes.
+    Append(u64),
+    /// El archivo se conserva en una copia de respaldo, creada como un hard link.
+    Backup,
+}
+
+/// Retorna el nombre del archivo de log de una tabla.
+fn wal_file_name(log_name: &str) -> String {
+    format!(".{}.wal", log_name)
+}
+
+fn wal_path(directory: &str, log_name: &str) -> String {
+    format!("{}/{}", directory, wal_file_name(log_name))
+}
+
+/// Retorna el nombre del log con el que se registra un Statement: el de la primera de sus tablas en orden alfabético.
+/// Cada Statement que modifica tablas tiene un lock exclusivo sobre ellas, por lo que dos Statements en curso nunca
+/// comparten un log.
+pub fn log_name(statement: &Statement) -> Option<String> {
+    statement.table_names().into_iter().min()
+}
+
+/// Retorna los nombres de los logs que quedaron en un directorio sin confirmar.
+pub fn pending_logs(directory: &str) -> Result<Vec<String>, CustomError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(vec![]),
+    };
+    let mut log_names = vec![];
+    for entry in entries {
+        let file_name = entry.map_err(|_| wal_error())?.file_name();
+        let file_name = file_name.to_string_lossy();
+        if let Some(log_name) = file_name
+            .strip_prefix('.')
+            .and_then(|name| name.strip_suffix(".wal"))
+        {
+            log_names.push(log_name.to_string());
+        }
+    }
+    Ok(log_names)
+}
+
+fn backup_path(directory: &str, file_name: &str) -> String {
+    format!("{}/.{}.backup", directory, file_name)
+}
+
+fn wal_error() -> CustomError {
+    CustomError::GenericError {
+        message: "Couldn't access write-ahead log".to_string(),
+    }
+}
+
+/// Retorna los archivos que puede modificar un Statement, junto a si solo les agrega registros al final.
+fn affected_files(statement: &Statement) -> Vec<(String, bool)> {
+    let mut files = vec![];
+    for table_name in statement.table_names() {
+        if let Statement::Insert { .. } = statement {
+            files.push((format!("{}.csv", table_name), true));
+        } else {
+            files.push((format!("{}.csv", table_name), false));
+            files.push((format!("{}.schema", table_name), false));
+        }
+    }
+    files
+}
+
+/// Registra en el log un Statement que modifica tablas, antes de aplicarlo.
+///
+/// El log es un archivo CSV .<log_name>.wal con un primer registro STATEMENT,<comando>,<tablas...> y un registro FILE por cada
+/// archivo que el Statement puede modificar, con su estado previo: FILE,<archivo>,MISSING si no existía,
+/// FILE,<archivo>,APPEND,<largo> si solo se le agregan registros, o FILE,<archivo>,BACKUP si se respaldó.
+/// El log se escribe de forma atómica y se sincroniza con el disco antes de crear los respaldos,
+/// que son hard links al archivo original, por lo que no copian su contenido.
+pub fn log_statement(
+    directory: &str,
+    log_name: &str,
+    statement: &Statement,
+) -> Result<(), CustomError> {
+    let mut entries = vec![];
+    for (file_name, append_only) in affected_files(statement) {
+        let state = match fs::metadata(format!("{}/{}", directory, file_name)) {
+            Err(_) => FileState::Missing,
+            Ok(metadata) if append_only => FileState::Append(metadata.len()),
+            Ok(_) => FileState::Backup,
+        };
+        entries.push((file_name, state));
+    }
+    replace_file(directory, &wal_file_name(log_name), |writer| {
+        let mut header = vec!["STATEMENT".to_string(), statement.name().to_string()];
+        header.extend(statement.table_names());
+        write_record(writer, &header)?;
+        for (file_name, state) in &entries {
+            let mut record = vec!["FILE".to_string(), file_name.to_string()];
+            match state {
+                FileState::Missing => record.push("MISSING".to_string()),
+                FileState::Append(length) => {
+                    record.push("APPEND".to_string());
+                    record.push(length.to_string());
+                }
+                FileState::Backup => record.push("BACKUP".to_string()),
+            }
+            write_record(writer, &record)?;
+        }
+        Ok(())
+    })?;
+    for (file_name, state) in &entries {
+        if let FileState::Backup = state {
+            let original = format!("{}/{}", directory, file_name);
+            let backup = backup_path(directory, file_name);
+            let _ = fs::remove_file(&backup); // un respaldo viejo de un corte anterior
+            if fs::hard_link(&original, &backup).is_err() && fs::copy(&original, &backup).is_err() {
+                return Err(wal_error());
+            }
+        }
+    }
+    sync_directory(directory)
+}
+
+fn write_record<W: Write>(writer: &mut W, fields: &[String]) -> Result<(), CustomError> {
+    let fields: Vec<String> = fields.iter().map(|field| format_field(field)).collect();
+    if writeln!(writer, "{}", fields.join(",")).is_err() {
+        return Err(wal_error());
+    }
+    Ok(())
+}
+
+/// Lee los archivos registrados en el log del directorio, con su estado previo.
+/// Si no hay log, retorna None.
+fn read_log(
+    directory: &str,
+    log_name: &str,
+) -> Result<Option<Vec<(String, FileState)>>, CustomError> {
+    let file = match File::open(wal_path(directory, log_name)) {
+        Ok(file) => file,
+        Err(_) => return Ok(None),
+    };
+    let mut reader = BufReader::new(file);
+    let mut entries = vec![];
+    while let Some(record) = read_record(&mut reader)? {
+        let fields = parse_fields(&record)?;
+        let fields: Vec<&str> = fields.iter().map(|field| field.as_str()).collect();
+        let state = match fields.as_slice() {
+            ["STATEMENT", ..] => continue,
+            ["FILE", _, "MISSING"] => FileState::Missing,
+            ["FILE", _, "BACKUP"] => FileState::Backup,
+            ["FILE", _, "APPEND", length] => match length.parse() {
+                Ok(length) => FileState::Append(length),
+                Err(_) => return Err(wal_error()),
+            },
+            _ => return Err(wal_error()),
+        };
+        entries.push((fields[1].to_string(), state));
+    }
+    Ok(Some(entries))
+}
+
+/// Confirma el Statement registrado en el log, una vez aplicado por completo.
+/// Borrar el log es lo que lo confirma, por lo que se borra antes que los respaldos.
+pub fn checkpoint(directory: &str, log_name: &str) -> Result<(), CustomError> {
+    let entries = read_log(directory, log_name)?.unwrap_or_default();
+    remove_file(&wal_path(directory, log_name))?;
+    sync_directory(directory)?;
+    for (file_name, state) in entries {
+        if let FileState::Backup = state {
+            let _ = fs::remove_file(backup_path(directory, &file_name));
+        }
+    }
+    Ok(())
+}
+
+/// Deshace el Statement registrado en el log, si quedó uno sin confirmar por un error o un corte.
+/// Cada archivo vuelve a su estado previo: se elimina si no existía, se trunca a su largo previo si solo se le
+/// agregaron registros, o se reemplaza por su respaldo. Puede volver a ejecutarse si se interrumpe.
+pub fn recover(directory: &str, log_name: &str) -> Result<(), CustomError> {
+    let entries = match read_log(directory, log_name)? {
+        Some(entries) => entries,
+        None => return Ok(()),
+    };
+    for (file_name, state) in &entries {
+        let file_path = format!("{}/{}", directory, file_name);
+        match state {
+            FileState::Missing => {
+                if fs::metadata(&file_path).is_ok() {
+                    remove_file(&file_path)?;
+                }
+            }
+            FileState::Append(length) => {
+                if let Ok(file) = OpenOptions::new().write(true).open(&file_path) {
+                    if file.set_len(*length).and_then(|_| file.sync_all()).is_err() {
+                        return Err(wal_error());
+                    }
+                }
+            }
+            FileState::Backup => {
+                // si no hay respaldo, el corte fue antes de crearlo y el archivo no se modificó
+                let backup = backup_path(directory, file_name);
+                if fs::metadata(&backup).is_ok() {
+                    rename_file(&backup, &file_path)?;
+                }
+            }
+        }
+    }
+    sync_directory(directory)?;
+    checkpoint(directory, log_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Expression;
+
+    #[test]
+    fn test_recover_append() {
+        let directory = "test_wal_append";
+        let table_path = format!("{}/items.csv", directory);
+        fs::create_dir_all(directory).unwrap();
+        fs::write(&table_path, "id\n1\n").unwrap();
+        let statement = Statement::Insert {
+            table_name: "items".to_string(),
+            columns: vec!["id".to_string()],
+            values: vec![],
+        };
+        log_statement(directory, "items", &statement).unwrap();
+        fs::write(&table_path, "id\n1\n2\n3").unwrap(); // simulo una escritura cortada
+        recover(directory, "items").unwrap();
+        assert_eq!(fs::read_to_string(&table_path).unwrap(), "id\n1\n");
+        assert!(pending_logs(directory).unwrap().is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_recover_rewrite_and_drop() {
+        let directory = "test_wal_rewrite";
+        let table_path = format!("{}/items.csv", directory);
+        fs::create_dir_all(directory).unwrap();
+        fs::write(&table_path, "id\n1\n").unwrap();
+        let statement = Statement::Delete {
+            table_name: "items".to_string(),
+            condition: Expression::True,
+        };
+        log_statement(directory, "items", &statement).unwrap();
+        fs::remove_file(&table_path).unwrap();
+        fs::write(format!("{}/items.schema", directory), "id INTEGER\n").unwrap();
+        recover(directory, "items").unwrap();
+        assert_eq!(fs::read_to_string(&table_path).unwrap(), "id\n1\n");
+        assert!(fs::metadata(format!("{}/items.schema", directory)).is_err());
+        assert_eq!(fs::read_dir(directory).unwrap().count(), 1);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_keeps_changes() {
+        let directory = "test_wal_checkpoint";
+        let table_path = format!("{}/items.csv", directory);
+        fs::create_dir_all(directory).unwrap();
+        fs::write(&table_path, "id\n1\n").unwrap();
+        let statement = Statement::DropTable {
+            table_name: "items".to_string(),
+            if_exists: false,
+        };
+        log_statement(directory, "items", &statement).unwrap();
+        fs::remove_file(&table_path).unwrap();
+        checkpoint(directory, "items").unwrap();
+        recover(directory, "items").unwrap();
+        assert_eq!(fs::read_dir(directory).unwrap().count(), 0);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,6 +1,25 @@
+use sql_rustico::command_parser::parse_statement;
 use sql_rustico::command_processer::process_command;
+use sql_rustico::custom_error::CustomError;
+use sql_rustico::database::Database;
+use sql_rustico::lock::lock_table;
+use sql_rustico::output_format::OutputFormat;
+use sql_rustico::tokenizer::tokenize;
+use sql_rustico::value::Value;
 use std::fs::File;
 use std::io::Write;
+use std::time::Duration;
+
+/// Retorna los nombres de los archivos de un directorio, sin los archivos de lock.
+fn data_files(directory: &str) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .filter(|file_name| !file_name.ends_with(".lock"))
+        .collect();
+    files.sort();
+    files
+}
 
 #[test]
 fn test_process_command_with_invalid_directory() {
@@ -79,6 +98,29 @@ fn test_process_command_with_insert_columns_with_spaces() {
     std::fs::remove_dir(table_dir).expect("Error deleting directory");
 }
 
+#[test]
+fn test_process_command_with_select_columns_with_spaces() {
+    let table_dir = "test_table_select_columns_with_spaces/";
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    std::fs::write(
+        format!("{}table_with_spaces.csv", table_dir),
+        "id,First Name\n1,Ada\n2,Grace\n",
+    )
+    .expect("Error writing to temp file");
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        "SELECT 'First Name', id FROM table_with_spaces ORDER BY 'First Name' DESC;".to_string(),
+    ];
+    let mut output = vec![];
+    process_command(&args, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "First Name,id\nGrace,2\nAda,1\n"
+    );
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
 #[test]
 fn test_process_command_with_update() {
     let table_dir = "test_table_update/";
@@ -159,3 +201,1261 @@ fn test_process_command_with_select() {
     std::fs::remove_file(file_path).expect("Error deleting file");
     std::fs::remove_dir(table_dir).expect("Error deleting directory");
 }
+
+#[test]
+fn test_process_command_with_quoted_csv_values() {
+    let table_dir = "test_table_quoted_values/";
+    let table_name = "tempProcessQuotedValues";
+    let file_path = format!("{}{}.csv", table_dir, table_name);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let mut file = File::create(&file_path).expect("Error creating temp file");
+    writeln!(file, "id,supplier").expect("Error writing to temp file");
+    writeln!(file, "1,\"Garcia's Bakery, Ltd.\"").expect("Error writing to temp file");
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!(
+            "INSERT INTO {} (id, supplier) VALUES ('2', 'Two\nLines, Inc.');",
+            table_name
+        ),
+    ];
+    let mut unused_output = vec![];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!("DELETE FROM {} WHERE id = 1;", table_name),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    let contents = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(contents, "id,supplier\n2,\"Two\nLines, Inc.\"\n");
+    std::fs::remove_file(file_path).expect("Error deleting file");
+    std::fs::remove_dir(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_with_select_ordered_numerically() {
+    let table_dir = "test_table_select_ordered/";
+    let table_name = "tempProcessSelectOrdered";
+    let file_path = format!("{}{}.csv", table_dir, table_name);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let mut file = File::create(&file_path).expect("Error creating temp file");
+    writeln!(file, "name,age").expect("Error writing to temp file");
+    writeln!(file, "juan,100").expect("Error writing to temp file");
+    writeln!(file, "maria,28").expect("Error writing to temp file");
+    writeln!(file, "Ana,28").expect("Error writing to temp file");
+    writeln!(file, "carlos,2.5").expect("Error writing to temp file");
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!("SELECT * FROM {} ORDER BY age, name DESC;", table_name),
+    ];
+    let mut output = vec![];
+    let result = process_command(&args, &mut output);
+    assert!(result.is_ok());
+    let output_as_str = String::from_utf8(output).unwrap();
+    assert_eq!(
+        output_as_str,
+        "name,age\ncarlos,2.5\nmaria,28\nAna,28\njuan,100\n"
+    );
+    std::fs::remove_file(file_path).expect("Error deleting file");
+    std::fs::remove_dir(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_with_create_and_drop_table() {
+    let table_dir = "test_table_create_drop/";
+    let table_name = "tempProcessCreateDrop";
+    let file_path = format!("{}{}.csv", table_dir, table_name);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!(
+            "CREATE TABLE {} (id, 'full name', 'city, country');",
+            table_name
+        ),
+    ];
+    let mut unused_output = vec![];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    let contents = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(contents, "id,full name,\"city, country\"\n");
+    assert!(process_command(&args, &mut unused_output).is_err());
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!("CREATE TABLE IF NOT EXISTS {} (other);", table_name),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!("DROP TABLE {};", table_name),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    assert!(std::fs::metadata(&file_path).is_err());
+    assert!(std::fs::metadata(format!("{}.{}.lock", table_dir, table_name)).is_err());
+    assert!(process_command(&args, &mut unused_output).is_err());
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!("DROP TABLE IF EXISTS {};", table_name),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    assert!(std::fs::metadata(format!("{}.{}.lock", table_dir, table_name)).is_err());
+    std::fs::remove_dir(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_with_alter_table() {
+    let table_dir = "test_table_alter/";
+    let table_name = "tempProcessAlter";
+    let file_path = format!("{}{}.csv", table_dir, table_name);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let mut file = File::create(&file_path).expect("Error creating temp file");
+    writeln!(file, "column1,column2").expect("Error writing to temp file");
+    writeln!(file, "value1,value2").expect("Error writing to temp file");
+    let commands = [
+        format!(
+            "ALTER TABLE {} ADD COLUMN column3 DEFAULT 'a, b';",
+            table_name
+        ),
+        format!("ALTER TABLE {} DROP COLUMN column1;", table_name),
+        format!(
+            "ALTER TABLE {} RENAME COLUMN column2 TO renamed;",
+            table_name
+        ),
+    ];
+    let mut unused_output = vec![];
+    for command in commands {
+        let args = vec!["sql".to_string(), table_dir.to_string(), command];
+        assert!(process_command(&args, &mut unused_output).is_ok());
+    }
+    let contents = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(contents, "renamed,column3\nvalue2,\"a, b\"\n");
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!(
+            "ALTER TABLE {} RENAME TO tempProcessAlterRenamed;",
+            table_name
+        ),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    assert!(std::fs::metadata(&file_path).is_err());
+    assert!(std::fs::metadata(format!("{}.{}.lock", table_dir, table_name)).is_err());
+    let renamed_path = format!("{}tempProcessAlterRenamed.csv", table_dir);
+    assert!(std::fs::metadata(&renamed_path).is_ok());
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_with_schema_validation() {
+    let table_dir = "test_table_schema/";
+    let table_name = "tempProcessSchema";
+    let file_path = format!("{}{}.csv", table_dir, table_name);
+    let schema_path = format!("{}{}.schema", table_dir, table_name);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let mut unused_output = vec![];
+    let commands = [
+        format!(
+            "CREATE TABLE {} (id INTEGER NOT NULL, price REAL, stock INTEGER DEFAULT 0);",
+            table_name
+        ),
+        format!("INSERT INTO {} (id, price) VALUES (1, '2.50');", table_name),
+        format!("UPDATE {} SET stock = 5 WHERE id = 1;", table_name),
+    ];
+    for command in commands {
+        let args = vec!["sql".to_string(), table_dir.to_string(), command];
+        assert!(process_command(&args, &mut unused_output).is_ok());
+    }
+    let invalid_commands = [
+        format!("INSERT INTO {} (id, price) VALUES (2, 'abc');", table_name),
+        format!("INSERT INTO {} (price) VALUES ('1.0');", table_name),
+        format!("UPDATE {} SET id = NULL;", table_name),
+    ];
+    for command in invalid_commands {
+        let args = vec!["sql".to_string(), table_dir.to_string(), command];
+        assert!(matches!(
+            process_command(&args, &mut unused_output),
+            Err(CustomError::InvalidColumn { .. })
+        ));
+    }
+    let contents = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(contents, "id,price,stock\n1,2.50,5\n");
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        format!("DROP TABLE {};", table_name),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_ok());
+    assert!(std::fs::metadata(&schema_path).is_err());
+    std::fs::remove_dir(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_with_unique_constraints() {
+    let table_dir = "test_table_unique/";
+    let table_name = "tempProcessUnique";
+    let file_path = format!("{}{}.csv", table_dir, table_name);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let mut unused_output = vec![];
+    let commands = [
+        format!(
+            "CREATE TABLE {} (id INTEGER PRIMARY KEY, code TEXT UNIQUE, name TEXT);",
+            table_name
+        ),
+        format!(
+            "INSERT INTO {} (id, code, name) VALUES (1, 'A', 'first'), (2, NULL, 'second'), (3, NULL, 'third');",
+            table_name
+        ),
+        format!("UPDATE {} SET code = 'B' WHERE id = 2;", table_name),
+        format!("UPDATE {} SET code = 'B' WHERE id = 2;", table_name),
+    ];
+    for command in commands {
+        let args = vec!["sql".to_string(), table_dir.to_string(), command];
+        assert!(process_command(&args, &mut unused_output).is_ok());
+    }
+    let invalid_commands = [
+        format!("INSERT INTO {} (id, code) VALUES (1, 'C');", table_name),
+        format!(
+            "INSERT INTO {} (id, code) VALUES (4, 'C'), (5, 'C');",
+            table_name
+        ),
+        format!("UPDATE {} SET code = 'A' WHERE id = 3;", table_name),
+        format!("UPDATE {} SET id = 7 WHERE id > 1;", table_name),
+        format!(
+            "ALTER TABLE {} ADD COLUMN tag UNIQUE DEFAULT 'x';",
+            table_name
+        ),
+    ];
+    for command in invalid_commands {
+        let args = vec!["sql".to_string(), table_dir.to_string(), command];
+        assert!(matches!(
+            process_command(&args, &mut unused_output),
+            Err(CustomError::ConstraintViolation { .. })
+        ));
+    }
+    let contents = std::fs::read_to_string(&file_path).unwrap();
+    assert_eq!(contents, "id,code,name\n1,A,first\n2,B,second\n3,,third\n");
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_unique_index_follows_table_changes() {
+    let table_dir = "test_unique_index/";
+    let table_path = format!("{}codes.csv", table_dir);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    let database = Database::open(table_dir).unwrap();
+    let is_duplicate = |command: &str| {
+        matches!(
+            database.execute_sql(command),
+            Err(CustomError::ConstraintViolation { .. })
+        )
+    };
+    database
+        .execute_sql("CREATE TABLE codes (id INTEGER PRIMARY KEY, code TEXT UNIQUE);")
+        .unwrap();
+    database
+        .execute_sql("INSERT INTO codes (id, code) VALUES (1, 'A'), (2, 'B');")
+        .unwrap();
+    assert_eq!(
+        data_files(table_dir),
+        vec!["codes.csv", "codes.index", "codes.schema"]
+    );
+    assert!(is_duplicate(
+        "INSERT INTO codes (id, code) VALUES (3, 'A');"
+    ));
+    // un UPDATE que no actualiza filas no repite el valor
+    assert_eq!(
+        database
+            .execute_sql("UPDATE codes SET code = 'A' WHERE id = 9;")
+            .unwrap(),
+        0
+    );
+    assert!(is_duplicate("UPDATE codes SET code = 'A' WHERE id = 2;"));
+    // al borrar la fila su valor vuelve a estar disponible, y el índice se actualiza en lugar de eliminarse
+    database
+        .execute_sql("DELETE FROM codes WHERE id = 1;")
+        .unwrap();
+    assert_eq!(
+        data_files(table_dir),
+        vec!["codes.csv", "codes.index", "codes.schema"]
+    );
+    database
+        .execute_sql("INSERT INTO codes (id, code) VALUES (1, 'C');")
+        .unwrap();
+    database
+        .execute_sql("UPDATE codes SET code = 'A' WHERE id = 2;")
+        .unwrap();
+    // una fila agregada sin pasar por la base hace que el índice se reconstruya
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&table_path)
+        .unwrap();
+    writeln!(file, "5,E").unwrap();
+    drop(file);
+    assert!(is_duplicate(
+        "INSERT INTO codes (id, code) VALUES (6, 'E');"
+    ));
+    database
+        .execute_sql("ALTER TABLE codes RENAME TO letters;")
+        .unwrap();
+    assert_eq!(
+        data_files(table_dir),
+        vec!["letters.csv", "letters.index", "letters.schema"]
+    );
+    assert!(is_duplicate(
+        "INSERT INTO letters (id, code) VALUES (7, 'C');"
+    ));
+    assert_eq!(
+        std::fs::read_to_string(format!("{}letters.csv", table_dir)).unwrap(),
+        "id,code\n2,A\n1,C\n5,E\n"
+    );
+    database.execute_sql("DROP TABLE letters;").unwrap();
+    // los valores se comparan según el tipo de la columna, tanto en el índice como en las filas nuevas
+    database
+        .execute_sql("CREATE TABLE prices (id INTEGER UNIQUE, price REAL UNIQUE);")
+        .unwrap();
+    database
+        .execute_sql("INSERT INTO prices (id, price) VALUES ('007', '2.50');")
+        .unwrap();
+    assert!(is_duplicate(
+        "INSERT INTO prices (id, price) VALUES (7, 1);"
+    ));
+    assert!(is_duplicate(
+        "INSERT INTO prices (id, price) VALUES (8, 2.5);"
+    ));
+    assert!(is_duplicate(
+        "INSERT INTO prices (id, price) VALUES (8, 3), (9, 3.0);"
+    ));
+    database.execute_sql("DROP TABLE prices;").unwrap();
+    assert!(data_files(table_dir).is_empty());
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_failed_update_keeps_table() {
+    let table_dir = "test_table_failed_update/";
+    let file_path = format!("{}tempProcessFailed.csv", table_dir);
+    let contents = "id,name\n1,first\n2,second,extra\n";
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    std::fs::write(&file_path, contents).expect("Error creating file");
+    let mut unused_output = vec![];
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        "UPDATE tempProcessFailed SET name = 'changed';".to_string(),
+    ];
+    assert!(process_command(&args, &mut unused_output).is_err());
+    assert_eq!(std::fs::read_to_string(&file_path).unwrap(), contents);
+    // no queda ningún archivo temporal en el directorio
+    assert_eq!(data_files(table_dir), vec!["tempProcessFailed.csv"]);
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_database_with_transaction() {
+    let table_dir = "test_table_transaction/";
+    let books_path = format!("{}tempBooks.csv", table_dir);
+    let stock_path = format!("{}tempStock.csv", table_dir);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    std::fs::write(&books_path, "id,title\n1,Dune\n").expect("Error creating file");
+    std::fs::write(&stock_path, "id,stock\n1,5\n").expect("Error creating file");
+    let database = Database::open(table_dir).unwrap();
+    let commands = [
+        "BEGIN TRANSACTION;",
+        "INSERT INTO tempBooks (id, title) VALUES (2, 'Emma');",
+        "UPDATE tempStock SET stock = 4 WHERE id = 1;",
+        "CREATE TABLE tempLog (message);",
+    ];
+    for command in commands {
+        assert!(database.execute_sql(command).is_ok());
+    }
+    // hasta el COMMIT las tablas originales no cambian
+    assert_eq!(
+        std::fs::read_to_string(&books_path).unwrap(),
+        "id,title\n1,Dune\n"
+    );
+    assert!(std::fs::metadata(format!("{}tempLog.csv", table_dir)).is_err());
+    assert!(database.execute_sql("COMMIT;").is_ok());
+    assert_eq!(
+        std::fs::read_to_string(&books_path).unwrap(),
+        "id,title\n1,Dune\n2,Emma\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&stock_path).unwrap(),
+        "id,stock\n1,4\n"
+    );
+    assert!(std::fs::metadata(format!("{}tempLog.csv", table_dir)).is_ok());
+
+    let commands = ["BEGIN;", "DROP TABLE tempLog;", "ROLLBACK;"];
+    for command in commands {
+        assert!(database.execute_sql(command).is_ok());
+    }
+    assert!(std::fs::metadata(format!("{}tempLog.csv", table_dir)).is_ok());
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_transaction_belongs_to_its_session() {
+    let table_dir = "test_table_transaction_sessions";
+    let books_path = format!("{}/tempBooks.csv", table_dir);
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    std::fs::write(&books_path, "id,title\n1,Dune\n").expect("Error creating file");
+    std::fs::write(format!("{}/tempStock.csv", table_dir), "id,stock\n1,5\n")
+        .expect("Error creating file");
+    let owner = Database::open_with_lock_timeout(table_dir, Duration::ZERO).unwrap();
+    let other = Database::open_with_lock_timeout(table_dir, Duration::ZERO).unwrap();
+    owner.execute_sql("BEGIN;").unwrap();
+    owner
+        .execute_sql("INSERT INTO tempBooks (id, title) VALUES (2, 'Emma');")
+        .unwrap();
+
+    // la otra sesión no ve la transacción: no puede empezar otra ni usar las tablas que la transacción modificó
+    assert!(!other.in_transaction());
+    assert!(matches!(
+        other.execute_sql("BEGIN;"),
+        Err(CustomError::TableLocked { .. })
+    ));
+    assert!(matches!(
+        other.execute_sql("SELECT * FROM tempBooks;"),
+        Err(CustomError::TableLocked { .. })
+    ));
+    assert!(matches!(
+        other.execute_sql("DELETE FROM tempBooks;"),
+        Err(CustomError::TableLocked { .. })
+    ));
+    // las demás tablas se usan fuera de la transacción
+    assert_eq!(
+        other
+            .execute_sql("UPDATE tempStock SET stock = 4 WHERE id = 1;")
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        owner.query("SELECT stock FROM tempStock;").unwrap().next(),
+        Some(Ok(vec![Value::Integer(4)]))
+    );
+
+    owner.execute_sql("COMMIT;").unwrap();
+    assert_eq!(other.query("SELECT * FROM tempBooks;").unwrap().count(), 2);
+
+    // una transacción cuya sesión terminó sin confirmarla se descarta
+    owner.execute_sql("BEGIN;").unwrap();
+    owner.execute_sql("DELETE FROM tempBooks;").unwrap();
+    drop(owner);
+    other.execute_sql("BEGIN;").unwrap();
+    assert_eq!(other.query("SELECT * FROM tempBooks;").unwrap().count(), 2);
+    other.execute_sql("ROLLBACK;").unwrap();
+    assert_eq!(
+        data_files(table_dir),
+        vec!["tempBooks.csv", "tempStock.csv"]
+    );
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_process_command_recovers_interrupted_insert() {
+    let table_dir = "test_table_recovery/";
+    let file_path = format!("{}tempRecovery.csv", table_dir);
+    let contents = "id,name\n1,first\n";
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    // simulo un INSERT que se cortó a mitad de una fila, luego de registrarse en el log
+    std::fs::write(&file_path, format!("{}2,sec", contents)).expect("Error creating file");
+    std::fs::write(
+        format!("{}.tempRecovery.wal", table_dir),
+        format!(
+            "STATEMENT,INSERT,tempRecovery\nFILE,tempRecovery.csv,APPEND,{}\n",
+            contents.len()
+        ),
+    )
+    .expect("Error creating log");
+    let mut output = vec![];
+    let args = vec![
+        "sql".to_string(),
+        table_dir.to_string(),
+        "SELECT * FROM tempRecovery;".to_string(),
+    ];
+    assert!(process_command(&args, &mut output).is_ok());
+    assert_eq!(String::from_utf8(output).unwrap(), contents);
+    assert_eq!(data_files(table_dir), vec!["tempRecovery.csv"]);
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_database_with_locked_table() {
+    let table_dir = "test_table_locked";
+    std::fs::create_dir_all(table_dir).expect("Error creating directory");
+    std::fs::write(format!("{}/tempLocked.csv", table_dir), "id\n1\n")
+        .expect("Error creating file");
+    let database = Database::open_with_lock_timeout(table_dir, Duration::ZERO).unwrap();
+    let select = || parse_statement(&tokenize("SELECT * FROM tempLocked;").unwrap()).unwrap();
+    let delete = || parse_statement(&tokenize("DELETE FROM tempLocked;").unwrap()).unwrap();
+    let mut unused_output = vec![];
+
+    // otro proceso está leyendo la tabla: se puede leer pero no modificar
+    let reader_lock = lock_table(table_dir, "tempLocked", false, Duration::ZERO).unwrap();
+    assert!(database.execute(select(), &mut unused_output).is_ok());
+    assert!(matches!(
+        database.execute(delete(), &mut unused_output),
+        Err(CustomError::TableLocked { .. })
+    ));
+    drop(reader_lock);
+
+    // otro proceso está modificando la tabla: no se puede ni leer
+    let writer_lock = lock_table(table_dir, "tempLocked", true, Duration::ZERO).unwrap();
+    assert!(matches!(
+        database.execute(select(), &mut unused_output),
+        Err(CustomError::TableLocked { .. })
+    ));
+    drop(writer_lock);
+
+    assert!(database.execute(delete(), &mut unused_output).is_ok());
+    std::fs::remove_dir_all(table_dir).expect("Error deleting directory");
+}
+
+#[test]
+fn test_binary_reports_errors_on_stderr_with_exit_code() {
+    let binary = env!("CARGO_BIN_EXE_sql_rustico");
+    let output = std::process::Command::new(binary)
+        .args(["tables/", "SELECT * FROM supermarket WHERE;"])
+        .output()
+        .expect("Error running binary");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("INVALID_SYNTAX: "));
+
+    let output = std::process::Command::new(binary)
+        .output()
+        .expect("Error running binary");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage:"));
+}
+
+#[test]
+fn test_select_output_formats() {
+    let directory = "test_output_formats";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,name\n2,\"Garcia, Ltd.\"\n1,plain\n",
+    )
+    .unwrap();
+    let mut database = Database::open(directory).unwrap();
+    let select = "SELECT id, name FROM items ORDER BY id;";
+
+    let mut output = vec![];
+    let statement = parse_statement(&tokenize(select).unwrap()).unwrap();
+    database.execute(statement, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "id,name\n1,plain\n2,\"Garcia, Ltd.\"\n"
+    );
+
+    database.set_output_format(OutputFormat::Ndjson);
+    let mut output = vec![];
+    let statement = parse_statement(&tokenize(select).unwrap()).unwrap();
+    database.execute(statement, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\"id\":1,\"name\":\"plain\"}\n{\"id\":2,\"name\":\"Garcia, Ltd.\"}\n"
+    );
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_sql_rustico"))
+        .args([directory, "SELECT name FROM items;", "--format", "markdown"])
+        .output()
+        .expect("Error running binary");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "| name |\n| --- |\n| Garcia, Ltd. |\n| plain |\n"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_database_query_and_affected_rows() {
+    let directory = "test_database_query";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(format!("{}/items.csv", directory), "id,code,price\n").unwrap();
+    std::fs::write(
+        format!("{}/items.schema", directory),
+        "id INTEGER NOT NULL PRIMARY KEY\ncode TEXT\nprice REAL\n",
+    )
+    .unwrap();
+    let database = Database::open(directory).unwrap();
+    assert_eq!(
+        database
+            .execute_sql("INSERT INTO items (id, code, price) VALUES (1, '007', 2), (2, 'B', 3.5);")
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        database
+            .execute_sql("UPDATE items SET price = 4 WHERE id > 5;")
+            .unwrap(),
+        0
+    );
+
+    let rows = database
+        .query("SELECT id, code, price FROM items ORDER BY id;")
+        .unwrap();
+    let columns: Vec<String> = rows
+        .columns()
+        .iter()
+        .map(|column| column.name.to_string())
+        .collect();
+    assert_eq!(columns, vec!["id", "code", "price"]);
+    assert_eq!(rows.column_index("price"), Some(2));
+    assert_eq!(
+        rows.collect::<Result<Vec<Vec<Value>>, CustomError>>()
+            .unwrap(),
+        vec![
+            vec![
+                Value::Integer(1),
+                Value::Text("007".to_string()),
+                Value::Float(2.0)
+            ],
+            vec![
+                Value::Integer(2),
+                Value::Text("B".to_string()),
+                Value::Float(3.5)
+            ],
+        ]
+    );
+    // las columnas calculadas o con alias conservan el tipo y la precisión de sus valores
+    assert_eq!(
+        database
+            .query("SELECT code AS label, price / 3 AS third FROM items WHERE id = 1;")
+            .unwrap()
+            .collect::<Result<Vec<Vec<Value>>, CustomError>>()
+            .unwrap(),
+        vec![vec![
+            Value::Text("007".to_string()),
+            Value::Float(2.0 / 3.0)
+        ]]
+    );
+    assert_eq!(
+        database.query("DELETE FROM items;").err().unwrap(),
+        CustomError::InvalidSyntax {
+            message: "Only SELECT statements can be queried".to_string()
+        }
+    );
+    assert_eq!(
+        database
+            .execute_sql("DELETE FROM items WHERE id = 1;")
+            .unwrap(),
+        1
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_prepared_statements_bind_values_safely() {
+    let directory = "test_prepared_statements";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(format!("{}/items.csv", directory), "id,name\n1,first\n").unwrap();
+    let database = Database::open(directory).unwrap();
+
+    let mut insert = database
+        .prepare("INSERT INTO items (id, name) VALUES ($1, $2);")
+        .unwrap();
+    insert.bind(1, Value::Integer(2)).unwrap();
+    insert
+        .bind(2, Value::Text("x'); DELETE FROM items; --".to_string()))
+        .unwrap();
+    assert_eq!(database.execute_prepared(&insert, &mut vec![]).unwrap(), 1);
+    insert.clear_bindings();
+    assert_eq!(
+        database.execute_prepared(&insert, &mut vec![]),
+        Err(CustomError::InvalidSyntax {
+            message: "No value bound for parameter $1".to_string()
+        })
+    );
+
+    let mut select = database
+        .prepare("SELECT id FROM items WHERE name = :name;")
+        .unwrap();
+    select
+        .bind_named("name", Value::Text("first' OR 'a' = 'a".to_string()))
+        .unwrap();
+    assert_eq!(database.query_prepared(&select).unwrap().count(), 0);
+    select
+        .bind_named(
+            "name",
+            Value::Text("x'); DELETE FROM items; --".to_string()),
+        )
+        .unwrap();
+    assert_eq!(
+        database
+            .query_prepared(&select)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap(),
+        vec![vec![Value::Integer(2)]]
+    );
+
+    let statement = parse_statement(&tokenize("DELETE FROM items WHERE id = ?;").unwrap()).unwrap();
+    assert!(database.execute(statement, &mut vec![]).is_err());
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/items.csv", directory)).unwrap(),
+        "id,name\n1,first\n2,x'); DELETE FROM items; --\n"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_query_streams_rows() {
+    let directory = "test_query_streams_rows";
+    std::fs::create_dir_all(directory).unwrap();
+    let mut contents = "id,name\n".to_string();
+    for id in 1..=1000 {
+        contents.push_str(&format!("{},item{}\n", id, id));
+    }
+    contents.push_str("1001,broken,extra\n");
+    std::fs::write(format!("{}/items.csv", directory), contents).unwrap();
+    let database = Database::open(directory).unwrap();
+    let other = Database::open_with_lock_timeout(directory, Duration::ZERO).unwrap();
+
+    let mut rows = database.query("SELECT name FROM items;").unwrap();
+    assert_eq!(
+        rows.next(),
+        Some(Ok(vec![Value::Text("item1".to_string())]))
+    );
+    // mientras se leen las filas la tabla no puede modificarse
+    assert!(matches!(
+        other.execute_sql("DELETE FROM items WHERE id = 1;"),
+        Err(CustomError::TableLocked { .. })
+    ));
+    assert_eq!(
+        rows.by_ref().take(999).filter(|row| row.is_ok()).count(),
+        999
+    );
+    // el error de una fila llega al leerla, y termina el resultado
+    assert!(matches!(rows.next(), Some(Err(_))));
+    assert_eq!(rows.next(), None);
+
+    // al descartar las filas sin terminar de leerlas se liberan los locks
+    let mut rows = database
+        .query("SELECT id FROM items WHERE id < 10;")
+        .unwrap();
+    assert_eq!(rows.next(), Some(Ok(vec![Value::Integer(1)])));
+    drop(rows);
+    assert_eq!(
+        other
+            .execute_sql("INSERT INTO items (id, name) VALUES (1002, 'new');")
+            .unwrap(),
+        1
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_escaped_string_literals() {
+    let directory = "test_escaped_strings";
+    std::fs::create_dir_all(directory).unwrap();
+    let database = Database::open(directory).unwrap();
+    database
+        .execute_sql("CREATE TABLE people (name TEXT DEFAULT 'O''Brien', note TEXT);")
+        .unwrap();
+    database
+        .execute_sql("INSERT INTO people (name, note) VALUES ('Garcia''s Bakery', E'line\\none');")
+        .unwrap();
+    database
+        .execute_sql("INSERT INTO people (note) VALUES ('default');")
+        .unwrap();
+    let names: Vec<Vec<Value>> = database
+        .query("SELECT name FROM people WHERE name = 'O''Brien' OR note = E'line\\none';")
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        names,
+        vec![
+            vec![Value::Text("Garcia's Bakery".to_string())],
+            vec![Value::Text("O'Brien".to_string())],
+        ]
+    );
+    assert_eq!(
+        database.execute_sql("INSERT INTO people (name) VALUES ('unterminated);"),
+        Err(CustomError::InvalidSyntax {
+            message: "Unterminated string literal".to_string()
+        })
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_decimal_and_negative_literals() {
+    let directory = "test_numeric_literals";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,price,delta\n1,1.25,-5\n2,2.50,0\n3,0.75,2\n",
+    )
+    .unwrap();
+    let mut output = vec![];
+    process_command(
+        &[
+            "".to_string(),
+            directory.to_string(),
+            "SELECT id FROM items WHERE price < 2.50 AND delta > -3.5e0;".to_string(),
+        ],
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "id\n3\n");
+    let database = Database::open(directory).unwrap();
+    database
+        .execute_sql("INSERT INTO items (id, price, delta) VALUES (4, 1e1, -1);")
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(format!("{}/items.csv", directory)).unwrap(),
+        "id,price,delta\n1,1.25,-5\n2,2.50,0\n3,0.75,2\n4,10.0,-1\n"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_aggregates_with_group_by_and_having() {
+    let directory = "test_aggregates";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,Category,Stock\n1,tools,5\n2,toys,3\n3,tools,10\n4,food,\n5,toys,1\n6,tools,0\n",
+    )
+    .unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(
+        select("SELECT Category, COUNT(*), SUM(Stock) FROM items WHERE id > 1 GROUP BY Category HAVING COUNT(Stock) > 0 ORDER BY COUNT(*) DESC, Category;"),
+        "Category,COUNT(*),SUM(Stock)\ntools,2,10\ntoys,2,4\n"
+    );
+    assert_eq!(
+        select("SELECT COUNT(*), AVG(Stock), MIN(Stock), MAX(Stock) FROM items;"),
+        "COUNT(*),AVG(Stock),MIN(Stock),MAX(Stock)\n6,3.8,0,10\n"
+    );
+    assert_eq!(
+        select("SELECT COUNT(*), SUM(Stock) FROM items WHERE id > 10;"),
+        "COUNT(*),SUM(Stock)\n0,\n"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_declared_column_types_in_queries() {
+    let directory = "test_declared_types";
+    std::fs::create_dir_all(directory).unwrap();
+    let database = Database::open(directory).unwrap();
+    for command in [
+        "CREATE TABLE offices (zip TEXT UNIQUE, city TEXT);",
+        "INSERT INTO offices (zip, city) VALUES ('007', 'Bond'), ('7', 'Siete'), ('10', 'Diez');",
+        "CREATE TABLE visits (zip TEXT, guest TEXT);",
+        "INSERT INTO visits (zip, guest) VALUES ('7', 'Ana');",
+    ] {
+        database.execute_sql(command).unwrap();
+    }
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    // una columna TEXT compara su texto, sin interpretarlo como número
+    assert_eq!(
+        select("SELECT city FROM offices WHERE zip = '7';").unwrap(),
+        "city\nSiete\n"
+    );
+    assert_eq!(
+        select("SELECT zip FROM offices ORDER BY zip;").unwrap(),
+        "zip\n007\n10\n7\n"
+    );
+    assert_eq!(
+        select("SELECT o.city, v.guest FROM offices o JOIN visits v ON o.zip = v.zip;").unwrap(),
+        "o.city,v.guest\nSiete,Ana\n"
+    );
+    assert_eq!(
+        database
+            .execute_sql("DELETE FROM offices WHERE zip = '007';")
+            .unwrap(),
+        1
+    );
+    assert_eq!(select("SELECT zip FROM offices;").unwrap(), "zip\n7\n10\n");
+    drop(database);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_date_columns_in_queries() {
+    let directory = "test_date_columns";
+    std::fs::create_dir_all(directory).unwrap();
+    let database = Database::open(directory).unwrap();
+    for command in [
+        "CREATE TABLE people (name TEXT, Date_of_Birth DATE);",
+        "INSERT INTO people (name, Date_of_Birth) VALUES ('Ana', '15/06/1985'), ('Luis', '1992-01-20'), ('Eva', '02/11/1990');",
+        "UPDATE people SET Date_of_Birth = '01/02/1992' WHERE name = 'Luis';",
+    ] {
+        database.execute_sql(command).unwrap();
+    }
+    // las fechas se guardan con formato YYYY-MM-DD sin importar cómo se escribieron
+    let table_path = format!("{}/people.csv", directory);
+    assert_eq!(
+        std::fs::read_to_string(&table_path).unwrap(),
+        "name,Date_of_Birth\nAna,1985-06-15\nLuis,1992-02-01\nEva,1990-11-02\n"
+    );
+    // una fila escrita con formato DD/MM/YYYY también se interpreta como fecha
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&table_path)
+        .unwrap();
+    std::io::Write::write_all(&mut file, b"Juan,03/04/1989\n").unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(
+        select("SELECT name, Date_of_Birth FROM people ORDER BY Date_of_Birth;").unwrap(),
+        "name,Date_of_Birth\nAna,1985-06-15\nJuan,1989-04-03\nEva,1990-11-02\nLuis,1992-02-01\n"
+    );
+    assert_eq!(
+        select("SELECT name FROM people WHERE Date_of_Birth > '01/01/1990' ORDER BY name;")
+            .unwrap(),
+        "name\nEva\nLuis\n"
+    );
+    assert_eq!(
+        select("SELECT name FROM people WHERE Date_of_Birth = '1989-04-03';").unwrap(),
+        "name\nJuan\n"
+    );
+    assert_eq!(
+        select("SELECT MIN(Date_of_Birth), MAX(Date_of_Birth) FROM people;").unwrap(),
+        "MIN(Date_of_Birth),MAX(Date_of_Birth)\n1985-06-15,1992-02-01\n"
+    );
+    drop(database);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_select_with_joins() {
+    let directory = "test_joins";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/patients.csv", directory),
+        "id,name,doctor_id\n1,Ana,10\n2,Juan,20\n3,Sofia,\n4,Luis,10\n",
+    )
+    .unwrap();
+    std::fs::write(
+        format!("{}/doctors.csv", directory),
+        "id,name,floor\n10,House,2\n30,Grey,1\n",
+    )
+    .unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(
+        select("SELECT p.name, d.name FROM patients p JOIN doctors AS d ON p.doctor_id = d.id;")
+            .unwrap(),
+        "p.name,d.name\nAna,House\nLuis,House\n"
+    );
+    assert_eq!(
+        select("SELECT p.name, floor FROM patients p LEFT JOIN doctors d ON d.id = p.doctor_id ORDER BY p.name;")
+            .unwrap(),
+        "p.name,floor\nAna,2\nJuan,\nLuis,2\nSofia,\n"
+    );
+    assert_eq!(
+        select("SELECT d.name, p.id FROM patients p RIGHT JOIN doctors d ON p.doctor_id = d.id;")
+            .unwrap(),
+        "d.name,p.id\nHouse,1\nHouse,4\nGrey,\n"
+    );
+    assert_eq!(
+        select(
+            "SELECT p.id, d.id FROM patients p JOIN doctors d ON p.id < d.floor WHERE d.floor > 1;"
+        )
+        .unwrap(),
+        "p.id,d.id\n1,10\n"
+    );
+    assert_eq!(
+        select("SELECT COUNT(*) FROM patients CROSS JOIN doctors;").unwrap(),
+        "COUNT(*)\n8\n"
+    );
+    assert_eq!(
+        select("SELECT name FROM patients JOIN doctors ON doctor_id = doctors.id;"),
+        Err(CustomError::InvalidColumn {
+            message: "Column name is ambiguous".to_string()
+        })
+    );
+    let database = Database::open(directory).unwrap();
+    let mut rows = database
+        .query("SELECT p.name, floor FROM patients p JOIN doctors d ON p.doctor_id = d.id;")
+        .unwrap();
+    assert_eq!(rows.column_index("floor"), Some(1));
+    assert_eq!(
+        rows.next(),
+        Some(Ok(vec![Value::Text("Ana".to_string()), Value::Integer(2)]))
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_select_limit_and_offset() {
+    let directory = "test_limit_offset";
+    std::fs::create_dir_all(directory).unwrap();
+    // la última fila es inválida, pero un LIMIT sin ORDER BY termina de leer antes de llegar a ella
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,name,stock\n1,a,5\n2,b,3\n3,c,5\n4,d,1\n5,e,9\ninvalid\n",
+    )
+    .unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(
+        select("SELECT id FROM items WHERE stock > 2 LIMIT 2 OFFSET 1;").unwrap(),
+        "id\n2\n3\n"
+    );
+    assert_eq!(select("SELECT id FROM items LIMIT 0;").unwrap(), "id\n");
+    assert!(select("SELECT id FROM items LIMIT 10;").is_err());
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,name,stock\n1,a,5\n2,b,3\n3,c,5\n4,d,1\n5,e,9\n",
+    )
+    .unwrap();
+    assert_eq!(
+        select("SELECT id, stock FROM items ORDER BY stock DESC LIMIT 3;").unwrap(),
+        "id,stock\n5,9\n1,5\n3,5\n"
+    );
+    assert_eq!(
+        select("SELECT id FROM items ORDER BY stock LIMIT 2 OFFSET 2;").unwrap(),
+        "id\n1\n3\n"
+    );
+    assert_eq!(
+        select("SELECT stock, COUNT(*) FROM items GROUP BY stock ORDER BY stock LIMIT 1 OFFSET 2;")
+            .unwrap(),
+        "stock,COUNT(*)\n5,2\n"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_select_distinct() {
+    let directory = "test_select_distinct";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,Category,Brand\n1,food,a\n2,toys,b\n3,food,a\n4,food,c\n5,toys,b\n6,tools,\n",
+    )
+    .unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(
+        select("SELECT DISTINCT Category FROM items;").unwrap(),
+        "Category\nfood\ntoys\ntools\n"
+    );
+    assert_eq!(
+        select(
+            "SELECT DISTINCT Category, Brand FROM items WHERE id > 1 ORDER BY Brand DESC LIMIT 2;"
+        )
+        .unwrap(),
+        "Category,Brand\nfood,c\ntoys,b\n"
+    );
+    assert_eq!(
+        select("SELECT Category, COUNT(DISTINCT Brand), COUNT(Brand) FROM items GROUP BY Category ORDER BY COUNT(DISTINCT Brand) DESC, Category;")
+            .unwrap(),
+        "Category,COUNT(DISTINCT Brand),COUNT(Brand)\nfood,2,3\ntoys,1,2\ntools,0,0\n"
+    );
+    assert_eq!(
+        select("SELECT DISTINCT COUNT(*) FROM items GROUP BY Category HAVING COUNT(*) < 3;")
+            .unwrap(),
+        "COUNT(*)\n2\n1\n"
+    );
+    assert_eq!(
+        select("SELECT DISTINCT Category FROM items ORDER BY id;"),
+        Err(CustomError::InvalidSyntax {
+            message: "ORDER BY column id must be selected with DISTINCT".to_string()
+        })
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_select_computed_columns() {
+    let directory = "test_select_computed_columns";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,Name,Category,Price,Stock\n1,pen,office,1.50,10\n2,book,office,12,3\n3,ball,toys,4,\n",
+    )
+    .unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(
+        select("SELECT Name, Price * Stock AS inventory_value, LOWER('UNIT') AS kind FROM items WHERE Price - 1 > 0 ORDER BY inventory_value DESC;")
+            .unwrap(),
+        "Name,inventory_value,kind\nbook,36,unit\npen,15.0,unit\nball,,unit\n"
+    );
+    assert_eq!(
+        select("SELECT UPPER(Name), Price AS cost, COALESCE(Stock, 0) % 4 FROM items LIMIT 2;")
+            .unwrap(),
+        "UPPER(Name),cost,\"COALESCE(Stock, 0) % 4\"\nPEN,1.5,2\nBOOK,12,3\n"
+    );
+    assert_eq!(
+        select("SELECT Category, SUM(Stock) * 2 AS doubled FROM items GROUP BY Category ORDER BY doubled;")
+            .unwrap(),
+        "Category,doubled\ntoys,\noffice,26\n"
+    );
+    // un alias no reemplaza a la columna de la tabla con el mismo nombre, pero ORDER BY lo prefiere
+    assert_eq!(
+        select("SELECT Name AS Category, Category FROM items ORDER BY Category;").unwrap(),
+        "Category,Category\nball,toys\nbook,office\npen,office\n"
+    );
+    assert_eq!(
+        select("SELECT DISTINCT Category, Stock * 0 AS zero FROM items ORDER BY zero, Category;")
+            .unwrap(),
+        "Category,zero\ntoys,\noffice,0\n"
+    );
+    assert_eq!(
+        select("SELECT Price / 0 FROM items;"),
+        Err(CustomError::GenericError {
+            message: "Division by zero".to_string()
+        })
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_aggregate_expressions() {
+    let directory = "test_aggregate_expressions";
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(
+        format!("{}/items.csv", directory),
+        "id,Name,Category,Price,Stock\n1,chair,home,199.60,2\n2,lamp,home,0.10,3\n3,ball,toys,4,5\n4,kite,toys,2,10\n5,doll,toys,7,\n",
+    )
+    .unwrap();
+    let select = |command: &str| {
+        let mut output = vec![];
+        process_command(
+            &["".to_string(), directory.to_string(), command.to_string()],
+            &mut output,
+        )
+        .map(|_| String::from_utf8(output).unwrap())
+    };
+    assert_eq!(
+        select("SELECT Name, Price * Stock FROM items WHERE id = 1;").unwrap(),
+        "Name,Price * Stock\nchair,399.2\n"
+    );
+    assert_eq!(
+        select("SELECT Category, SUM(Price * Stock), COUNT(DISTINCT Price * Stock), MAX(Stock - 1) FROM items GROUP BY Category ORDER BY SUM(Price * Stock) DESC;")
+            .unwrap(),
+        "Category,SUM(Price * Stock),COUNT(DISTINCT Price * Stock),MAX(Stock - 1)\nhome,399.5,2,2\ntoys,40,1,9\n"
+    );
+    assert_eq!(
+        select("SELECT SUM(Price * Stock) FROM items WHERE Category = 'home';").unwrap(),
+        "SUM(Price * Stock)\n399.5\n"
+    );
+    // los paréntesis forman parte del nombre, para que dos expresiones distintas no se confundan
+    assert_eq!(
+        select("SELECT SUM((Price + 1) * Stock), SUM(Price + 1 * Stock) FROM items WHERE Category = 'toys';")
+            .unwrap(),
+        "SUM((Price + 1) * Stock),SUM(Price + 1 * Stock)\n55,21\n"
+    );
+    assert_eq!(
+        select("SELECT (Price + 1) * 2, Price + 1 * 2 FROM items WHERE id = 3;").unwrap(),
+        "(Price + 1) * 2,Price + 1 * 2\n10,6\n"
+    );
+    // el redondeo es solo para mostrar: los grupos conservan el valor completo
+    let rows = Database::open(directory)
+        .unwrap()
+        .query("SELECT Category, SUM(Price * Stock) FROM items WHERE id = 2 GROUP BY Category;")
+        .unwrap()
+        .collect::<Result<Vec<Vec<Value>>, CustomError>>()
+        .unwrap();
+    assert_eq!(
+        rows,
+        vec![vec![
+            Value::Text("home".to_string()),
+            Value::Float(0.1 * 3.0)
+        ]]
+    );
+    assert_eq!(
+        select("SELECT SUM(Price * Missing) FROM items;"),
+        Err(CustomError::GenericError {
+            message: "Column not found: Missing".to_string()
+        })
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_select_from_table_named_like_keyword() {
+    let mut output = vec![];
+    process_command(
+        &[
+            "".to_string(),
+            "tables/".to_string(),
+            "SELECT Nombre, Edad FROM table ORDER BY Edad LIMIT 2;".to_string(),
+        ],
+        &mut output,
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Nombre,Edad\nLucía,26\nMaria,28\n"
+    );
+}
+
+#[test]
+fn test_select_in_missing_directory_reports_missing_table() {
+    let mut output = vec![];
+    let error = process_command(
+        &[
+            "".to_string(),
+            "test_missing_directory".to_string(),
+            "SELECT * FROM items;".to_string(),
+        ],
+        &mut output,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        error,
+        CustomError::GenericError {
+            message: "Couldn't open table file".to_string()
+        }
+    );
+    assert!(std::fs::metadata("test_missing_directory").is_err());
+}